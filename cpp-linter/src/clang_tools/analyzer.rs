@@ -0,0 +1,668 @@
+//! This module holds the [`Analyzer`] trait which is implemented by each C/C++
+//! analysis tool that cpp-linter can use (including clang-format and clang-tidy).
+//!
+//! Each analyzer is registered in the [`AnalyzerRegistry`] by name. The enabled
+//! analyzers are run on every file, and the [`Advice`] they produce is reported
+//! through the same feedback channels (thread comments, step summary, file
+//! annotations, PR reviews, and check statistics).
+
+use std::{
+    fmt::Debug,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+
+use super::{
+    capture_clang_version, clang_format::ClangFormat, clang_tidy::ClangTidy, cppcheck::Cppcheck,
+    include_cleaner::IncludeCleaner, include_what_you_use::IncludeWhatYouUse, rules::RuleEngine,
+    static_analyzer::StaticAnalyzer, MakeSuggestions, ReviewComments,
+};
+use crate::{
+    cli::ClangParams,
+    common_fs::{FileFilter, FileObj},
+};
+
+/// A single diagnostic reported by an [`Analyzer`] about a file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnalyzerDiagnostic {
+    /// The line number from which the diagnostic originated.
+    pub line: u32,
+
+    /// The column number from which the diagnostic originated.
+    pub cols: u32,
+
+    /// The severity of the diagnostic (ie `error`, `warning`, `note`).
+    pub severity: String,
+
+    /// The name/id of the check (or rule) that caused the diagnostic.
+    pub check: String,
+
+    /// A helpful message explaining the diagnostic.
+    pub message: String,
+
+    /// The locations related to the diagnostic (ie the steps of a bug path).
+    pub related: Vec<RelatedLocation>,
+}
+
+impl AnalyzerDiagnostic {
+    /// Describe the [`AnalyzerDiagnostic::related`] locations as a numbered list.
+    ///
+    /// Each item is prefixed with the given `indent`.
+    pub fn describe_related(&self, indent: &str) -> String {
+        self.related_steps()
+            .iter()
+            .map(|step| format!("{indent}{step}\n"))
+            .collect()
+    }
+
+    /// Describe each of the [`AnalyzerDiagnostic::related`] locations as a numbered
+    /// step.
+    pub fn related_steps(&self) -> Vec<String> {
+        self.related
+            .iter()
+            .enumerate()
+            .map(|(index, step)| {
+                format!(
+                    "{}. {}:{}:{}: {}",
+                    index + 1,
+                    step.file,
+                    step.line,
+                    step.cols,
+                    step.message
+                )
+            })
+            .collect()
+    }
+}
+
+/// A location related to an [`AnalyzerDiagnostic`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelatedLocation {
+    /// The file's path (relative to the repository root, using posix path separators).
+    pub file: String,
+
+    /// The line number of the location.
+    pub line: u32,
+
+    /// The column number of the location.
+    pub cols: u32,
+
+    /// A message explaining the location's relevance.
+    pub message: String,
+}
+
+/// The advice that an [`Analyzer`] produced for a single file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnalyzerAdvice {
+    /// The name of the [`Analyzer`] that produced this advice.
+    pub tool: String,
+
+    /// The diagnostics about the file.
+    pub diagnostics: Vec<AnalyzerDiagnostic>,
+
+    /// The file's content with the analyzer's fixes applied (if any are supported).
+    pub patched: Option<Vec<u8>>,
+}
+
+impl MakeSuggestions for AnalyzerAdvice {
+    fn get_suggestion_help(&self, start_line: u32, end_line: u32) -> String {
+        let mut help = format!("### {} suggestions\n", self.tool);
+        for diagnostic in &self.diagnostics {
+            if (start_line..=end_line).contains(&diagnostic.line) {
+                help.push_str(&format!(
+                    "- {}: [{}]\n  > {}\n",
+                    diagnostic.severity, diagnostic.check, diagnostic.message
+                ));
+                help.push_str(&diagnostic.describe_related("  "));
+            }
+        }
+        help
+    }
+
+    fn get_tool_name(&self) -> String {
+        self.tool.clone()
+    }
+}
+
+impl Advice for AnalyzerAdvice {
+    fn patched(&self) -> Option<&[u8]> {
+        self.patched.as_deref()
+    }
+
+    fn concerns(&self, _file: &FileObj) -> u64 {
+        self.diagnostics.len() as u64
+    }
+
+    fn describe(&self, file: &FileObj, remaining_length: &mut u64) -> String {
+        let mut description = String::new();
+        for diagnostic in &self.diagnostics {
+            let note = format!(
+                "- <strong>{filename}:{line}:{cols}:</strong> {severity}: [{check}]\n   > {message}\n{steps}",
+                filename = file.name.to_string_lossy().replace('\\', "/"),
+                line = diagnostic.line,
+                cols = diagnostic.cols,
+                severity = diagnostic.severity,
+                check = diagnostic.check,
+                message = diagnostic.message,
+                steps = diagnostic.describe_related("   > "),
+            );
+            if (note.len() as u64) < *remaining_length {
+                description.push_str(&note);
+                *remaining_length -= note.len() as u64;
+            }
+        }
+        description
+    }
+
+    fn annotations(&self, _file: &FileObj) -> Vec<Annotation> {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| Annotation {
+                severity: diagnostic.severity.clone(),
+                line: diagnostic.line,
+                cols: diagnostic.cols,
+                title: format!("{} [{}]", self.tool, diagnostic.check),
+                message: [diagnostic.message.clone()]
+                    .into_iter()
+                    .chain(diagnostic.related_steps())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            })
+            .collect()
+    }
+
+    fn checks(&self, _file: &FileObj) -> Vec<(String, String)> {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let check = if diagnostic.check.is_empty() {
+                    &self.tool
+                } else {
+                    &diagnostic.check
+                };
+                (check.clone(), diagnostic.severity.clone())
+            })
+            .collect()
+    }
+
+    fn unfixed_concerns(
+        &self,
+        file: &FileObj,
+        review_comments: &ReviewComments,
+    ) -> Vec<(u32, String)> {
+        let file_name = file.name.to_string_lossy().replace('\\', "/");
+        // Diagnostics already explained in a suggestion (from the patch) are skipped.
+        let suggestion_help = format!("### {} suggestions", self.tool);
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| {
+                !review_comments.comments.iter().any(|s| {
+                    s.path == file_name
+                        && (s.line_start..=s.line_end).contains(&diagnostic.line)
+                        && s.suggestion.contains(&suggestion_help)
+                })
+            })
+            .map(|diagnostic| {
+                let suggestion = format!(
+                    "### {} diagnostic\n**{file_name}:{}:{}** {}: [{}]\n\n> {}\n",
+                    self.tool,
+                    diagnostic.line,
+                    diagnostic.cols,
+                    diagnostic.severity,
+                    diagnostic.check,
+                    diagnostic.message
+                );
+                (diagnostic.line, suggestion)
+            })
+            .collect()
+    }
+}
+
+/// A file annotation that describes a single concern.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotation {
+    /// The severity of the concern (ie `error`, `warning`, `note`).
+    pub severity: String,
+
+    /// The line number that the annotation points to.
+    pub line: u32,
+
+    /// The column number that the annotation points to.
+    pub cols: u32,
+
+    /// A short title for the annotation.
+    pub title: String,
+
+    /// The annotation's message (which may span multiple lines).
+    pub message: String,
+}
+
+/// The advice that a tool produced for a single file.
+///
+/// This describes the tool's concerns for every feedback channel, so feedback is
+/// created the same way for all tools.
+pub trait Advice: MakeSuggestions + Debug + Send + Sync {
+    /// The file's content with the tool's fixes applied (if any are supported).
+    fn patched(&self) -> Option<&[u8]>;
+
+    /// Count the concerns about the given `file`.
+    fn concerns(&self, file: &FileObj) -> u64;
+
+    /// Summarize the `total` count of the tool's concerns (about all files) in the
+    /// heading of a thread comment's section.
+    fn summarize(&self, total: u64) -> String {
+        format!("{total}<strong> concern(s)</strong>")
+    }
+
+    /// Describe the concerns about the given `file` as a markdown list.
+    ///
+    /// Only items that fit within the `remaining_length` are included, and their
+    /// length is deducted from it.
+    fn describe(&self, file: &FileObj, remaining_length: &mut u64) -> String;
+
+    /// Describe the concerns about the given `file` as file annotations.
+    fn annotations(&self, file: &FileObj) -> Vec<Annotation>;
+
+    /// Get the check name and severity of each concern about the given `file`.
+    ///
+    /// These are aggregated into [`CheckStats`](super::stats::CheckStats). Concerns
+    /// without a check name (ie unformatted code) are not included.
+    fn checks(&self, _file: &FileObj) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// Describe the concerns about the given `file` that the `review_comments` do not
+    /// already suggest a fix for.
+    ///
+    /// Returns the line number and description of each concern.
+    fn unfixed_concerns(
+        &self,
+        _file: &FileObj,
+        _review_comments: &ReviewComments,
+    ) -> Vec<(u32, String)> {
+        vec![]
+    }
+}
+
+/// The [`Advice`] that an [`Analyzer`] produced for a file and a list of cached log
+/// messages.
+pub type Analysis = (Box<dyn Advice>, Vec<(log::Level, String)>);
+
+/// An interface for C/C++ analysis tools.
+///
+/// Implementors are registered in the [`AnalyzerRegistry`].
+pub trait Analyzer: Debug + Send + Sync {
+    /// The name of the analyzer, as used with `--analyzers` and in feedback.
+    fn name(&self) -> &'static str;
+
+    /// Find the analyzer's executable.
+    ///
+    /// The `version` is the value of `--version` (see [`super::get_clang_tool_exe()`]).
+    /// Analyzers implemented in cpp-linter (that need no executable) return [`None`].
+    fn find_executable(&self, version: &str) -> Result<Option<PathBuf>>;
+
+    /// Get the version of the analyzer from the given `exe`cutable.
+    ///
+    /// By default, the version is parsed from the output of `exe --version`. Analyzers
+    /// that need no executable use cpp-linter's version.
+    fn version(&self, exe: Option<&Path>) -> Result<String> {
+        match exe {
+            Some(exe) => capture_clang_version(&exe.to_path_buf()),
+            None => Ok(env!("CARGO_PKG_VERSION").to_string()),
+        }
+    }
+
+    /// Prepare the analyzer (with its resolved `exe`cutable and `version`) before any
+    /// of the `files` are analyzed.
+    ///
+    /// Returns a list of cached log messages.
+    fn prepare(
+        &self,
+        _exe: Option<&Path>,
+        _version: &str,
+        _clang_params: &mut ClangParams,
+        _files: &[Arc<Mutex<FileObj>>],
+    ) -> Result<Vec<(log::Level, String)>> {
+        Ok(vec![])
+    }
+
+    /// Is the analyzer enabled by the given `clang_params` (as resolved for a file)?
+    fn is_enabled(&self, _clang_params: &ClangParams) -> bool {
+        true
+    }
+
+    /// Get the reason that the given `file` is not analyzed (if it is ignored).
+    ///
+    /// By default, files are ignored with `--ignore-tidy` (see
+    /// [`ClangParams::analyzer_filter`]).
+    fn ignored_because(&self, file: &Path, clang_params: &ClangParams) -> Option<String> {
+        is_filtered(clang_params.analyzer_filter.as_ref(), file)
+            .then(|| String::from("`--ignore-tidy`"))
+    }
+
+    /// Describe the configuration source that the analyzer uses for the given `file`
+    /// (if any is known).
+    ///
+    /// The `clang_params` are resolved for the `file`.
+    fn describe_config(
+        &self,
+        _file: &Path,
+        _root: &Path,
+        _clang_params: &ClangParams,
+    ) -> Option<String> {
+        None
+    }
+
+    /// Analyze the given `file`.
+    fn analyze(
+        &self,
+        exe: Option<&Path>,
+        file: &FileObj,
+        clang_params: &ClangParams,
+    ) -> Result<Analysis>;
+}
+
+/// Is the given `file` excluded by the given `filter` (if any)?
+pub fn is_filtered(filter: Option<&FileFilter>, file: &Path) -> bool {
+    filter.is_some_and(|f| !f.is_source_or_ignored(file))
+}
+
+/// An [`Analyzer`] with its executable resolved.
+#[derive(Debug, Clone)]
+pub struct ResolvedAnalyzer {
+    /// The analyzer.
+    pub analyzer: Arc<dyn Analyzer>,
+
+    /// The path to the analyzer's executable (if any).
+    pub exe: Option<PathBuf>,
+}
+
+impl ResolvedAnalyzer {
+    /// Analyze the given `file` and store the resulting advice in [`FileObj::advice`].
+    pub fn run(
+        &self,
+        file: &mut FileObj,
+        clang_params: &ClangParams,
+    ) -> Result<Vec<(log::Level, String)>> {
+        let (advice, logs) = self
+            .analyzer
+            .analyze(self.exe.as_deref(), file, clang_params)?;
+        file.advice.push(Arc::from(advice));
+        Ok(logs)
+    }
+}
+
+/// Get the version of an analyzer's `exe`cutable.
+///
+/// The version number is the first capture group of the given regular expression
+/// `pattern` when matched against the output of `exe --version`.
+pub fn capture_version(exe: &Path, pattern: &str) -> Result<String> {
+    let output = Command::new(exe)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("Failed to run {}", exe.to_string_lossy()))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version_pattern = Regex::new(pattern).unwrap();
+    let captures = version_pattern.captures(&stdout).ok_or(anyhow!(
+        "Failed to find version number in `{} --version` output",
+        exe.to_string_lossy()
+    ))?;
+    Ok(captures.get(1).unwrap().as_str().to_string())
+}
+
+/// Run the given `cmd` and capture its output.
+///
+/// If the command does not finish within the given `timeout`, then it is killed and
+/// [`None`] is returned.
+pub fn run_command(cmd: &mut Command, timeout: Option<Duration>) -> Result<Option<Output>> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let Some(timeout) = timeout else {
+        return cmd
+            .output()
+            .map(Some)
+            .with_context(|| format!("Failed to run {program}"));
+    };
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {program}"))?;
+    // read the pipes in separate threads, so the child never blocks on a full pipe
+    let read_pipe = |pipe: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut buf = vec![];
+            if let Some(mut pipe) = pipe {
+                pipe.read_to_end(&mut buf).ok();
+            }
+            buf
+        })
+    };
+    let stdout = read_pipe(
+        child
+            .stdout
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+    let stderr = read_pipe(
+        child
+            .stderr
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() >= timeout {
+            child.kill().ok();
+            child.wait().ok();
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
+}
+
+/// Create a log message about an analyzer (named `tool`) that exceeded the
+/// `--analyzer-timeout` while analyzing the given `file`.
+pub fn timeout_log(tool: &str, file: &Path, timeout: Option<Duration>) -> (log::Level, String) {
+    (
+        log::Level::Warn,
+        format!(
+            "{tool} was stopped after {}s while analyzing {} (see `--analyzer-timeout`)",
+            timeout.unwrap_or_default().as_secs(),
+            file.to_string_lossy()
+        ),
+    )
+}
+
+/// A collection of the [`Analyzer`]s known to cpp-linter.
+#[derive(Debug, Default)]
+pub struct AnalyzerRegistry {
+    analyzers: Vec<Arc<dyn Analyzer>>,
+}
+
+impl AnalyzerRegistry {
+    /// Create a registry of all analyzers that cpp-linter supports.
+    pub fn new() -> Self {
+        let mut registry = Self::default();
+        registry.register(Arc::new(ClangFormat));
+        registry.register(Arc::new(ClangTidy));
+        registry.register(Arc::new(Cppcheck));
+        registry.register(Arc::new(IncludeWhatYouUse));
+        registry.register(Arc::new(IncludeCleaner));
+        registry.register(Arc::new(RuleEngine));
+        registry.register(Arc::new(StaticAnalyzer));
+        registry
+    }
+
+    /// Add an `analyzer` to the registry.
+    pub fn register(&mut self, analyzer: Arc<dyn Analyzer>) {
+        self.analyzers.push(analyzer);
+    }
+
+    /// Get the names of all registered analyzers.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.analyzers.iter().map(|a| a.name()).collect()
+    }
+
+    /// Get the registered analyzer with the given `name`.
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Analyzer>> {
+        self.analyzers.iter().find(|a| a.name() == name).cloned()
+    }
+
+    /// Get the analyzers with the given `names` and resolve their executables.
+    ///
+    /// Returns the resolved analyzers and their versions (in the given order).
+    pub fn resolve(
+        &self,
+        names: &[String],
+        version: &str,
+    ) -> Result<Vec<(ResolvedAnalyzer, String)>> {
+        let mut resolved = vec![];
+        for name in names {
+            let analyzer = self.get(name).ok_or(anyhow!(
+                "Unknown analyzer '{name}'. Supported analyzers are: {}",
+                self.names().join(", ")
+            ))?;
+            let exe = analyzer.find_executable(version)?;
+            let version_found = analyzer.version(exe.as_deref())?;
+            if let Some(exe) = &exe {
+                log::debug!("{} --version: v{version_found}", exe.to_string_lossy());
+            }
+            resolved.push((ResolvedAnalyzer { analyzer, exe }, version_found));
+        }
+        Ok(resolved)
+    }
+}
+
+/// Get a total count of concerns from the tool named `tool` (or from all tools if
+/// [`None`]) for the given list of [`FileObj`]s.
+pub fn tally_advice(files: &[Arc<Mutex<FileObj>>], tool: Option<&str>) -> u64 {
+    let mut total = 0;
+    for file in files {
+        let file = file.lock().unwrap();
+        for advice in &file.advice {
+            if tool.is_none_or(|tool| advice.get_tool_name() == tool) {
+                total += advice.concerns(&file);
+            }
+        }
+    }
+    total
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        path::{Path, PathBuf},
+        process::Command,
+        sync::Arc,
+        time::Duration,
+    };
+
+    use anyhow::Result;
+
+    use super::{
+        run_command, Analysis, Analyzer, AnalyzerAdvice, AnalyzerDiagnostic, AnalyzerRegistry,
+    };
+    use crate::{cli::ClangParams, common_fs::FileObj};
+
+    /// An analyzer that always reports a TODO on line 2.
+    #[derive(Debug)]
+    struct TodoAnalyzer;
+
+    impl Analyzer for TodoAnalyzer {
+        fn name(&self) -> &'static str {
+            "todo"
+        }
+
+        fn find_executable(&self, _version: &str) -> Result<Option<PathBuf>> {
+            Ok(None)
+        }
+
+        fn analyze(
+            &self,
+            _exe: Option<&Path>,
+            _file: &FileObj,
+            _clang_params: &ClangParams,
+        ) -> Result<Analysis> {
+            let advice = AnalyzerAdvice {
+                tool: self.name().to_string(),
+                diagnostics: vec![AnalyzerDiagnostic {
+                    line: 2,
+                    cols: 1,
+                    severity: String::from("warning"),
+                    check: String::from("todo"),
+                    message: String::from("found a TODO"),
+                    related: vec![],
+                }],
+                patched: None,
+            };
+            Ok((Box::new(advice), vec![]))
+        }
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = AnalyzerRegistry::new();
+        registry.register(Arc::new(TodoAnalyzer));
+        assert_eq!(
+            registry.names(),
+            vec![
+                "clang-format",
+                "clang-tidy",
+                "cppcheck",
+                "include-what-you-use",
+                "clang-include-cleaner",
+                "rules",
+                "clang-static-analyzer",
+                "todo"
+            ]
+        );
+        assert!(registry.resolve(&[String::from("unknown")], "").is_err());
+
+        let resolved = registry.resolve(&[String::from("todo")], "").unwrap();
+        let (analyzer, version) = &resolved[0];
+        assert!(analyzer.exe.is_none());
+        assert_eq!(version, env!("CARGO_PKG_VERSION"));
+
+        let mut file = FileObj::new(PathBuf::from("demo.cpp"));
+        analyzer.run(&mut file, &ClangParams::default()).unwrap();
+        let advice = &file.advice[0];
+        assert_eq!(advice.get_tool_name(), "todo");
+        assert!(advice
+            .get_suggestion_help(1, 3)
+            .contains("warning: [todo]\n  > found a TODO"));
+        assert!(!advice.get_suggestion_help(3, 4).contains("found a TODO"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn command_timeout() {
+        let output = run_command(
+            Command::new("echo").arg("done"),
+            Some(Duration::from_secs(10)),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "done\n");
+        let output = run_command(
+            Command::new("sleep").arg("10"),
+            Some(Duration::from_millis(100)),
+        )
+        .unwrap();
+        assert!(output.is_none());
+    }
+}
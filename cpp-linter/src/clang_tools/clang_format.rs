@@ -2,22 +2,36 @@
 //! output.
 
 use std::{
+    env::current_dir,
     fs,
-    process::Command,
-    sync::{Arc, Mutex, MutexGuard},
+    io::Write,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
 };
 
-use anyhow::{Context, Result};
-use log::Level;
+use anyhow::{anyhow, Context, Result};
+use quick_xml::{events::Event, Reader};
 use serde::Deserialize;
 
 // project-specific crates/modules
-use super::MakeSuggestions;
+use super::{
+    analyzer::{is_filtered, Advice, Analysis, Analyzer, Annotation},
+    config_source::describe_format_config,
+    get_clang_tool_exe, MakeSuggestions,
+};
 use crate::{
     cli::ClangParams,
-    common_fs::{get_line_count_from_offset, FileObj},
+    common_fs::{
+        get_column_from_offset, get_line_count_from_offset, is_clang_format_ignored, FileObj,
+        CLANG_FORMAT_IGNORE_FILE_NAME,
+    },
 };
 
+/// The name of clang-format (as an [`Analyzer`]).
+pub const CLANG_FORMAT_NAME: &str = "clang-format";
+
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
 pub struct FormatAdvice {
     /// A list of [`Replacement`]s that clang-tidy wants to make.
@@ -25,6 +39,10 @@ pub struct FormatAdvice {
     pub replacements: Vec<Replacement>,
 
     pub patched: Option<Vec<u8>>,
+
+    /// The `--style` that the file was checked against.
+    #[serde(skip)]
+    pub style: String,
 }
 
 impl MakeSuggestions for FormatAdvice {
@@ -33,23 +51,282 @@ impl MakeSuggestions for FormatAdvice {
     }
 
     fn get_tool_name(&self) -> String {
-        "clang-format".to_string()
+        CLANG_FORMAT_NAME.to_string()
+    }
+}
+
+impl Advice for FormatAdvice {
+    fn patched(&self) -> Option<&[u8]> {
+        self.patched.as_deref()
+    }
+
+    fn concerns(&self, _file: &FileObj) -> u64 {
+        u64::from(!self.replacements.is_empty())
+    }
+
+    fn summarize(&self, total: u64) -> String {
+        format!("<strong>{total} file(s) not formatted</strong>")
+    }
+
+    fn describe(&self, file: &FileObj, remaining_length: &mut u64) -> String {
+        let mut description = String::new();
+        if self.replacements.is_empty() {
+            return description;
+        }
+        let note = format!("- {}\n", file.name.to_string_lossy().replace('\\', "/"));
+        if (note.len() as u64) >= *remaining_length {
+            return description;
+        }
+        description.push_str(&note);
+        *remaining_length -= note.len() as u64;
+        // list the replacements while they fit; the file is always listed
+        let total = self.replacements.len();
+        for (index, replacement) in self.replacements.iter().enumerate() {
+            let line = format!("  - {}\n", code_span(&replacement.describe()));
+            let more = format!("  - … and {} more\n", total - index);
+            // leave room to mention the replacements that don't fit
+            let needed = if index + 1 == total {
+                line.len()
+            } else {
+                line.len() + more.len()
+            };
+            if needed as u64 >= *remaining_length {
+                if (more.len() as u64) < *remaining_length {
+                    description.push_str(&more);
+                    *remaining_length -= more.len() as u64;
+                }
+                break;
+            }
+            description.push_str(&line);
+            *remaining_length -= line.len() as u64;
+        }
+        description
+    }
+
+    fn annotations(&self, file: &FileObj) -> Vec<Annotation> {
+        // annotate the file if any applicable lines were formatted
+        let Some(first) = self.replacements.first() else {
+            return vec![];
+        };
+        let name = file.name.to_string_lossy().replace('\\', "/");
+        // list the column range and change of each replacement
+        let changes = self
+            .replacements
+            .iter()
+            .map(Replacement::describe)
+            .collect::<Vec<_>>()
+            .join("\n");
+        vec![Annotation {
+            severity: String::from("notice"),
+            line: first.line,
+            cols: first.cols,
+            title: format!("Run clang-format on {name}"),
+            message: format!(
+                "File {name} does not conform to {} style guidelines.\n{changes}",
+                summarize_style(&self.style)
+            ),
+        }]
+    }
+}
+
+/// The [`Analyzer`] that checks the formatting of files with clang-format.
+///
+/// It is enabled with `--style` and is not used for files matched by
+/// `--ignore-format` or a `.clang-format-ignore` file.
+#[derive(Debug)]
+pub struct ClangFormat;
+
+impl Analyzer for ClangFormat {
+    fn name(&self) -> &'static str {
+        CLANG_FORMAT_NAME
+    }
+
+    fn find_executable(&self, version: &str) -> Result<Option<PathBuf>> {
+        get_clang_tool_exe(CLANG_FORMAT_NAME, version).map(Some)
+    }
+
+    fn prepare(
+        &self,
+        exe: Option<&Path>,
+        _version: &str,
+        clang_params: &mut ClangParams,
+        _files: &[Arc<Mutex<FileObj>>],
+    ) -> Result<Vec<(log::Level, String)>> {
+        // clang-tidy's fixes are also formatted with clang-format
+        clang_params.clang_format_command = exe.map(Path::to_path_buf);
+        Ok(vec![])
+    }
+
+    fn is_enabled(&self, clang_params: &ClangParams) -> bool {
+        !clang_params.style.is_empty()
+    }
+
+    fn ignored_because(&self, file: &Path, clang_params: &ClangParams) -> Option<String> {
+        if is_filtered(clang_params.format_filter.as_ref(), file) {
+            return Some(String::from("`--ignore-format`"));
+        }
+        let root = current_dir().unwrap_or_default();
+        is_clang_format_ignored(file, &root).then(|| format!("`{CLANG_FORMAT_IGNORE_FILE_NAME}`"))
+    }
+
+    fn describe_config(
+        &self,
+        file: &Path,
+        root: &Path,
+        clang_params: &ClangParams,
+    ) -> Option<String> {
+        if clang_params.style.is_empty() {
+            return None;
+        }
+        describe_format_config(file, root, &clang_params.style)
+    }
+
+    fn analyze(
+        &self,
+        exe: Option<&Path>,
+        file: &FileObj,
+        clang_params: &ClangParams,
+    ) -> Result<Analysis> {
+        let exe = exe.ok_or(anyhow!("clang-format executable was not resolved"))?;
+        let (advice, logs) = run_clang_format(exe, file, clang_params)?;
+        Ok((Box::new(advice), logs))
     }
 }
 
 /// A single replacement that clang-format wants to make.
-#[derive(Debug, PartialEq, Eq, Default, Clone, Copy, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Default, Clone, Deserialize)]
 pub struct Replacement {
     /// The byte offset where the replacement will start.
     #[serde(rename = "@offset")]
     pub offset: u32,
 
+    /// The number of bytes (starting at [`Replacement::offset`]) to replace.
+    #[serde(rename = "@length", default)]
+    pub length: u32,
+
+    /// The text that replaces the bytes spanned by [`Replacement::offset`] and
+    /// [`Replacement::length`].
+    ///
+    /// This value is parsed separately (see [`parse_format_advice()`]) because
+    /// deserialization trims whitespace from text content.
+    #[serde(skip)]
+    pub text: String,
+
     /// The line number described by the [`Replacement::offset`].
     ///
     /// This value is not provided by the XML output, but we calculate it after
     /// deserialization.
     #[serde(default)]
     pub line: u32,
+
+    /// The column number (in bytes) described by the [`Replacement::offset`].
+    ///
+    /// This value is not provided by the XML output, but we calculate it after
+    /// deserialization.
+    #[serde(default)]
+    pub cols: u32,
+
+    /// The line number where the replaced bytes end.
+    ///
+    /// This value is not provided by the XML output, but we calculate it after
+    /// deserialization.
+    #[serde(default)]
+    pub end_line: u32,
+
+    /// The column number (in bytes) that immediately follows the replaced bytes.
+    ///
+    /// This value is not provided by the XML output, but we calculate it after
+    /// deserialization.
+    #[serde(default)]
+    pub end_cols: u32,
+}
+
+impl Replacement {
+    /// Calculate the line and column numbers of this replacement from the given
+    /// `contents` of the original file.
+    pub fn locate(&mut self, contents: &[u8]) {
+        self.line = get_line_count_from_offset(contents, self.offset);
+        self.cols = get_column_from_offset(contents, self.offset);
+        let end = self.offset + self.length;
+        self.end_line = get_line_count_from_offset(contents, end);
+        self.end_cols = get_column_from_offset(contents, end);
+    }
+
+    /// Describe the column range and the change that this replacement makes.
+    ///
+    /// For example, `3:5-3:7 replace 2 byte(s) with " "`.
+    pub fn describe(&self) -> String {
+        let position = if self.length == 0 {
+            format!("{}:{}", self.line, self.cols)
+        } else {
+            format!(
+                "{}:{}-{}:{}",
+                self.line, self.cols, self.end_line, self.end_cols
+            )
+        };
+        let change = match (self.length, self.text.is_empty()) {
+            (0, _) => format!("insert {:?}", self.text),
+            (length, true) => format!("remove {length} byte(s)"),
+            (length, false) => format!("replace {length} byte(s) with {:?}", self.text),
+        };
+        format!("{position} {change}")
+    }
+}
+
+/// Parse the XML output from clang-format (using `--output-replacements-xml`).
+pub fn parse_format_advice(xml: &str) -> Result<FormatAdvice> {
+    let mut advice = quick_xml::de::from_str::<FormatAdvice>(xml)?;
+    let texts = parse_replacement_texts(xml)?;
+    for (replacement, text) in advice.replacements.iter_mut().zip(texts) {
+        replacement.text = text;
+    }
+    Ok(advice)
+}
+
+/// Get the (untrimmed) text of each `<replacement>` element in the given `xml`.
+fn parse_replacement_texts(xml: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut texts = vec![];
+    let mut current = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) if e.name().as_ref() == b"replacement" => {
+                current = Some(String::new());
+            }
+            Event::Text(e) => {
+                if let Some(text) = &mut current {
+                    text.push_str(&e.unescape()?);
+                }
+            }
+            Event::End(e) if e.name().as_ref() == b"replacement" => {
+                texts.extend(current.take());
+            }
+            Event::Empty(e) if e.name().as_ref() == b"replacement" => {
+                texts.push(String::new());
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(texts)
+}
+
+/// Wrap the given `text` in a markdown code span.
+///
+/// The code span is fenced with more backticks than the longest run of backticks in
+/// the `text`, so the `text` cannot end the code span early.
+fn code_span(text: &str) -> String {
+    let longest_run = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run + 1);
+    if longest_run > 0 {
+        format!("{fence} {text} {fence}")
+    } else {
+        format!("{fence}{text}{fence}")
+    }
 }
 
 /// Get a string that summarizes the given `--style`
@@ -66,26 +343,14 @@ pub fn summarize_style(style: &str) -> String {
     }
 }
 
-/// Get a total count of clang-format advice from the given list of [FileObj]s.
-pub fn tally_format_advice(files: &[Arc<Mutex<FileObj>>]) -> u64 {
-    let mut total = 0;
-    for file in files {
-        let file = file.lock().unwrap();
-        if let Some(advice) = &file.format_advice {
-            if !advice.replacements.is_empty() {
-                total += 1;
-            }
-        }
-    }
-    total
-}
-
-/// Run clang-tidy for a specific `file`, then parse and return it's XML output.
+/// Run clang-format (the given `exe`) for a specific `file`, then parse and return
+/// it's XML output.
 pub fn run_clang_format(
-    file: &mut MutexGuard<FileObj>,
+    exe: &Path,
+    file: &FileObj,
     clang_params: &ClangParams,
-) -> Result<Vec<(log::Level, String)>> {
-    let mut cmd = Command::new(clang_params.clang_format_command.as_ref().unwrap());
+) -> Result<(FormatAdvice, Vec<(log::Level, String)>)> {
+    let mut cmd = Command::new(exe);
     let mut logs = vec![];
     cmd.args(["--style", &clang_params.style]);
     let ranges = file.get_ranges(&clang_params.lines_changed_only);
@@ -93,33 +358,13 @@ pub fn run_clang_format(
         cmd.arg(format!("--lines={}:{}", range.start(), range.end()));
     }
     let file_name = file.name.to_string_lossy().to_string();
-    cmd.arg(file.name.to_path_buf().as_os_str());
-    let patched = if !clang_params.format_review {
-        None
-    } else {
-        logs.push((
-            Level::Info,
-            format!(
-                "Getting format fixes with \"{} {}\"",
-                clang_params
-                    .clang_format_command
-                    .as_ref()
-                    .unwrap()
-                    .to_str()
-                    .unwrap_or_default(),
-                cmd.get_args()
-                    .map(|a| a.to_string_lossy())
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            ),
-        ));
-        Some(
-            cmd.output()
-                .with_context(|| format!("Failed to get fixes from clang-format: {file_name}"))?
-                .stdout,
-        )
-    };
-    cmd.arg("--output-replacements-xml");
+    // The file's content is fed via stdin, so the replacements are guaranteed to
+    // correspond to the content cached here.
+    let original_contents = fs::read(&file.name).with_context(|| {
+        format!("Failed to read file's original content before running clang-format: {file_name}")
+    })?;
+    cmd.arg(format!("--assume-filename={file_name}"))
+        .arg("--output-replacements-xml");
     logs.push((
         log::Level::Info,
         format!(
@@ -131,8 +376,19 @@ pub fn run_clang_format(
                 .join(" ")
         ),
     ));
-    let output = cmd
-        .output()
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run clang-format for {file_name}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(&original_contents)
+            .with_context(|| format!("Failed to pass {file_name} to clang-format"))?;
+    }
+    let output = child
+        .wait_with_output()
         .with_context(|| format!("Failed to get replacements from clang-format: {file_name}"))?;
     if !output.stderr.is_empty() || !output.status.success() {
         logs.push((
@@ -147,47 +403,123 @@ pub fn run_clang_format(
         let xml = String::from_utf8(output.stdout).with_context(|| {
             format!("XML output from clang-format was not UTF-8 encoded: {file_name}")
         })?;
-        quick_xml::de::from_str::<FormatAdvice>(&xml).with_context(|| {
+        parse_format_advice(&xml).with_context(|| {
             format!("Failed to parse XML output from clang-format for {file_name}")
         })?
     } else {
         FormatAdvice {
             replacements: vec![],
             patched: None,
+            style: String::new(),
         }
     };
-    format_advice.patched = patched;
-    if !format_advice.replacements.is_empty() {
-        let original_contents = fs::read(&file.name).with_context(|| {
-            format!(
-                "Failed to read file's original content before translating byte offsets: {file_name}",
-            )
-        })?;
-        // get line and column numbers from format_advice.offset
-        let mut filtered_replacements = Vec::new();
-        for replacement in &mut format_advice.replacements {
-            let line_number = get_line_count_from_offset(&original_contents, replacement.offset);
-            replacement.line = line_number;
-            for range in &ranges {
-                if range.contains(&line_number) {
-                    filtered_replacements.push(*replacement);
-                    break;
-                }
-            }
-            if ranges.is_empty() {
-                // lines_changed_only is disabled
-                filtered_replacements.push(*replacement);
-            }
+    format_advice.style = clang_params.style.clone();
+    if clang_params.format_review {
+        format_advice.patched = Some(apply_replacements(
+            &original_contents,
+            &format_advice.replacements,
+        ));
+    }
+    // get line and column numbers from format_advice.offset
+    let mut filtered_replacements = Vec::new();
+    for mut replacement in format_advice.replacements {
+        replacement.locate(&original_contents);
+        if ranges.is_empty() || ranges.iter().any(|range| range.contains(&replacement.line)) {
+            // lines_changed_only is disabled or the line was changed
+            filtered_replacements.push(replacement);
+        }
+    }
+    format_advice.replacements = filtered_replacements;
+    Ok((format_advice, logs))
+}
+
+/// Format only the given `ranges` of lines in the given `contents` (in memory) and
+/// return the formatted contents.
+///
+/// The `file_name` is only used to let clang-format find the `style` configuration
+/// and infer the language.
+pub fn format_ranges(
+    exe: &Path,
+    style: &str,
+    file_name: &str,
+    contents: &[u8],
+    ranges: &[RangeInclusive<u32>],
+) -> Result<Vec<u8>> {
+    let mut cmd = Command::new(exe);
+    cmd.args(["--style", style]);
+    for range in ranges {
+        cmd.arg(format!("--lines={}:{}", range.start(), range.end()));
+    }
+    cmd.arg(format!("--assume-filename={file_name}"))
+        .arg("--output-replacements-xml");
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run clang-format for {file_name}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(contents)
+            .with_context(|| format!("Failed to pass {file_name} to clang-format"))?;
+    }
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to get replacements from clang-format: {file_name}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "clang-format failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    if output.stdout.is_empty() {
+        return Ok(contents.to_vec());
+    }
+    let xml = String::from_utf8(output.stdout).with_context(|| {
+        format!("XML output from clang-format was not UTF-8 encoded: {file_name}")
+    })?;
+    let advice = parse_format_advice(&xml)
+        .with_context(|| format!("Failed to parse XML output from clang-format for {file_name}"))?;
+    Ok(apply_replacements(contents, &advice.replacements))
+}
+
+/// Apply the given `replacements` to the given `contents` (in memory).
+///
+/// Replacements are applied in order of their [`Replacement::offset`]. Any replacement
+/// that overlaps a previous replacement (or is out of bounds) is skipped.
+pub fn apply_replacements(contents: &[u8], replacements: &[Replacement]) -> Vec<u8> {
+    let mut sorted = replacements.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|replacement| replacement.offset);
+    let mut patched = Vec::with_capacity(contents.len());
+    let mut cursor = 0;
+    for replacement in sorted {
+        let start = replacement.offset as usize;
+        let end = start + replacement.length as usize;
+        if start < cursor || end > contents.len() {
+            continue;
         }
-        format_advice.replacements = filtered_replacements;
+        patched.extend_from_slice(&contents[cursor..start]);
+        patched.extend_from_slice(replacement.text.as_bytes());
+        cursor = end;
     }
-    file.format_advice = Some(format_advice);
-    Ok(logs)
+    patched.extend_from_slice(&contents[cursor..]);
+    patched
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{summarize_style, FormatAdvice, Replacement};
+    use super::{
+        apply_replacements, code_span, parse_format_advice, summarize_style, FormatAdvice,
+        Replacement,
+    };
+
+    #[test]
+    fn code_spans() {
+        assert_eq!(code_span("a b"), "`a b`");
+        assert_eq!(code_span("a ` b"), "`` a ` b ``");
+        assert_eq!(code_span("``"), "``` `` ```");
+    }
 
     #[test]
     fn parse_blank_xml() {
@@ -209,22 +541,86 @@ mod tests {
             .to_vec();
 
         let expected = FormatAdvice {
-            replacements: [113, 147, 161, 165]
-                .iter()
-                .map(|offset| Replacement {
-                    offset: *offset,
-                    ..Default::default()
-                })
-                .collect(),
+            replacements: [
+                (113, 5, "\n      "),
+                (147, 0, " "),
+                (161, 0, ""),
+                (165, 19, "\n\n"),
+            ]
+            .iter()
+            .map(|(offset, length, text)| Replacement {
+                offset: *offset,
+                length: *length,
+                text: text.to_string(),
+                ..Default::default()
+            })
+            .collect(),
             patched: None,
+            style: String::new(),
         };
 
         let xml = String::from_utf8(xml_raw).unwrap();
 
-        let document = quick_xml::de::from_str::<FormatAdvice>(&xml).unwrap();
+        let document = parse_format_advice(&xml).unwrap();
         assert_eq!(expected, document);
     }
 
+    #[test]
+    fn locate_replacements() {
+        let contents = b"int main(){\n  return 0;\n}\n";
+        let mut insert = Replacement {
+            offset: 10,
+            text: String::from(" "),
+            ..Default::default()
+        };
+        insert.locate(contents);
+        assert_eq!((insert.line, insert.cols), (1, 11));
+        assert_eq!(insert.describe(), "1:11 insert \" \"");
+
+        let mut replace = Replacement {
+            offset: 11,
+            length: 3,
+            text: String::from("\n    "),
+            ..Default::default()
+        };
+        replace.locate(contents);
+        assert_eq!((replace.end_line, replace.end_cols), (2, 3));
+        assert_eq!(
+            replace.describe(),
+            "1:12-2:3 replace 3 byte(s) with \"\\n    \""
+        );
+
+        let mut remove = Replacement {
+            offset: 23,
+            length: 1,
+            ..Default::default()
+        };
+        remove.locate(contents);
+        assert_eq!(remove.describe(), "2:12-3:1 remove 1 byte(s)");
+    }
+
+    #[test]
+    fn apply_in_memory() {
+        let contents = b"int main(){\n  return 0;\n}\n";
+        let replacement = |offset, length, text: &str| Replacement {
+            offset,
+            length,
+            text: text.to_string(),
+            ..Default::default()
+        };
+        let replacements = vec![
+            replacement(23, 1, "\n\n"),
+            replacement(10, 0, " "),
+            replacement(12, 2, "    "),
+            // overlaps the previous replacement
+            replacement(13, 1, ""),
+        ];
+        assert_eq!(
+            String::from_utf8(apply_replacements(contents, &replacements)).unwrap(),
+            "int main() {\n    return 0;\n\n}\n"
+        );
+    }
+
     fn formalize_style(style: &str, expected: &str) {
         assert_eq!(summarize_style(style), expected);
     }
@@ -2,43 +2,36 @@
 //! output.
 
 use std::{
+    collections::HashSet,
     env::{consts::OS, current_dir},
     fs,
-    path::PathBuf,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
     process::Command,
-    sync::{Arc, Mutex, MutexGuard},
+    str::FromStr,
+    sync::{Arc, Mutex},
 };
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 // non-std crates
 use regex::Regex;
 use serde::Deserialize;
 
 // project-specific modules/crates
-use super::MakeSuggestions;
+use super::{
+    analyzer::{is_filtered, Advice, Analysis, Analyzer, Annotation},
+    clang_format::{apply_replacements, format_ranges, Replacement},
+    compilation_db::CompilationUnit,
+    config_source::{describe_tidy_config, find_config_file, TIDY_CONFIG_FILE_NAMES},
+    get_clang_tool_exe, make_patch, MakeSuggestions, ReviewComments,
+};
 use crate::{
     cli::{ClangParams, LinesChangedOnly},
-    common_fs::{normalize_path, FileObj},
+    common_fs::{get_line_count_from_offset, normalize_path, FileObj},
 };
 
-/// Used to deserialize a json compilation database's translation unit.
-///
-/// The only purpose this serves is to normalize relative paths for build systems that
-/// use/need relative paths (ie ninja).
-#[derive(Deserialize, Debug, Clone)]
-pub struct CompilationUnit {
-    /// The directory of the build environment
-    directory: String,
-
-    /// The file path of the translation unit.
-    ///
-    /// Sometimes, this is relative to the build [`CompilationUnit::directory`].
-    ///
-    /// This is typically the path that clang-tidy uses in its stdout (for a dry run).
-    /// So, having this information helps with matching clang-tidy's stdout with the
-    /// repository files.
-    file: String,
-}
+/// The name of clang-tidy (as an [`Analyzer`]).
+pub const CLANG_TIDY_NAME: &str = "clang-tidy";
 
 /// A structure that represents a single notification parsed from clang-tidy's stdout.
 #[derive(Debug, Clone)]
@@ -68,27 +61,159 @@ pub struct TidyNotification {
     /// fixes/advice. This information is purely superfluous.
     pub suggestion: Vec<String>,
 
-    /// The list of line numbers that had fixes applied via `clang-tidy --fix-error`.
+    /// The list of line numbers that have fixes (exported via `clang-tidy --export-fixes`).
     pub fixed_lines: Vec<u32>,
+
+    /// The documentation URLs of the diagnostic's names (see [`tidy_doc_url()`]).
+    ///
+    /// Names without an entry are linked to the latest clang-tidy documentation.
+    pub doc_urls: Vec<(String, String)>,
 }
 
 impl TidyNotification {
+    /// Get the diagnostic's name(s) as markdown links to the documentation.
+    ///
+    /// A diagnostic has multiple (comma-separated) names if it is reported by
+    /// multiple enabled aliases of the same check. Each alias is linked separately to
+    /// its own documentation page; aliases are not resolved to the primary check (the
+    /// alias' page usually redirects there).
     pub fn diagnostic_link(&self) -> String {
-        if self.diagnostic.starts_with("clang-diagnostic") {
-            return self.diagnostic.clone();
+        self.diagnostic
+            .split(',')
+            .map(|check| {
+                let url = self
+                    .doc_urls
+                    .iter()
+                    .find(|(name, _)| name == check)
+                    .map(|(_, url)| url.clone())
+                    .or_else(|| builtin_doc_url(check, None));
+                match url {
+                    Some(url) => format!("[{check}]({url})"),
+                    None => check.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Get the folder of the LLVM release documentation for the given `major` version.
+///
+/// The docs are published for the first release of each major version, which was
+/// `N.0.0` before v17, `17.0.1` for v17, and `N.1.0` since v18.
+fn release_folder(major: u32) -> String {
+    match major {
+        ..=16 => format!("{major}.0.0"),
+        17 => String::from("17.0.1"),
+        _ => format!("{major}.1.0"),
+    }
+}
+
+/// Get the URL of the LLVM documentation for the given `check`.
+///
+/// If the `major` version of clang-tidy is known, then the documentation of that
+/// release is used. Otherwise, the latest documentation is used.
+/// A `clang-diagnostic-*` check links to the clang warning reference. [`None`] is
+/// returned for a diagnostic that has no documentation (ie `clang-diagnostic-error`).
+fn builtin_doc_url(check: &str, major: Option<u32>) -> Option<String> {
+    if let Some(flag) = check.strip_prefix("clang-diagnostic-") {
+        if flag == "error" || flag == "warning" {
+            // compiler errors and warnings without a `-W` flag
+            return None;
         }
-        let (category, name) = if self.diagnostic.starts_with("clang-analyzer-") {
-            (
-                "clang-analyzer",
-                self.diagnostic.strip_prefix("clang-analyzer-").unwrap(),
-            )
-        } else {
-            self.diagnostic.split_once('-').unwrap()
+        let base = match major {
+            Some(major) => format!(
+                "https://releases.llvm.org/{}/tools/clang/docs/",
+                release_folder(major)
+            ),
+            None => String::from("https://clang.llvm.org/docs/"),
         };
-        format!(
-            "[{}](https://clang.llvm.org/extra/clang-tidy/checks/{category}/{name}.html)",
-            self.diagnostic
-        )
+        return Some(format!(
+            "{base}DiagnosticsReference.html#w{}",
+            flag.to_lowercase()
+        ));
+    }
+    let base = match major {
+        Some(major) => format!(
+            "https://releases.llvm.org/{}/tools/clang/tools/extra/docs/clang-tidy/checks/",
+            release_folder(major)
+        ),
+        None => String::from("https://clang.llvm.org/extra/clang-tidy/checks/"),
+    };
+    if major.is_some_and(|major| major < 15) {
+        // the checks' docs were grouped into directories (by category) in v15
+        return Some(format!("{base}{check}.html"));
+    }
+    let (category, name) = match check.strip_prefix("clang-analyzer-") {
+        Some(name) => ("clang-analyzer", name),
+        None => check.split_once('-')?,
+    };
+    Some(format!("{base}{category}/{name}.html"))
+}
+
+/// Get the URL of the documentation for the given `check`.
+///
+/// A URL template from `--tidy-doc-link` (see [`find_doc_url()`]) takes precedence.
+/// Otherwise, the documentation of the given clang-tidy `version` is used.
+pub fn tidy_doc_url(check: &str, links: &[TidyDocLink], version: Option<&str>) -> Option<String> {
+    let major = version
+        .and_then(|v| v.split('.').next())
+        .and_then(|major| major.parse::<u32>().ok());
+    find_doc_url(check, links).or_else(|| builtin_doc_url(check, major))
+}
+
+/// A URL template for the documentation of the clang-tidy checks with a certain prefix.
+///
+/// This is parsed from a `--tidy-doc-link` value in the form `prefix=template`.
+/// In the template, `{check}` is replaced with the check's name and `{name}` is
+/// replaced with the check's name without the prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TidyDocLink {
+    /// The prefix of the check names that this template applies to.
+    pub prefix: String,
+
+    /// The URL template.
+    pub template: String,
+}
+
+impl FromStr for TidyDocLink {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (prefix, template) = value.split_once('=').ok_or(anyhow!(
+            "Expected a value like `prefix=template`, got `{value}`"
+        ))?;
+        if prefix.is_empty() || template.is_empty() {
+            return Err(anyhow!(
+                "Expected a value like `prefix=template`, got `{value}`"
+            ));
+        }
+        Ok(Self {
+            prefix: prefix.to_string(),
+            template: template.to_string(),
+        })
+    }
+}
+
+/// Find the documentation URL for the given `check` from the configured `links`.
+///
+/// If multiple prefixes match, then the longest one is used.
+pub fn find_doc_url(check: &str, links: &[TidyDocLink]) -> Option<String> {
+    links
+        .iter()
+        .filter(|link| check.starts_with(&link.prefix))
+        .max_by_key(|link| link.prefix.len())
+        .map(|link| {
+            link.template
+                .replace("{check}", check)
+                .replace("{name}", &check[link.prefix.len()..])
+        })
+}
+
+/// Add a `-load` argument for each of the given clang-tidy plugins (see `--tidy-load`).
+pub fn add_load_args(cmd: &mut Command, plugins: &[PathBuf]) {
+    for plugin in plugins {
+        cmd.arg(format!("-load={}", plugin.to_string_lossy()));
     }
 }
 
@@ -126,19 +251,275 @@ impl MakeSuggestions for TidyAdvice {
     }
 
     fn get_tool_name(&self) -> String {
-        "clang-tidy".to_string()
+        CLANG_TIDY_NAME.to_string()
+    }
+}
+
+impl TidyAdvice {
+    /// Get the notifications about the given `file` itself.
+    ///
+    /// Notifications about other files (ie included headers) are not reported.
+    fn notes_about<'a>(&'a self, file: &'a FileObj) -> impl Iterator<Item = &'a TidyNotification> {
+        self.notes
+            .iter()
+            .filter(|note| Path::new(&note.filename) == file.name)
+    }
+}
+
+impl Advice for TidyAdvice {
+    fn patched(&self) -> Option<&[u8]> {
+        self.patched.as_deref()
+    }
+
+    fn concerns(&self, file: &FileObj) -> u64 {
+        self.notes_about(file).count() as u64
+    }
+
+    fn describe(&self, file: &FileObj, remaining_length: &mut u64) -> String {
+        let mut description = String::new();
+        for tidy_note in self.notes_about(file) {
+            let file_path = Path::new(&tidy_note.filename);
+            let mut tmp_note = format!("- {}\n\n", tidy_note.filename);
+            tmp_note.push_str(&format!(
+                "   <strong>{filename}:{line}:{cols}:</strong> {severity}: [{diagnostic}]\n   > {rationale}\n{concerned_code}",
+                filename = tidy_note.filename,
+                line = tidy_note.line,
+                cols = tidy_note.cols,
+                severity = tidy_note.severity,
+                diagnostic = tidy_note.diagnostic_link(),
+                rationale = tidy_note.rationale,
+                concerned_code = if tidy_note.suggestion.is_empty() {String::from("")} else {
+                    format!("\n   ```{ext}\n   {suggestion}\n   ```\n",
+                        ext = file_path.extension().unwrap_or_default().to_string_lossy(),
+                        suggestion = tidy_note.suggestion.join("\n   "),
+                    ).to_string()
+                },
+            ).to_string());
+
+            if (tmp_note.len() as u64) < *remaining_length {
+                description.push_str(&tmp_note);
+                *remaining_length -= tmp_note.len() as u64;
+            }
+        }
+        description
+    }
+
+    fn annotations(&self, file: &FileObj) -> Vec<Annotation> {
+        // lines are already filtered as specified to clang-tidy CLI.
+        self.notes_about(file)
+            .map(|note| Annotation {
+                severity: note.severity.clone(),
+                line: note.line,
+                cols: note.cols,
+                title: format!(
+                    "{}:{}:{} [{}]",
+                    note.filename, note.line, note.cols, note.diagnostic
+                ),
+                message: note.rationale.clone(),
+            })
+            .collect()
+    }
+
+    fn checks(&self, file: &FileObj) -> Vec<(String, String)> {
+        self.notes_about(file)
+            .map(|note| (note.diagnostic.clone(), note.severity.clone()))
+            .collect()
+    }
+
+    fn unfixed_concerns(
+        &self,
+        file: &FileObj,
+        _review_comments: &ReviewComments,
+    ) -> Vec<(u32, String)> {
+        let file_name = file.name.to_string_lossy().replace('\\', "/");
+        let file_ext = file.name.extension().unwrap_or_default().to_string_lossy();
+        self.notes
+            .iter()
+            // the notification had no suggestion applied in `patched`
+            .filter(|note| note.fixed_lines.is_empty())
+            .map(|note| {
+                let mut suggestion = format!(
+                    "### clang-tidy diagnostic\n**{file_name}:{}:{}** {}: [{}]\n\n> {}\n",
+                    &note.line,
+                    &note.cols,
+                    &note.severity,
+                    note.diagnostic_link(),
+                    &note.rationale
+                );
+                if !note.suggestion.is_empty() {
+                    suggestion.push_str(
+                        format!("\n```{file_ext}\n{}\n```\n", &note.suggestion.join("\n")).as_str(),
+                    );
+                }
+                (note.line, suggestion)
+            })
+            .collect()
+    }
+}
+
+/// The [`Analyzer`] that diagnoses files with clang-tidy.
+///
+/// It is enabled with `--tidy-checks` and is not used for files matched by
+/// `--ignore-tidy`.
+#[derive(Debug)]
+pub struct ClangTidy;
+
+impl Analyzer for ClangTidy {
+    fn name(&self) -> &'static str {
+        CLANG_TIDY_NAME
+    }
+
+    fn find_executable(&self, version: &str) -> Result<Option<PathBuf>> {
+        get_clang_tool_exe(CLANG_TIDY_NAME, version).map(Some)
+    }
+
+    fn prepare(
+        &self,
+        exe: Option<&Path>,
+        version: &str,
+        clang_params: &mut ClangParams,
+        files: &[Arc<Mutex<FileObj>>],
+    ) -> Result<Vec<(log::Level, String)>> {
+        let exe = exe.ok_or(anyhow!("clang-tidy executable was not resolved"))?;
+        clang_params.tidy_version = Some(version.to_string());
+        clang_params.clang_tidy_command = Some(exe.to_path_buf());
+        check_tidy_checks(exe, clang_params, files)
+    }
+
+    fn is_enabled(&self, clang_params: &ClangParams) -> bool {
+        clang_params.tidy_checks != "-*"
+    }
+
+    fn ignored_because(&self, file: &Path, clang_params: &ClangParams) -> Option<String> {
+        is_filtered(clang_params.tidy_filter.as_ref(), file)
+            .then(|| String::from("`--ignore-tidy`"))
+    }
+
+    fn describe_config(
+        &self,
+        file: &Path,
+        root: &Path,
+        clang_params: &ClangParams,
+    ) -> Option<String> {
+        if clang_params.tidy_checks == "-*" {
+            return None;
+        }
+        describe_tidy_config(file, root, &clang_params.tidy_checks)
+    }
+
+    fn analyze(
+        &self,
+        _exe: Option<&Path>,
+        file: &FileObj,
+        clang_params: &ClangParams,
+    ) -> Result<Analysis> {
+        let (advice, logs) = run_clang_tidy(file, clang_params)?;
+        Ok((Box::new(advice), logs))
     }
 }
 
+/// Validate the `--tidy-checks` (and those of any overrides) against the checks that
+/// the clang-tidy `exe` supports, then list the enabled checks as debugging output.
+///
+/// The `Checks` of the `.clang-tidy` config files that apply to the `files` are
+/// validated as well. Returns a list of cached log messages.
+fn check_tidy_checks(
+    exe: &Path,
+    clang_params: &ClangParams,
+    files: &[Arc<Mutex<FileObj>>],
+) -> Result<Vec<(log::Level, String)>> {
+    let mut logs = vec![];
+    match list_checks(exe, "*", None, &clang_params.tidy_load) {
+        Ok(available) => {
+            let override_checks = clang_params
+                .overrides
+                .iter()
+                .filter_map(|o| o.tidy_checks.as_deref());
+            for checks in [clang_params.tidy_checks.as_str()]
+                .into_iter()
+                .chain(override_checks)
+            {
+                validate_checks(
+                    checks,
+                    &available,
+                    clang_params.strict_checks,
+                    "`--tidy-checks`",
+                )?;
+            }
+            let root = current_dir().with_context(|| "Failed to get current working directory")?;
+            let mut validated = HashSet::new();
+            for file in files {
+                let file_name = file.lock().unwrap().name.clone();
+                let Some(config) = find_config_file(&file_name, &root, &TIDY_CONFIG_FILE_NAMES)
+                else {
+                    continue;
+                };
+                if !validated.insert(config.clone()) {
+                    continue;
+                }
+                let source = format!(
+                    "`{}`",
+                    config
+                        .strip_prefix(&root)
+                        .unwrap_or(&config)
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                );
+                match config_checks(exe, &file_name, &clang_params.tidy_load) {
+                    Ok(Some(checks)) => {
+                        validate_checks(&checks, &available, clang_params.strict_checks, &source)?
+                    }
+                    Ok(None) => {}
+                    Err(e) => logs.push((
+                        log::Level::Debug,
+                        format!("Unable to validate the checks in {source}: {e:?}"),
+                    )),
+                }
+            }
+        }
+        Err(e) => logs.push((
+            log::Level::Debug,
+            format!("Unable to validate clang-tidy checks: {e:?}"),
+        )),
+    }
+    if log::log_enabled!(log::Level::Debug) {
+        // use the first file to find the applicable .clang-tidy config file
+        let first_file = files
+            .first()
+            .and_then(|f| f.lock().ok().map(|f| f.name.clone()));
+        match list_checks(
+            exe,
+            &clang_params.tidy_checks,
+            first_file.as_deref(),
+            &clang_params.tidy_load,
+        ) {
+            Ok(enabled) => {
+                logs.push((
+                    log::Level::Debug,
+                    String::from("Enabled clang-tidy checks:"),
+                ));
+                for check in enabled {
+                    logs.push((log::Level::Debug, format!("  {check}")));
+                }
+            }
+            Err(e) => logs.push((
+                log::Level::Debug,
+                format!("Unable to list enabled clang-tidy checks: {e:?}"),
+            )),
+        }
+    }
+    Ok(logs)
+}
+
 /// Parses clang-tidy stdout.
 ///
 /// Here it helps to have the JSON database deserialized for normalizing paths present
 /// in the notifications.
-fn parse_tidy_output(
+pub(super) fn parse_tidy_output(
     tidy_stdout: &[u8],
     database_json: &Option<Vec<CompilationUnit>>,
 ) -> Result<TidyAdvice> {
-    let note_header = Regex::new(r"^(.+):(\d+):(\d+):\s(\w+):(.*)\[([a-zA-Z\d\-\.]+)\]$").unwrap();
+    let note_header = Regex::new(r"^(.+):(\d+):(\d+):\s(\w+):(.*)\[([a-zA-Z\d\-\.,]+)\]$").unwrap();
     let fixed_note =
         Regex::new(r"^.+:(\d+):\d+:\snote: FIX-IT applied suggested code changes$").unwrap();
     let mut found_fix = false;
@@ -197,6 +578,7 @@ fn parse_tidy_output(
                 diagnostic: String::from(&captured[6]),
                 suggestion: Vec::new(),
                 fixed_lines: Vec::new(),
+                doc_urls: vec![],
             });
             // begin capturing subsequent lines as suggestions
             found_fix = false;
@@ -228,69 +610,252 @@ fn parse_tidy_output(
     })
 }
 
-/// Get a total count of clang-tidy advice from the given list of [FileObj]s.
-pub fn tally_tidy_advice(files: &[Arc<Mutex<FileObj>>]) -> u64 {
-    let mut total = 0;
-    for file in files {
-        let file = file.lock().unwrap();
-        if let Some(advice) = &file.tidy_advice {
-            for tidy_note in &advice.notes {
-                let file_path = PathBuf::from(&tidy_note.filename);
-                if file_path == file.name {
-                    total += 1;
-                }
+/// Parse the list of check names from the output of `clang-tidy --list-checks`.
+fn parse_check_list(stdout: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .skip_while(|line| !line.starts_with("Enabled checks:"))
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Run `clang-tidy --list-checks` with the given `checks` and return the enabled checks.
+///
+/// The `file` (if any) is used to find the applicable `.clang-tidy` config file.
+///
+/// The checks of any given clang-tidy `plugins` are also listed.
+pub fn list_checks(
+    exe: &Path,
+    checks: &str,
+    file: Option<&Path>,
+    plugins: &[PathBuf],
+) -> Result<Vec<String>> {
+    let mut cmd = Command::new(exe);
+    add_load_args(&mut cmd, plugins);
+    cmd.arg("--list-checks");
+    if !checks.is_empty() {
+        cmd.arg(format!("--checks={checks}"));
+    }
+    match file {
+        Some(file) => cmd.arg(file),
+        // no compilation database is needed for listing checks
+        None => cmd.arg("--"),
+    };
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to run {} --list-checks", exe.to_string_lossy()))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} --list-checks failed: {}",
+            exe.to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(parse_check_list(&output.stdout))
+}
+
+/// Run `clang-tidy --dump-config` for the given `file` and return the `Checks` of its
+/// effective configuration (if any).
+///
+/// Unlike [`list_checks()`], the `--tidy-checks` are not passed, so only the checks of
+/// the `.clang-tidy` config files (that apply to the `file`) are returned.
+pub fn config_checks(exe: &Path, file: &Path, plugins: &[PathBuf]) -> Result<Option<String>> {
+    let mut cmd = Command::new(exe);
+    add_load_args(&mut cmd, plugins);
+    // no compilation database is needed for dumping the config
+    cmd.arg("--dump-config").arg(file).arg("--");
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to run {} --dump-config", exe.to_string_lossy()))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} --dump-config failed: {}",
+            exe.to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(parse_config_checks(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Get the `Checks` value from the YAML output of `clang-tidy --dump-config`.
+fn parse_config_checks(yaml: &str) -> Option<String> {
+    let config = serde_yaml_ng::from_str::<serde_yaml_ng::Value>(yaml).ok()?;
+    config.get("Checks")?.as_str().map(str::to_string)
+}
+
+/// The prefix of the compiler diagnostics that clang-tidy reports like checks.
+///
+/// These are not listed by `clang-tidy --list-checks`.
+const CLANG_DIAGNOSTIC_PREFIX: &str = "clang-diagnostic-";
+
+/// Find the globs in the given `checks` (as passed to `--tidy-checks`) that do not
+/// match any of the `available` checks.
+///
+/// Both enabling and disabling (prefixed with `-`) globs are considered. Globs for
+/// the `clang-diagnostic-*` compiler diagnostics are never reported.
+pub fn find_unknown_globs(checks: &str, available: &[String]) -> Vec<String> {
+    checks
+        .split(',')
+        .map(str::trim)
+        .filter(|glob| !glob.is_empty())
+        .filter(|glob| {
+            let pattern = glob.trim_start_matches('-');
+            if pattern.starts_with(CLANG_DIAGNOSTIC_PREFIX) {
+                return false;
             }
-        }
+            let regex = Regex::new(&format!(
+                "^{}$",
+                regex::escape(pattern).replace(r"\*", ".*")
+            ))
+            .unwrap();
+            !available.iter().any(|check| regex.is_match(check))
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Validate the globs in the given `checks` against the `available` checks
+/// (see [`list_checks()`]).
+///
+/// The `source` describes where the `checks` came from (ie `--tidy-checks`).
+/// A warning is logged for each glob that matches no checks. If `strict` is true, then
+/// unknown globs are considered an error.
+pub fn validate_checks(
+    checks: &str,
+    available: &[String],
+    strict: bool,
+    source: &str,
+) -> Result<()> {
+    let unknown = find_unknown_globs(checks, available);
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    for glob in &unknown {
+        log::warn!(
+            "The clang-tidy check glob '{glob}' (in {source}) does not match any available checks"
+        );
     }
-    total
+    if strict {
+        return Err(anyhow!(
+            "Unknown clang-tidy checks specified in {source} (with `--strict-checks`): {}",
+            unknown.join(", ")
+        ));
+    }
+    Ok(())
 }
 
-/// Run clang-tidy, then parse and return it's output.
-pub fn run_clang_tidy(
-    file: &mut MutexGuard<FileObj>,
-    clang_params: &ClangParams,
-) -> Result<Vec<(log::Level, std::string::String)>> {
+/// Describe the compile command that clang-tidy used for the given `file`.
+///
+/// This helps to explain why a file failed to be analyzed.
+fn describe_compile_command(file: &Path, clang_params: &ClangParams) -> String {
+    let Some(units) = &clang_params.database_json else {
+        return "No compilation database was used.".to_string();
+    };
+    let file_path = normalize_path(&current_dir().unwrap_or_default().join(file));
+    if let Some(unit) = units.iter().find(|unit| unit.path() == file_path) {
+        return format!(
+            "The compile command used (in {}) was:\n{}",
+            unit.directory,
+            unit.command_line()
+        );
+    }
+    match clang_params.inferred_flags.get(file) {
+        Some(flags) => format!(
+            "No compile command was found in the compilation database. The inferred flags were:\n{}",
+            flags.join(" ")
+        ),
+        None => "No compile command was found in the compilation database.".to_string(),
+    }
+}
+
+/// Create a clang-tidy command with the checks, compilation database and compiler
+/// arguments that apply to the given `file`.
+pub(super) fn tidy_command(file: &Path, clang_params: &ClangParams) -> Command {
     let mut cmd = Command::new(clang_params.clang_tidy_command.as_ref().unwrap());
-    let mut logs = vec![];
+    add_load_args(&mut cmd, &clang_params.tidy_load);
     if !clang_params.tidy_checks.is_empty() {
         cmd.args(["-checks", &clang_params.tidy_checks]);
     }
     if let Some(db) = &clang_params.database {
         cmd.args(["-p", &db.to_string_lossy()]);
     }
-    for arg in &clang_params.extra_args {
+    let inferred_flags = clang_params
+        .inferred_flags
+        .get(file)
+        .map(|flags| flags.as_slice())
+        .unwrap_or_default();
+    for arg in clang_params.extra_args.iter().chain(inferred_flags) {
         cmd.args(["--extra-arg", format!("\"{}\"", arg).as_str()]);
     }
+    cmd
+}
+
+/// Run clang-tidy, then parse and return it's output.
+pub fn run_clang_tidy(
+    file: &FileObj,
+    clang_params: &ClangParams,
+) -> Result<(TidyAdvice, Vec<(log::Level, String)>)> {
+    let mut cmd = tidy_command(&file.name, clang_params);
+    let mut logs = vec![];
     let file_name = file.name.to_string_lossy().to_string();
-    if clang_params.lines_changed_only != LinesChangedOnly::Off {
-        let ranges = file.get_ranges(&clang_params.lines_changed_only);
-        if !ranges.is_empty() {
-            let filter = format!(
-                "[{{\"name\":{:?},\"lines\":{:?}}}]",
-                &file_name.replace('/', if OS == "windows" { "\\" } else { "/" }),
+    let header_unit = clang_params.header_units.get(&file.name);
+    if let Some(unit) = header_unit {
+        // analyze the header through a translation unit that includes it
+        logs.push((
+            log::Level::Info,
+            format!(
+                "Analyzing {file_name} through translation unit {}",
+                unit.to_string_lossy()
+            ),
+        ));
+        cmd.arg(format!(
+            "--header-filter={}$",
+            regex::escape(&file_name.replace('\\', "/"))
+        ));
+    }
+    let ranges = if clang_params.lines_changed_only != LinesChangedOnly::Off {
+        file.get_ranges(&clang_params.lines_changed_only)
+    } else {
+        vec![]
+    };
+    // A header analyzed through a translation unit always needs a line filter,
+    // so diagnostics about the translation unit itself are excluded.
+    if !ranges.is_empty() || header_unit.is_some() {
+        let lines = if ranges.is_empty() {
+            String::new()
+        } else {
+            format!(
+                ",\"lines\":{:?}",
                 ranges
                     .iter()
                     .map(|r| [r.start(), r.end()])
                     .collect::<Vec<_>>()
-            );
-            cmd.args(["--line-filter", filter.as_str()]);
-        }
+            )
+        };
+        let filter = format!(
+            "[{{\"name\":{:?}{lines}}}]",
+            &file_name.replace('/', if OS == "windows" { "\\" } else { "/" }),
+        );
+        cmd.args(["--line-filter", filter.as_str()]);
     }
-    let original_content = if !clang_params.tidy_review {
+    // Fixes are exported (instead of applied), so the file is never modified.
+    let fixes_dir = if !clang_params.tidy_review {
         None
     } else {
-        cmd.arg("--fix-errors");
-        Some(fs::read_to_string(&file.name).with_context(|| {
-            format!(
-                "Failed to cache file's original content before applying clang-tidy changes: {}",
-                file_name.clone()
-            )
-        })?)
+        let tmp_dir = tempfile::tempdir()
+            .with_context(|| "Failed to create a directory for clang-tidy's fixes")?;
+        cmd.arg(format!(
+            "--export-fixes={}",
+            tmp_dir.path().join(FIXES_FILE_NAME).to_string_lossy()
+        ));
+        Some(tmp_dir)
     };
-    if !clang_params.style.is_empty() {
-        cmd.args(["--format-style", clang_params.style.as_str()]);
-    }
-    cmd.arg(file.name.to_string_lossy().as_ref());
+    cmd.arg(header_unit.unwrap_or(&file.name).to_string_lossy().as_ref());
     logs.push((
         log::Level::Info,
         format!(
@@ -319,30 +884,257 @@ pub fn run_clang_tidy(
             ),
         ));
     }
-    file.tidy_advice = Some(parse_tidy_output(
-        &output.stdout,
-        &clang_params.database_json,
-    )?);
-    if clang_params.tidy_review {
-        if let Some(tidy_advice) = &mut file.tidy_advice {
-            // cache file changes in a buffer and restore the original contents for further analysis
-            tidy_advice.patched =
-                Some(fs::read(&file_name).with_context(|| {
-                    format!("Failed to read changes from clang-tidy: {file_name}")
-                })?);
+    if !output.status.success() {
+        logs.push((
+            log::Level::Warn,
+            format!(
+                "clang-tidy failed to analyze {file_name} ({}). {}",
+                output.status,
+                describe_compile_command(header_unit.unwrap_or(&file.name), clang_params)
+            ),
+        ));
+    }
+    let mut tidy_advice = parse_tidy_output(&output.stdout, &clang_params.database_json)?;
+    for note in &mut tidy_advice.notes {
+        note.doc_urls = note
+            .diagnostic
+            .split(',')
+            .filter_map(|check| {
+                tidy_doc_url(
+                    check,
+                    &clang_params.tidy_doc_links,
+                    clang_params.tidy_version.as_deref(),
+                )
+                .map(|url| (check.to_string(), url))
+            })
+            .collect();
+    }
+    if header_unit.is_some() {
+        // only keep the notifications about the header
+        let header_name = file_name.replace('\\', "/");
+        tidy_advice
+            .notes
+            .retain(|note| note.filename == header_name);
+    }
+    if let Some(fixes_dir) = &fixes_dir {
+        let fixes_path = fixes_dir.path().join(FIXES_FILE_NAME);
+        // clang-tidy does not write the file if there is nothing to export
+        let fixes = if fixes_path.is_file() {
+            let yaml = fs::read_to_string(&fixes_path)
+                .with_context(|| format!("Failed to read fixes from clang-tidy: {file_name}"))?;
+            parse_exported_fixes(&yaml)
+                .with_context(|| format!("Failed to parse fixes from clang-tidy: {file_name}"))?
+        } else {
+            ExportedFixes::default()
+        };
+        let original_content = fs::read(&file.name).with_context(|| {
+            format!("Failed to read file's original content to apply clang-tidy fixes: {file_name}")
+        })?;
+        let file_path = &file.name;
+        let mut patched = fixes.apply_to(file_path, &original_content, &mut tidy_advice.notes);
+        // Exported fixes are not formatted (unlike `clang-tidy --fix --format-style`),
+        // so the fixed lines are formatted with clang-format instead.
+        if let (false, Some(exe)) = (
+            clang_params.style.is_empty() || patched == original_content,
+            &clang_params.clang_format_command,
+        ) {
+            let ranges = changed_ranges(file_path, &original_content, &patched);
+            match format_ranges(exe, &clang_params.style, &file_name, &patched, &ranges) {
+                Ok(formatted) => patched = formatted,
+                Err(e) => logs.push((
+                    log::Level::Warn,
+                    format!("Failed to format the fixes from clang-tidy for {file_name}: {e}"),
+                )),
+            }
         }
-        // original_content is guaranteed to be Some() value at this point
-        fs::write(&file_name, original_content.unwrap())
-            .with_context(|| format!("Failed to restore file's original content: {file_name}"))?;
+        tidy_advice.patched = Some(patched);
+    }
+    Ok((tidy_advice, logs))
+}
+
+/// Get the ranges of lines in the `patched` content that differ from the
+/// `original` content.
+fn changed_ranges(file: &Path, original: &[u8], patched: &[u8]) -> Vec<RangeInclusive<u32>> {
+    let Ok(patch) = make_patch(file, patched, original) else {
+        return vec![];
+    };
+    (0..patch.num_hunks())
+        .filter_map(|index| patch.hunk(index).ok())
+        .map(|(hunk, _)| {
+            let start = hunk.new_start().max(1);
+            // a hunk that only removes lines still affects the line at its location
+            start..=start + hunk.new_lines().max(1) - 1
+        })
+        .collect()
+}
+
+/// The name of the file that clang-tidy exports fixes to (in a temporary directory).
+const FIXES_FILE_NAME: &str = "fixes.yaml";
+
+/// A single replacement exported by clang-tidy (using `--export-fixes`).
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+struct ExportedReplacement {
+    file_path: String,
+    offset: u32,
+    length: u32,
+    #[serde(default)]
+    replacement_text: String,
+}
+
+/// The message (and location) of a diagnostic exported by clang-tidy.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+struct ExportedMessage {
+    file_path: String,
+    file_offset: u32,
+    #[serde(default)]
+    replacements: Vec<ExportedReplacement>,
+}
+
+/// A single diagnostic exported by clang-tidy.
+///
+/// Since clang-tidy v9, the location and replacements are nested in the
+/// `DiagnosticMessage`. Older versions put them at the diagnostic's level.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+struct ExportedDiagnostic {
+    diagnostic_name: String,
+    diagnostic_message: Option<ExportedMessage>,
+    file_path: Option<String>,
+    file_offset: Option<u32>,
+    #[serde(default)]
+    replacements: Vec<ExportedReplacement>,
+}
+
+impl ExportedDiagnostic {
+    /// Get the location (file path and byte offset) and replacements of this diagnostic.
+    fn parts(&self) -> (Option<&str>, Option<u32>, &[ExportedReplacement]) {
+        match &self.diagnostic_message {
+            Some(message) => (
+                Some(&message.file_path),
+                Some(message.file_offset),
+                &message.replacements,
+            ),
+            None => (
+                self.file_path.as_deref(),
+                self.file_offset,
+                &self.replacements,
+            ),
+        }
+    }
+}
+
+/// The fixes exported by clang-tidy (using `--export-fixes`).
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+struct ExportedFixes {
+    #[serde(default)]
+    diagnostics: Vec<ExportedDiagnostic>,
+}
+
+/// Parse the YAML document that clang-tidy exported fixes to.
+fn parse_exported_fixes(yaml: &str) -> Result<ExportedFixes> {
+    Ok(serde_yaml_ng::from_str(yaml)?)
+}
+
+impl ExportedFixes {
+    /// Apply the fixes about the given `file` to its `original_content` (in memory) and
+    /// return the patched content.
+    ///
+    /// The lines fixed for each of the given `notes` are also recorded in
+    /// [`TidyNotification::fixed_lines`].
+    ///
+    /// Like `clang-tidy --fix`, a diagnostic's replacements are applied all or nothing.
+    /// If any of them conflicts with a previously accepted replacement, then the whole
+    /// diagnostic's fix is skipped (so a fix is never applied partially).
+    fn apply_to(
+        &self,
+        file: &Path,
+        original_content: &[u8],
+        notes: &mut [TidyNotification],
+    ) -> Vec<u8> {
+        let cur_dir = current_dir().unwrap_or_default();
+        let file_path = normalize_path(&cur_dir.join(file));
+        let file_name = file.to_string_lossy().replace('\\', "/");
+        let is_this_file = |path: &str| normalize_path(&cur_dir.join(path)) == file_path;
+        let mut replacements: Vec<Replacement> = vec![];
+        for diagnostic in &self.diagnostics {
+            let (path, offset, fixes) = diagnostic.parts();
+            let fixes = fixes
+                .iter()
+                .filter(|fix| is_this_file(&fix.file_path))
+                .map(|fix| Replacement {
+                    offset: fix.offset,
+                    length: fix.length,
+                    text: fix.replacement_text.clone(),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>();
+            if fixes.is_empty() {
+                continue;
+            }
+            // the same fix may be suggested by multiple diagnostics
+            let new_fixes = fixes
+                .iter()
+                .filter(|fix| !replacements.contains(fix))
+                .cloned()
+                .collect::<Vec<_>>();
+            if new_fixes.iter().any(|fix| {
+                fix.offset as usize + fix.length as usize > original_content.len()
+                    || replacements.iter().any(|other| conflicts(fix, other))
+            }) {
+                continue;
+            }
+            if let (Some(path), Some(offset)) = (path, offset) {
+                if is_this_file(path) {
+                    let line = get_line_count_from_offset(original_content, offset);
+                    let note = notes.iter_mut().find(|note| {
+                        note.filename == file_name
+                            && note.line == line
+                            && note
+                                .diagnostic
+                                .split(',')
+                                .any(|name| name == diagnostic.diagnostic_name)
+                    });
+                    if let Some(note) = note {
+                        for fix in &fixes {
+                            let fixed_line =
+                                get_line_count_from_offset(original_content, fix.offset);
+                            if !note.fixed_lines.contains(&fixed_line) {
+                                note.fixed_lines.push(fixed_line);
+                            }
+                        }
+                    }
+                }
+            }
+            replacements.extend(new_fixes);
+        }
+        apply_replacements(original_content, &replacements)
+    }
+}
+
+/// Do the given replacements conflict (ie change overlapping ranges)?
+///
+/// An insertion conflicts with a replacement only if it is strictly inside the
+/// replaced range.
+fn conflicts(a: &Replacement, b: &Replacement) -> bool {
+    let (a_start, a_end) = (a.offset, a.offset + a.length);
+    let (b_start, b_end) = (b.offset, b.offset + b.length);
+    if a.length == 0 || b.length == 0 {
+        (b_start < a_start && a_start < b_end) || (a_start < b_start && b_start < a_end)
+    } else {
+        a_start < b_end && b_start < a_end
     }
-    Ok(logs)
 }
 
 #[cfg(test)]
 mod test {
     use std::{
+        collections::HashMap,
         env,
-        path::PathBuf,
+        path::{Path, PathBuf},
+        str::FromStr,
         sync::{Arc, Mutex},
     };
 
@@ -355,7 +1147,135 @@ mod test {
     };
 
     use super::run_clang_tidy;
-    use super::TidyNotification;
+    use super::{
+        changed_ranges, find_doc_url, parse_tidy_output, tidy_doc_url, TidyDocLink,
+        TidyNotification,
+    };
+    use super::{
+        find_unknown_globs, parse_check_list, parse_config_checks, parse_exported_fixes,
+        validate_checks,
+    };
+
+    #[test]
+    fn apply_exported_fixes() {
+        let file = env::temp_dir().join("demo.cpp");
+        let file_name = file.to_string_lossy().to_string();
+        let content = b"int main() {\n  int *p = 0;\n  return 0;\n}\n";
+        // The second diagnostic uses the format of clang-tidy v8 (and older).
+        let yaml = format!(
+            r#"---
+MainSourceFile: '{file_name}'
+Diagnostics:
+  - DiagnosticName: modernize-use-nullptr
+    DiagnosticMessage:
+      Message: use nullptr
+      FilePath: '{file_name}'
+      FileOffset: 24
+      Replacements:
+        - FilePath: '{file_name}'
+          Offset: 24
+          Length: 1
+          ReplacementText: nullptr
+    Level: Warning
+  - DiagnosticName: readability-duplicate
+    Message: duplicate fix
+    FilePath: '{file_name}'
+    FileOffset: 24
+    Replacements:
+      - FilePath: '{file_name}'
+        Offset: 24
+        Length: 1
+        ReplacementText: nullptr
+      - FilePath: '/other/file.cpp'
+        Offset: 0
+        Length: 3
+        ReplacementText: ''
+...
+"#
+        );
+        let fixes = parse_exported_fixes(&yaml).unwrap();
+        assert_eq!(fixes.diagnostics.len(), 2);
+        let mut notes = vec![TidyNotification {
+            filename: file_name.replace('\\', "/"),
+            line: 2,
+            cols: 12,
+            rationale: String::from("use nullptr"),
+            severity: String::from("warning"),
+            diagnostic: String::from("modernize-use-nullptr"),
+            suggestion: vec![],
+            fixed_lines: vec![],
+            doc_urls: vec![],
+        }];
+        let patched = fixes.apply_to(&file, content, &mut notes);
+        assert_eq!(
+            String::from_utf8(patched).unwrap(),
+            "int main() {\n  int *p = nullptr;\n  return 0;\n}\n"
+        );
+        assert_eq!(notes[0].fixed_lines, vec![2]);
+    }
+
+    #[test]
+    fn conflicting_fixes() {
+        let file = env::temp_dir().join("demo.cpp");
+        let file_name = file.to_string_lossy().to_string();
+        let content = b"int foo = 1;\nint bar = foo;\n";
+        // The second diagnostic conflicts with the first one's second replacement,
+        // so none of its replacements are applied.
+        let yaml = format!(
+            r#"---
+MainSourceFile: '{file_name}'
+Diagnostics:
+  - DiagnosticName: hicpp-rename
+    DiagnosticMessage:
+      Message: rename foo
+      FilePath: '{file_name}'
+      FileOffset: 4
+      Replacements:
+        - FilePath: '{file_name}'
+          Offset: 4
+          Length: 3
+          ReplacementText: baz
+        - FilePath: '{file_name}'
+          Offset: 23
+          Length: 3
+          ReplacementText: baz
+  - DiagnosticName: readability-conflict
+    DiagnosticMessage:
+      Message: conflicting fix
+      FilePath: '{file_name}'
+      FileOffset: 17
+      Replacements:
+        - FilePath: '{file_name}'
+          Offset: 0
+          Length: 3
+          ReplacementText: long
+        - FilePath: '{file_name}'
+          Offset: 17
+          Length: 9
+          ReplacementText: qux = 1
+...
+"#
+        );
+        let fixes = parse_exported_fixes(&yaml).unwrap();
+        let mut notes = vec![TidyNotification {
+            filename: file_name.replace('\\', "/"),
+            line: 1,
+            cols: 5,
+            rationale: String::from("rename foo"),
+            severity: String::from("warning"),
+            // an aliased diagnostic
+            diagnostic: String::from("hicpp-rename,readability-rename"),
+            suggestion: vec![],
+            fixed_lines: vec![],
+            doc_urls: vec![],
+        }];
+        let patched = fixes.apply_to(&file, content, &mut notes);
+        assert_eq!(
+            String::from_utf8(patched).unwrap(),
+            "int baz = 1;\nint bar = baz;\n"
+        );
+        assert_eq!(notes[0].fixed_lines, vec![1, 2]);
+    }
 
     #[test]
     fn clang_diagnostic_link() {
@@ -368,6 +1288,7 @@ mod test {
             diagnostic: String::from("clang-diagnostic-error"),
             suggestion: vec![],
             fixed_lines: vec![],
+            doc_urls: vec![],
         };
         assert_eq!(note.diagnostic_link(), note.diagnostic);
     }
@@ -385,6 +1306,7 @@ mod test {
             diagnostic: String::from("clang-analyzer-core.NullDereference"),
             suggestion: vec![],
             fixed_lines: vec![],
+            doc_urls: vec![],
         };
         let expected = format!(
             "[{}](https://clang.llvm.org/extra/clang-tidy/checks/{}/{}.html)",
@@ -393,6 +1315,101 @@ mod test {
         assert_eq!(note.diagnostic_link(), expected);
     }
 
+    #[test]
+    fn custom_doc_link() {
+        assert!(TidyDocLink::from_str("acme-").is_err());
+        assert!(TidyDocLink::from_str("=https://docs.acme.dev").is_err());
+        let links = vec![
+            TidyDocLink::from_str("acme-=https://docs.acme.dev/{name}.html").unwrap(),
+            TidyDocLink::from_str("acme-internal-=https://wiki.acme.dev/tidy#{check}").unwrap(),
+        ];
+        assert_eq!(
+            find_doc_url("acme-no-raw-new", &links).as_deref(),
+            Some("https://docs.acme.dev/no-raw-new.html")
+        );
+        assert_eq!(
+            find_doc_url("acme-internal-lock-order", &links).as_deref(),
+            Some("https://wiki.acme.dev/tidy#acme-internal-lock-order")
+        );
+        assert!(find_doc_url("bugprone-use-after-move", &links).is_none());
+
+        let note = TidyNotification {
+            filename: String::from("some_src.cpp"),
+            line: 1,
+            cols: 1,
+            rationale: String::from("raw new is not allowed"),
+            severity: String::from("warning"),
+            diagnostic: String::from("acme-no-raw-new"),
+            suggestion: vec![],
+            fixed_lines: vec![],
+            doc_urls: vec![(
+                String::from("acme-no-raw-new"),
+                find_doc_url("acme-no-raw-new", &links).unwrap(),
+            )],
+        };
+        assert_eq!(
+            note.diagnostic_link(),
+            "[acme-no-raw-new](https://docs.acme.dev/no-raw-new.html)"
+        );
+    }
+
+    #[test]
+    fn fixed_line_ranges() {
+        let original = b"int a;\nint b;\nint c;\nint d;\n";
+        let patched = b"int a;\nint  b;\nint c;\n";
+        assert_eq!(
+            changed_ranges(Path::new("demo.cpp"), original, patched),
+            vec![2..=2, 3..=3]
+        );
+        assert!(changed_ranges(Path::new("demo.cpp"), original, original).is_empty());
+    }
+
+    #[test]
+    fn versioned_links() {
+        assert_eq!(
+            tidy_doc_url("bugprone-use-after-move", &[], Some("14.0.6")).as_deref(),
+            Some("https://releases.llvm.org/14.0.0/tools/clang/tools/extra/docs/clang-tidy/checks/bugprone-use-after-move.html")
+        );
+        assert_eq!(
+            tidy_doc_url("clang-analyzer-core.NullDereference", &[], Some("17.0.6")).as_deref(),
+            Some("https://releases.llvm.org/17.0.1/tools/clang/tools/extra/docs/clang-tidy/checks/clang-analyzer/core.NullDereference.html")
+        );
+        assert_eq!(
+            tidy_doc_url("modernize-use-auto", &[], Some("18.1.8")).as_deref(),
+            Some("https://releases.llvm.org/18.1.0/tools/clang/tools/extra/docs/clang-tidy/checks/modernize/use-auto.html")
+        );
+        assert_eq!(
+            tidy_doc_url("clang-diagnostic-unused-variable", &[], Some("16")).as_deref(),
+            Some("https://releases.llvm.org/16.0.0/tools/clang/docs/DiagnosticsReference.html#wunused-variable")
+        );
+        assert!(tidy_doc_url("clang-diagnostic-error", &[], Some("16")).is_none());
+        let links = vec![TidyDocLink::from_str("bugprone-=https://docs.acme.dev/{check}").unwrap()];
+        assert_eq!(
+            tidy_doc_url("bugprone-use-after-move", &links, Some("14")).as_deref(),
+            Some("https://docs.acme.dev/bugprone-use-after-move")
+        );
+    }
+
+    #[test]
+    fn alias_links() {
+        let stdout = b"tests/demo/demo.cpp:4:5: warning: do not declare C-style arrays [hicpp-avoid-c-arrays,modernize-avoid-c-arrays]\n";
+        let advice = parse_tidy_output(stdout, &None).unwrap();
+        let mut note = advice.notes[0].clone();
+        assert_eq!(
+            note.diagnostic,
+            "hicpp-avoid-c-arrays,modernize-avoid-c-arrays"
+        );
+        note.doc_urls = vec![(
+            String::from("hicpp-avoid-c-arrays"),
+            tidy_doc_url("hicpp-avoid-c-arrays", &[], Some("14")).unwrap(),
+        )];
+        assert_eq!(
+            note.diagnostic_link(),
+            "[hicpp-avoid-c-arrays](https://releases.llvm.org/14.0.0/tools/clang/tools/extra/docs/clang-tidy/checks/hicpp-avoid-c-arrays.html), \
+             [modernize-avoid-c-arrays](https://clang.llvm.org/extra/clang-tidy/checks/modernize/avoid-c-arrays.html)"
+        );
+    }
+
     // ***************** test for regex parsing of clang-tidy stdout
 
     #[test]
@@ -428,20 +1445,35 @@ mod test {
         let clang_params = ClangParams {
             style: "".to_string(),
             tidy_checks: "".to_string(), // use .clang-tidy config file
+            strict_checks: false,
+            nolint_audit: false,
             lines_changed_only: LinesChangedOnly::Off,
             database: None,
             extra_args: extra_args.clone(), // <---- the reason for this test
             database_json: None,
             format_filter: None,
             tidy_filter: None,
+            analyzer_filter: None,
             tidy_review: false,
             format_review: false,
             clang_tidy_command: Some(exe_path),
             clang_format_command: None,
+            overrides: vec![],
+            header_units: HashMap::new(),
+            analyzer_names: vec![],
+            analyzers: vec![],
+            rules: vec![],
+            jobs: 0,
+            analyzer_timeout: None,
+            tidy_load: vec![],
+            tidy_doc_links: vec![],
+            tidy_version: None,
+            diff_available: false,
+            inferred_flags: HashMap::new(),
         };
-        let mut file_lock = arc_ref.lock().unwrap();
-        let logs = run_clang_tidy(&mut file_lock, &clang_params)
-            .unwrap()
+        let file_lock = arc_ref.lock().unwrap();
+        let (_advice, logs) = run_clang_tidy(&file_lock, &clang_params).unwrap();
+        let logs = logs
             .into_iter()
             .filter_map(|(_lvl, msg)| {
                 if msg.contains("Running ") {
@@ -461,4 +1493,35 @@ mod test {
             assert!(args.contains(&extra_arg.as_str()));
         }
     }
+
+    #[test]
+    fn check_list() {
+        let stdout = b"Enabled checks:\n    bugprone-use-after-move\n    modernize-use-auto\n\n";
+        let available = parse_check_list(stdout);
+        assert_eq!(available, ["bugprone-use-after-move", "modernize-use-auto"]);
+
+        let checks = "-*,bugprone-use-after-mov,modernize-*,-readability-*";
+        assert_eq!(
+            find_unknown_globs(checks, &available),
+            ["bugprone-use-after-mov", "-readability-*"]
+        );
+        assert!(validate_checks(checks, &available, false, "`--tidy-checks`").is_ok());
+        assert!(validate_checks(checks, &available, true, "`--tidy-checks`").is_err());
+        assert!(validate_checks("-*,modernize-*", &available, true, "`--tidy-checks`").is_ok());
+
+        // compiler diagnostics are not listed by clang-tidy
+        let checks = "-*,clang-diagnostic-*,-clang-diagnostic-unused-variable";
+        assert!(find_unknown_globs(checks, &available).is_empty());
+        assert!(validate_checks(checks, &available, true, "`--tidy-checks`").is_ok());
+    }
+
+    #[test]
+    fn config_checks() {
+        let yaml = "---\nChecks: 'clang-diagnostic-*,bugprone-*'\nWarningsAsErrors: ''\nHeaderFilterRegex: ''\n...\n";
+        assert_eq!(
+            parse_config_checks(yaml).as_deref(),
+            Some("clang-diagnostic-*,bugprone-*")
+        );
+        assert!(parse_config_checks("---\nWarningsAsErrors: ''\n").is_none());
+    }
 }
@@ -0,0 +1,657 @@
+//! This module holds functionality related to reading a JSON compilation database
+//! (`compile_commands.json`).
+
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::common_fs::normalize_path;
+
+/// The name of a JSON compilation database file.
+pub const DATABASE_FILE_NAME: &str = "compile_commands.json";
+
+/// The name of a file that lists compiler flags (one per line).
+///
+/// This is used by clang tools when no compilation database is present.
+pub const COMPILE_FLAGS_FILE_NAME: &str = "compile_flags.txt";
+
+/// Compiler flags whose value is a path, which may be given as a separate argument
+/// or joined with the flag (ie `-Iinclude`).
+const PATH_FLAGS: [&str; 6] = [
+    "-I",
+    "-isystem",
+    "-iquote",
+    "-idirafter",
+    "-include",
+    "-imacros",
+];
+
+/// Compiler flags that take a separate value but are irrelevant for analysis.
+const OUTPUT_FLAGS: [&str; 4] = ["-o", "-MF", "-MT", "-MQ"];
+
+/// Used to (de)serialize a json compilation database's translation unit.
+///
+/// This serves to normalize relative paths for build systems that use/need
+/// relative paths (ie ninja), to list the translation units that are built, and
+/// to reason about the compiler flags used for each file.
+///
+/// See the [JSON Compilation Database Format Specification](https://clang.llvm.org/docs/JSONCompilationDatabase.html).
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct CompilationUnit {
+    /// The directory of the build environment
+    pub directory: String,
+
+    /// The file path of the translation unit.
+    ///
+    /// Sometimes, this is relative to the build [`CompilationUnit::directory`].
+    ///
+    /// This is typically the path that clang-tidy uses in its stdout (for a dry run).
+    /// So, having this information helps with matching clang-tidy's stdout with the
+    /// repository files.
+    pub file: String,
+
+    /// The compile command as a single shell-escaped string.
+    ///
+    /// Either this or [`CompilationUnit::arguments`] is expected to be present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+
+    /// The compile command as a list of arguments.
+    ///
+    /// This takes precedence over [`CompilationUnit::command`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Vec<String>>,
+
+    /// The name of the output created by this compilation step.
+    ///
+    /// This can be used to distinguish different build configurations of the
+    /// same file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+impl CompilationUnit {
+    /// Get the normalized absolute path to this translation unit's
+    /// [`CompilationUnit::directory`].
+    ///
+    /// A relative [`CompilationUnit::directory`] is assumed to be relative to the
+    /// current working directory.
+    pub fn directory_path(&self) -> PathBuf {
+        let directory = Path::new(&self.directory);
+        if directory.is_absolute() {
+            normalize_path(directory)
+        } else {
+            normalize_path(&std::env::current_dir().unwrap_or_default().join(directory))
+        }
+    }
+
+    /// Get the normalized absolute path to this translation unit's [`CompilationUnit::file`].
+    pub fn path(&self) -> PathBuf {
+        normalize_path(&self.directory_path().join(&self.file))
+    }
+
+    /// Get the compile command's arguments.
+    ///
+    /// If only [`CompilationUnit::command`] is present, then it is split as a shell
+    /// would split it.
+    pub fn args(&self) -> Vec<String> {
+        if let Some(arguments) = &self.arguments {
+            arguments.clone()
+        } else if let Some(command) = &self.command {
+            shlex::split(command).unwrap_or_default()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Get the compile command as a single string (suitable for display).
+    pub fn command_line(&self) -> String {
+        match (&self.arguments, &self.command) {
+            (Some(arguments), _) => shlex::try_join(arguments.iter().map(String::as_str))
+                .unwrap_or_else(|_| arguments.join(" ")),
+            (None, Some(command)) => command.clone(),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Does this unit's build configuration match the given `config`?
+    ///
+    /// A unit matches if the `config` value is a path component (ie a directory name)
+    /// of its [`CompilationUnit::output`] or [`CompilationUnit::directory`].
+    pub fn is_config(&self, config: &str) -> bool {
+        let has_component = |path: &str| {
+            Path::new(path)
+                .components()
+                .any(|c| c.as_os_str() == config)
+        };
+        self.output.as_deref().is_some_and(has_component) || has_component(&self.directory)
+    }
+
+    /// Does this unit's compile command have an argument equal to the given `config`
+    /// value (or an argument that assigns it, ie `-DCMAKE_BUILD_TYPE=Release`)?
+    pub fn has_config_arg(&self, config: &str) -> bool {
+        self.args().iter().any(|arg| {
+            arg == config
+                || arg
+                    .rsplit_once('=')
+                    .is_some_and(|(_, value)| value == config)
+        })
+    }
+
+    /// Get the (absolute) include directories of this unit (ie from `-I` flags).
+    pub fn include_dirs(&self) -> Vec<PathBuf> {
+        self.reusable_flags()
+            .iter()
+            .filter_map(|flag| {
+                ["-I", "-isystem", "-iquote", "-idirafter"]
+                    .iter()
+                    .find_map(|prefix| flag.strip_prefix(prefix))
+                    .map(PathBuf::from)
+            })
+            .collect()
+    }
+
+    /// Get the compiler flags of this unit that can be reused for a different file.
+    ///
+    /// The compiler, the unit's file, and any output-related flags are excluded.
+    /// Relative paths in include flags are made absolute (relative to the unit's
+    /// [`CompilationUnit::directory`]).
+    pub fn reusable_flags(&self) -> Vec<String> {
+        let directory = self.directory_path();
+        let args = self.args();
+        let unit_path = self.path();
+        let mut flags = Vec::new();
+        // skip the compiler (first argument)
+        let mut args_iter = args.iter().skip(1);
+        while let Some(arg) = args_iter.next() {
+            if OUTPUT_FLAGS.contains(&arg.as_str()) {
+                args_iter.next();
+                continue;
+            }
+            if arg == "-c"
+                || OUTPUT_FLAGS.iter().any(|f| arg.starts_with(f))
+                || normalize_path(&directory.join(arg)) == unit_path
+            {
+                continue;
+            }
+            if PATH_FLAGS.contains(&arg.as_str()) {
+                if let Some(value) = args_iter.next() {
+                    flags.push(format!("{arg}{}", absolute_flag_path(&directory, value)));
+                }
+                continue;
+            }
+            match PATH_FLAGS.iter().find(|f| arg.starts_with(*f)) {
+                Some(flag) => {
+                    flags.push(format!(
+                        "{flag}{}",
+                        absolute_flag_path(&directory, &arg[flag.len()..])
+                    ));
+                }
+                None => flags.push(arg.clone()),
+            }
+        }
+        flags
+    }
+}
+
+/// Make the `value` of a path flag absolute (relative to the given `directory`).
+fn absolute_flag_path(directory: &Path, value: &str) -> String {
+    normalize_path(&directory.join(value))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Infer compiler flags for a `file` that has no entry in the compilation database
+/// `units`.
+///
+/// This is similar to what clangd does:
+///
+/// 1. Use the flags of a translation unit located in the same directory as the
+///    `file`. A unit with the same file extension is preferred.
+/// 2. Use the flags listed in the nearest `compile_flags.txt` file (found in the
+///    `file`'s directory or any parent directory up to `root`).
+///
+/// Returns [`None`] if the `file` has an entry in the database or no flags could be
+/// inferred. Otherwise, returns a description of where the flags came from along with
+/// the inferred flags.
+pub fn infer_flags(
+    file: &Path,
+    units: &[CompilationUnit],
+    root: &Path,
+) -> Option<(String, Vec<String>)> {
+    let file = normalize_path(&root.join(file));
+    if units.iter().any(|unit| unit.path() == file) {
+        return None;
+    }
+    let parent = file.parent()?;
+    let sibling = units
+        .iter()
+        .filter(|unit| unit.path().parent() == Some(parent))
+        .min_by_key(|unit| unit.path().extension() != file.extension());
+    if let Some(unit) = sibling {
+        let flags = unit.reusable_flags();
+        if !flags.is_empty() {
+            return Some((unit.path().to_string_lossy().to_string(), flags));
+        }
+    }
+    let mut dir = Some(parent);
+    while let Some(current) = dir.filter(|d| d.starts_with(root)) {
+        let flags_file = current.join(COMPILE_FLAGS_FILE_NAME);
+        if let Ok(content) = fs::read_to_string(&flags_file) {
+            let flags = content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(
+                    |line| match PATH_FLAGS.iter().find(|f| line.starts_with(*f)) {
+                        Some(flag) if line.len() > flag.len() => format!(
+                            "{flag}{}",
+                            absolute_flag_path(current, line[flag.len()..].trim_start())
+                        ),
+                        _ => line.to_string(),
+                    },
+                )
+                .collect::<Vec<_>>();
+            return Some((flags_file.to_string_lossy().to_string(), flags));
+        }
+        dir = current.parent();
+    }
+    None
+}
+
+/// Read and parse the `compile_commands.json` file in the given `db_path` directory.
+pub fn load_database(db_path: &Path) -> Result<Vec<CompilationUnit>> {
+    let db_file = db_path.join(DATABASE_FILE_NAME);
+    let db_str = fs::read(&db_file)
+        .with_context(|| format!("Failed to read {}", db_file.to_string_lossy()))?;
+    // A compilation database should be UTF-8 encoded, but file paths are not; use lossy conversion.
+    serde_json::from_str::<Vec<CompilationUnit>>(&String::from_utf8_lossy(&db_str)).with_context(
+        || {
+            format!(
+                "Failed to parse {}; it is not a valid JSON compilation database",
+                db_file.to_string_lossy()
+            )
+        },
+    )
+}
+
+/// Remove duplicate entries (for the same file) from the given compilation database
+/// `units`.
+///
+/// If a file has multiple entries (ie for different build configurations), then the
+/// first entry that matches the given `config` is kept (see
+/// [`CompilationUnit::is_config()`]). The compile commands are only considered if
+/// no entry matches (see [`CompilationUnit::has_config_arg()`]). If no `config` is
+/// given or no entry matches,
+/// then the first entry is kept. The order of the `units` is preserved.
+pub fn dedupe_units(units: Vec<CompilationUnit>, config: Option<&str>) -> Vec<CompilationUnit> {
+    let mut order: Vec<PathBuf> = Vec::new();
+    let mut entries: HashMap<PathBuf, Vec<CompilationUnit>> = HashMap::new();
+    for unit in units {
+        let path = unit.path();
+        if !entries.contains_key(&path) {
+            order.push(path.clone());
+        }
+        entries.entry(path).or_default().push(unit);
+    }
+    order
+        .into_iter()
+        .filter_map(|path| {
+            let mut candidates = entries.remove(&path)?;
+            if candidates.len() > 1 {
+                log::debug!(
+                    "Found {} compilation database entries for {}",
+                    candidates.len(),
+                    path.to_string_lossy()
+                );
+            }
+            let selected = config
+                .and_then(|config| {
+                    candidates
+                        .iter()
+                        .position(|u| u.is_config(config))
+                        .or_else(|| candidates.iter().position(|u| u.has_config_arg(config)))
+                })
+                .unwrap_or_default();
+            Some(candidates.swap_remove(selected))
+        })
+        .collect()
+}
+
+/// Write the given `units` as a `compile_commands.json` file in the given `db_path`
+/// directory.
+///
+/// All [`CompilationUnit::directory`] values are written as absolute paths.
+pub fn write_database(units: &[CompilationUnit], db_path: &Path) -> Result<()> {
+    let units = units
+        .iter()
+        .map(|unit| CompilationUnit {
+            directory: unit.directory_path().to_string_lossy().to_string(),
+            ..unit.clone()
+        })
+        .collect::<Vec<_>>();
+    let db_file = db_path.join(DATABASE_FILE_NAME);
+    fs::write(&db_file, serde_json::to_string_pretty(&units)?)
+        .with_context(|| format!("Failed to write {}", db_file.to_string_lossy()))
+}
+
+/// Ensure all translation units in the given compilation database `units` exist.
+///
+/// The `db_path` is only used for the error message.
+pub fn validate_database(units: &[CompilationUnit], db_path: &Path) -> Result<()> {
+    let missing = units
+        .iter()
+        .map(|unit| unit.path())
+        .filter(|path| !path.is_file())
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "The compilation database in {} references {} file(s) that do not exist (is it outdated?):\n{}",
+        db_path.to_string_lossy(),
+        missing.len(),
+        missing
+            .iter()
+            .map(|path| format!("  {}", path.to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+/// The name of CMake's presets files (in order of precedence).
+const CMAKE_PRESETS_FILE_NAMES: [&str; 2] = ["CMakeUserPresets.json", "CMakePresets.json"];
+
+/// Get the build directories (`binaryDir`) of the configure presets in the CMake
+/// presets files located in the given `root` directory.
+///
+/// Only the `${sourceDir}` and `${presetName}` macros are expanded. Presets that use
+/// other macros are skipped.
+fn cmake_preset_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    for name in CMAKE_PRESETS_FILE_NAMES {
+        let Ok(content) = fs::read_to_string(root.join(name)) else {
+            continue;
+        };
+        let presets = match serde_json::from_str::<serde_json::Value>(&content) {
+            Ok(presets) => presets,
+            Err(e) => {
+                log::debug!("Failed to parse {name}: {e}");
+                continue;
+            }
+        };
+        let Some(configure_presets) = presets["configurePresets"].as_array() else {
+            continue;
+        };
+        for preset in configure_presets {
+            let (Some(preset_name), Some(binary_dir)) =
+                (preset["name"].as_str(), preset["binaryDir"].as_str())
+            else {
+                continue;
+            };
+            let binary_dir = binary_dir
+                .replace("${sourceDir}", &root.to_string_lossy())
+                .replace("${presetName}", preset_name);
+            if !binary_dir.contains("${") {
+                dirs.push(root.join(binary_dir));
+            }
+        }
+    }
+    dirs
+}
+
+/// List the sub-directories of `root` whose name starts with the given `prefix`.
+fn list_dirs(root: &Path, prefix: &str) -> Vec<PathBuf> {
+    let mut dirs = fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.is_dir()
+                        && path
+                            .file_name()
+                            .is_some_and(|name| name.to_string_lossy().starts_with(prefix))
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+/// Search the given `root` directory for a compilation database.
+///
+/// The following directories are searched:
+///
+/// - the `binaryDir` of configure presets in `CMakeUserPresets.json` or `CMakePresets.json`
+/// - `build/`
+/// - `out/*/`
+/// - `cmake-build-*/`
+/// - the `root` directory
+///
+/// If multiple databases are found, then the most recently modified one is selected.
+/// Returns the path to the directory that contains the selected database.
+pub fn discover_database(root: &Path) -> Option<PathBuf> {
+    let mut candidates = cmake_preset_dirs(root);
+    candidates.push(root.join("build"));
+    candidates.extend(list_dirs(&root.join("out"), ""));
+    candidates.extend(list_dirs(root, "cmake-build-"));
+    candidates.push(root.to_path_buf());
+    let mut found = candidates
+        .into_iter()
+        .filter_map(|dir| {
+            let modified = fs::metadata(dir.join(DATABASE_FILE_NAME))
+                .and_then(|meta| meta.modified())
+                .ok()?;
+            log::debug!("Found a compilation database in {}", dir.to_string_lossy());
+            Some((dir, modified))
+        })
+        .collect::<Vec<_>>();
+    // stable sort keeps the search order for databases with the same timestamp
+    found.sort_by_key(|(_, modified)| Reverse(*modified));
+    found.into_iter().next().map(|(dir, _)| dir)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use tempfile::tempdir;
+
+    use super::{
+        dedupe_units, discover_database, infer_flags, load_database, validate_database,
+        write_database, CompilationUnit, COMPILE_FLAGS_FILE_NAME, DATABASE_FILE_NAME,
+    };
+
+    #[test]
+    fn load_db() {
+        let tmp = tempdir().unwrap();
+        let build_dir = tmp.path().join("build");
+        fs::write(
+            tmp.path().join(DATABASE_FILE_NAME),
+            format!(
+                r#"[{{"directory": "{}", "file": "../src/demo.cpp", "command": "c++ -c ../src/demo.cpp"}}]"#,
+                build_dir.to_string_lossy().replace('\\', "/")
+            ),
+        )
+        .unwrap();
+        let units = load_database(tmp.path()).unwrap();
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].path(), tmp.path().join("src").join("demo.cpp"));
+    }
+
+    #[test]
+    fn load_missing_db() {
+        let tmp = tempdir().unwrap();
+        assert!(load_database(tmp.path()).is_err());
+    }
+
+    #[test]
+    fn reusable_flags() {
+        let unit = CompilationUnit {
+            directory: "/proj/build".to_string(),
+            file: "../src/demo.cpp".to_string(),
+            command: Some(
+                "/usr/bin/c++ -DDEMO -I../include -isystem /opt/include -std=c++17 -o demo.o -c ../src/demo.cpp"
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+        assert_eq!(
+            unit.reusable_flags(),
+            vec![
+                "-DDEMO",
+                "-I/proj/include",
+                "-isystem/opt/include",
+                "-std=c++17"
+            ]
+        );
+    }
+
+    #[test]
+    fn infer_from_sibling_or_flags_file() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path();
+        for dir in ["src", "tools"] {
+            fs::create_dir(root.join(dir)).unwrap();
+        }
+        let units = vec![CompilationUnit {
+            directory: root.to_string_lossy().to_string(),
+            file: "src/demo.cpp".to_string(),
+            command: None,
+            arguments: Some(
+                ["c++", "-Iinclude", "-c", "src/demo.cpp"]
+                    .map(String::from)
+                    .to_vec(),
+            ),
+            ..Default::default()
+        }];
+
+        // files in the database don't need inferred flags
+        assert!(infer_flags(&PathBuf::from("src/demo.cpp"), &units, root).is_none());
+
+        let (source, flags) = infer_flags(&PathBuf::from("src/other.cpp"), &units, root).unwrap();
+        assert_eq!(PathBuf::from(source), root.join("src").join("demo.cpp"));
+        assert_eq!(
+            flags,
+            vec![format!("-I{}", root.join("include").to_string_lossy())]
+        );
+
+        // no sibling and no compile_flags.txt
+        assert!(infer_flags(&PathBuf::from("tools/tool.cpp"), &units, root).is_none());
+
+        fs::write(
+            root.join(COMPILE_FLAGS_FILE_NAME),
+            "-std=c++20\n-Ithird_party\n",
+        )
+        .unwrap();
+        let (source, flags) = infer_flags(&PathBuf::from("tools/tool.cpp"), &units, root).unwrap();
+        assert_eq!(PathBuf::from(source), root.join(COMPILE_FLAGS_FILE_NAME));
+        assert_eq!(
+            flags,
+            vec![
+                "-std=c++20".to_string(),
+                format!("-I{}", root.join("third_party").to_string_lossy())
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_db() {
+        let tmp = tempdir().unwrap();
+        fs::write(tmp.path().join(DATABASE_FILE_NAME), "{not json").unwrap();
+        let err = load_database(tmp.path()).unwrap_err();
+        assert!(format!("{err:#}").contains("not a valid JSON compilation database"));
+
+        let units = vec![CompilationUnit {
+            directory: tmp.path().to_string_lossy().to_string(),
+            file: "missing.cpp".to_string(),
+            ..Default::default()
+        }];
+        assert!(validate_database(&units, tmp.path()).is_err());
+        fs::write(tmp.path().join("missing.cpp"), "").unwrap();
+        assert!(validate_database(&units, tmp.path()).is_ok());
+    }
+
+    #[test]
+    fn discover_db() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path();
+        assert!(discover_database(root).is_none());
+
+        let cmake_dir = root.join("cmake-build-debug");
+        fs::create_dir(&cmake_dir).unwrap();
+        fs::write(cmake_dir.join(DATABASE_FILE_NAME), "[]").unwrap();
+        assert_eq!(discover_database(root), Some(cmake_dir));
+
+        // a newer database is preferred
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(
+            root.join("CMakePresets.json"),
+            r#"{"version": 3, "configurePresets": [{"name": "ci", "binaryDir": "${sourceDir}/out/${presetName}"}]}"#,
+        )
+        .unwrap();
+        let preset_dir = root.join("out").join("ci");
+        fs::create_dir_all(&preset_dir).unwrap();
+        fs::write(preset_dir.join(DATABASE_FILE_NAME), "[]").unwrap();
+        let found = discover_database(root).unwrap();
+        assert_eq!(
+            fs::canonicalize(found).unwrap(),
+            fs::canonicalize(preset_dir).unwrap()
+        );
+    }
+
+    #[test]
+    fn dedupe_and_select_config() {
+        let tmp = tempdir().unwrap();
+        let unit = |file: &str, output: &str| CompilationUnit {
+            directory: tmp.path().to_string_lossy().to_string(),
+            file: file.to_string(),
+            arguments: Some(["c++", "-c", file, "-o", output].map(String::from).to_vec()),
+            output: Some(output.to_string()),
+            ..Default::default()
+        };
+        let units = vec![
+            unit("a.cpp", "debug/a.o"),
+            unit("b.cpp", "debug/b.o"),
+            unit("a.cpp", "release/a.o"),
+        ];
+
+        let deduped = dedupe_units(units.clone(), None);
+        assert_eq!(deduped, vec![units[0].clone(), units[1].clone()]);
+
+        let deduped = dedupe_units(units.clone(), Some("release"));
+        assert_eq!(deduped, vec![units[2].clone(), units[1].clone()]);
+        assert_eq!(deduped[0].command_line(), "c++ -c a.cpp -o release/a.o");
+
+        // round trip
+        write_database(&deduped, tmp.path()).unwrap();
+        assert_eq!(load_database(tmp.path()).unwrap(), deduped);
+
+        // only whole path components match
+        let units = vec![
+            unit("a.cpp", "RelWithDebInfo/a.o"),
+            unit("a.cpp", "Release/a.o"),
+        ];
+        let deduped = dedupe_units(units.clone(), Some("Release"));
+        assert_eq!(deduped, vec![units[1].clone()]);
+
+        // fall back to the compile command's arguments
+        let configured = |build_type: &str| CompilationUnit {
+            directory: tmp.path().to_string_lossy().to_string(),
+            file: String::from("a.cpp"),
+            command: Some(format!("c++ -DCMAKE_BUILD_TYPE={build_type} -c a.cpp")),
+            ..Default::default()
+        };
+        let units = vec![configured("RelWithDebInfo"), configured("Release")];
+        let deduped = dedupe_units(units.clone(), Some("Release"));
+        assert_eq!(deduped, vec![units[1].clone()]);
+    }
+}
@@ -0,0 +1,232 @@
+//! This module holds functionality to explain which configuration is used by
+//! clang-tidy and clang-format for a certain file.
+//!
+//! Both tools search the analyzed file's directory (and its parent directories) for a
+//! configuration file. The nearest one found is then merged with cpp-linter's
+//! `--tidy-checks` or `--style` (respectively).
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use super::{clang_tidy::tidy_command, get_clang_tool_exe};
+use crate::cli::ClangParams;
+
+/// The names of clang-tidy's configuration files.
+pub const TIDY_CONFIG_FILE_NAMES: [&str; 1] = [".clang-tidy"];
+
+/// The names of clang-format's configuration files.
+pub const FORMAT_CONFIG_FILE_NAMES: [&str; 2] = [".clang-format", "_clang-format"];
+
+/// Find the nearest configuration file (with one of the given `names`) that applies
+/// to the given `file`.
+///
+/// The `file`'s directory and all its parent directories (up to `root`) are searched.
+/// A relative `file` path is assumed to be relative to `root`.
+pub fn find_config_file(file: &Path, root: &Path, names: &[&str]) -> Option<PathBuf> {
+    let file = if file.is_absolute() {
+        file.to_path_buf()
+    } else {
+        root.join(file)
+    };
+    let mut dir = file.parent();
+    while let Some(current) = dir {
+        if !current.starts_with(root) {
+            break;
+        }
+        if let Some(config) = names
+            .iter()
+            .map(|name| current.join(name))
+            .find(|path| path.is_file())
+        {
+            return Some(config);
+        }
+        dir = current.parent();
+    }
+    None
+}
+
+/// Display the given `path` relative to `root` (using posix path separators).
+fn display_relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Describe where clang-tidy's configuration for the given `file` comes from.
+///
+/// Returns [`None`] if neither a `.clang-tidy` file nor `checks` are used.
+pub fn describe_tidy_config(file: &Path, root: &Path, checks: &str) -> Option<String> {
+    let mut sources = vec![];
+    if let Some(config) = find_config_file(file, root, &TIDY_CONFIG_FILE_NAMES) {
+        sources.push(format!("`{}`", display_relative(&config, root)));
+    }
+    if !checks.is_empty() {
+        sources.push(String::from("`--tidy-checks`"));
+    }
+    if sources.is_empty() {
+        None
+    } else {
+        Some(sources.join(" + "))
+    }
+}
+
+/// Describe where clang-format's configuration for the given `file` comes from.
+///
+/// Returns [`None`] if clang-format is not used (ie the `style` is blank).
+pub fn describe_format_config(file: &Path, root: &Path, style: &str) -> Option<String> {
+    if style.is_empty() {
+        return None;
+    }
+    if let Some(path) = style.strip_prefix("file:") {
+        return Some(format!("`{}`", display_relative(Path::new(path), root)));
+    }
+    if style == "file" {
+        return Some(
+            match find_config_file(file, root, &FORMAT_CONFIG_FILE_NAMES) {
+                Some(config) => format!("`{}`", display_relative(&config, root)),
+                // clang-format falls back to the LLVM style by default
+                None => String::from("fallback style `LLVM`"),
+            },
+        );
+    }
+    Some(format!("`--style={style}`"))
+}
+
+/// Run `clang-tidy --dump-config` for the given `file` and return the effective
+/// configuration (in YAML syntax).
+///
+/// The same arguments (compilation database, extra arguments, and plugins) are
+/// used as when clang-tidy analyzes the `file`.
+pub fn dump_tidy_config(file: &Path, clang_params: &ClangParams) -> Result<String> {
+    let exe = clang_params
+        .clang_tidy_command
+        .as_ref()
+        .ok_or(anyhow!("clang-tidy executable was not found"))?;
+    let mut cmd = tidy_command(file, clang_params);
+    cmd.arg("--dump-config").arg(file.as_os_str());
+    if clang_params.database.is_none() {
+        cmd.arg("--");
+    }
+    capture_config(cmd, exe)
+}
+
+/// Run `clang-format --dump-config` for the given `file` and return the effective
+/// configuration (in YAML syntax).
+pub fn dump_format_config(file: &Path, clang_params: &ClangParams) -> Result<String> {
+    let exe = clang_params
+        .clang_format_command
+        .as_ref()
+        .ok_or(anyhow!("clang-format executable was not found"))?;
+    let mut cmd = Command::new(exe);
+    cmd.args(["--dump-config", "--style", &clang_params.style]);
+    cmd.arg(file.as_os_str());
+    capture_config(cmd, exe)
+}
+
+/// Run the given `cmd` and return its stdout (or an error with its stderr).
+fn capture_config(mut cmd: Command, exe: &Path) -> Result<String> {
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to run {}", exe.to_string_lossy()))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} --dump-config failed: {}",
+            exe.to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Explain the effective clang-tidy and clang-format configuration for the given
+/// `file` (relative to `root`).
+///
+/// Any [`ClangParams::overrides`] that match the `file` are considered. The clang tools
+/// are found using the given `version` (see [`get_clang_tool_exe()`]).
+pub fn explain_config(
+    file: &Path,
+    root: &Path,
+    clang_params: &ClangParams,
+    version: &str,
+) -> Result<String> {
+    let mut params = clang_params.resolve_for(file).into_owned();
+    let file_name = file.to_string_lossy().replace('\\', "/");
+    let mut out = String::new();
+    if params.tidy_checks == "-*" {
+        out.push_str(&format!("clang-tidy is not used for {file_name}\n"));
+    } else {
+        params.clang_tidy_command = Some(get_clang_tool_exe("clang-tidy", version)?);
+        out.push_str(&format!(
+            "# clang-tidy configuration for {file_name}\n# Configured by {}\n",
+            describe_tidy_config(file, root, &params.tidy_checks)
+                .unwrap_or(String::from("defaults"))
+        ));
+        out.push_str(&dump_tidy_config(file, &params)?);
+    }
+    if params.style.is_empty() {
+        out.push_str(&format!("clang-format is not used for {file_name}\n"));
+    } else {
+        params.clang_format_command = Some(get_clang_tool_exe("clang-format", version)?);
+        out.push_str(&format!(
+            "# clang-format configuration for {file_name}\n# Configured by {}\n",
+            // style is not blank, so there is a description
+            describe_format_config(file, root, &params.style).unwrap_or_default()
+        ));
+        out.push_str(&dump_format_config(file, &params)?);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use tempfile::tempdir;
+
+    use super::{describe_format_config, describe_tidy_config};
+
+    #[test]
+    fn nearest_config() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/sub")).unwrap();
+        fs::write(root.join(".clang-tidy"), "Checks: '-*'\n").unwrap();
+        fs::write(root.join("src/_clang-format"), "BasedOnStyle: LLVM\n").unwrap();
+        let file = Path::new("src/sub/demo.cpp");
+        assert_eq!(
+            describe_tidy_config(file, root, "readability-*"),
+            Some(String::from("`.clang-tidy` + `--tidy-checks`"))
+        );
+        assert_eq!(
+            describe_format_config(file, root, "file"),
+            Some(String::from("`src/_clang-format`"))
+        );
+        assert_eq!(
+            describe_format_config(Path::new("demo.cpp"), root, "file"),
+            Some(String::from("fallback style `LLVM`"))
+        );
+    }
+
+    #[test]
+    fn explicit_config() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path();
+        let file = Path::new("demo.cpp");
+        assert!(describe_tidy_config(file, root, "").is_none());
+        assert!(describe_format_config(file, root, "").is_none());
+        assert_eq!(
+            describe_format_config(file, root, "llvm"),
+            Some(String::from("`--style=llvm`"))
+        );
+        let style = format!("file:{}", root.join("styles/.clang-format").display());
+        assert_eq!(
+            describe_format_config(file, root, &style),
+            Some(String::from("`styles/.clang-format`"))
+        );
+    }
+}
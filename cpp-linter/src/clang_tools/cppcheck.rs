@@ -0,0 +1,324 @@
+//! This module holds the [`Analyzer`] implementation for
+//! [cppcheck](https://cppcheck.sourceforge.io/).
+//!
+//! cppcheck is run on each file individually. If the file is a translation unit in the
+//! compilation database, then the database is used (via `--project`) to get the
+//! compiler flags. The diagnostics are parsed from cppcheck's XML output.
+
+use std::{
+    env::current_dir,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use which::which;
+
+use super::{
+    analyzer::{
+        capture_version, run_command, timeout_log, Analysis, Analyzer, AnalyzerAdvice,
+        AnalyzerDiagnostic,
+    },
+    compilation_db::DATABASE_FILE_NAME,
+};
+use crate::{
+    cli::{ClangParams, LinesChangedOnly},
+    common_fs::{normalize_path, FileObj},
+};
+
+/// The compiler flags (passed via `--extra-arg`) that cppcheck also understands.
+const CPPCHECK_FLAGS: [&str; 4] = ["-I", "-D", "-U", "-std="];
+
+/// Used to deserialize cppcheck's XML output (`--xml --xml-version=2`).
+#[derive(Debug, Deserialize, Default)]
+struct CppcheckResults {
+    #[serde(default)]
+    errors: CppcheckErrors,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct CppcheckErrors {
+    #[serde(rename = "error", default)]
+    errors: Vec<CppcheckError>,
+}
+
+/// A single finding from cppcheck.
+#[derive(Debug, Deserialize)]
+struct CppcheckError {
+    #[serde(rename = "@id")]
+    id: String,
+    #[serde(rename = "@severity")]
+    severity: String,
+    #[serde(rename = "@msg")]
+    msg: String,
+
+    /// The locations of the finding. The first location is the primary one.
+    #[serde(rename = "location", default)]
+    locations: Vec<CppcheckLocation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CppcheckLocation {
+    #[serde(rename = "@file")]
+    file: String,
+    #[serde(rename = "@line")]
+    line: u32,
+    #[serde(rename = "@column", default)]
+    column: u32,
+}
+
+/// Parse cppcheck's XML output into diagnostics about the given `file`.
+///
+/// Relative paths in the output are resolved against `root`. Findings about other
+/// files and findings of `information` severity (which are about cppcheck's own
+/// configuration, ie missing includes) are excluded.
+fn parse_cppcheck_output(xml: &str, file: &Path, root: &Path) -> Result<Vec<AnalyzerDiagnostic>> {
+    let results = quick_xml::de::from_str::<CppcheckResults>(xml)
+        .with_context(|| "Failed to parse cppcheck's XML output")?;
+    let file_path = normalize_path(&root.join(file));
+    let mut diagnostics = vec![];
+    for error in results.errors.errors {
+        if error.severity == "information" {
+            continue;
+        }
+        let Some(location) = error.locations.first() else {
+            continue;
+        };
+        if normalize_path(&root.join(&location.file)) != file_path {
+            continue;
+        }
+        diagnostics.push(AnalyzerDiagnostic {
+            line: location.line,
+            cols: location.column,
+            severity: error.severity,
+            check: error.id,
+            message: error.msg,
+            related: vec![],
+        });
+    }
+    Ok(diagnostics)
+}
+
+/// Run [cppcheck](https://cppcheck.sourceforge.io/) on files.
+#[derive(Debug, Default)]
+pub struct Cppcheck;
+
+impl Cppcheck {
+    /// Create a cppcheck command for the given `file` (relative to `root`).
+    fn command(exe: &Path, file: &Path, root: &Path, clang_params: &ClangParams) -> Command {
+        let mut cmd = Command::new(exe);
+        cmd.args([
+            "--xml",
+            "--xml-version=2",
+            "--quiet",
+            "--inline-suppr",
+            "--enable=warning,style,performance,portability",
+        ]);
+        let file_path = normalize_path(&root.join(file));
+        let in_database = clang_params
+            .database_json
+            .as_ref()
+            .is_some_and(|units| units.iter().any(|unit| unit.path() == file_path));
+        match &clang_params.database {
+            Some(db) if in_database => {
+                let db = if db.is_dir() {
+                    db.join(DATABASE_FILE_NAME)
+                } else {
+                    db.to_path_buf()
+                };
+                cmd.arg(format!("--project={}", db.to_string_lossy()));
+                cmd.arg(format!("--file-filter={}", file_path.to_string_lossy()));
+            }
+            _ => {
+                let inferred_flags = clang_params
+                    .inferred_flags
+                    .get(file)
+                    .map(|flags| flags.as_slice())
+                    .unwrap_or_default();
+                for arg in clang_params.extra_args.iter().chain(inferred_flags) {
+                    if CPPCHECK_FLAGS.iter().any(|f| arg.starts_with(f)) {
+                        cmd.arg(arg);
+                    }
+                }
+                cmd.arg(file.as_os_str());
+            }
+        }
+        cmd
+    }
+}
+
+impl Analyzer for Cppcheck {
+    fn name(&self) -> &'static str {
+        "cppcheck"
+    }
+
+    fn find_executable(&self, _version: &str) -> Result<Option<PathBuf>> {
+        // cppcheck is not versioned like the clang tools
+        which("cppcheck")
+            .map(Some)
+            .map_err(|_| anyhow!("Could not find cppcheck"))
+    }
+
+    fn version(&self, exe: Option<&Path>) -> Result<String> {
+        let exe = exe.ok_or(anyhow!("cppcheck executable was not found"))?;
+        // ie "Cppcheck 2.13.0"
+        capture_version(exe, r"(\d+\.\d+(?:\.\d+)?)")
+    }
+
+    fn analyze(
+        &self,
+        exe: Option<&Path>,
+        file: &FileObj,
+        clang_params: &ClangParams,
+    ) -> Result<Analysis> {
+        let exe = exe.ok_or(anyhow!("cppcheck executable was not found"))?;
+        let root = current_dir().with_context(|| "Failed to get current working directory")?;
+        let mut logs = vec![];
+        let mut cmd = Self::command(exe, &file.name, &root, clang_params);
+        logs.push((
+            log::Level::Info,
+            format!(
+                "Running \"{} {}\"",
+                cmd.get_program().to_string_lossy(),
+                cmd.get_args()
+                    .map(|x| x.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        ));
+        let Some(output) = run_command(&mut cmd, clang_params.analyzer_timeout)? else {
+            logs.push(timeout_log(
+                self.name(),
+                &file.name,
+                clang_params.analyzer_timeout,
+            ));
+            let advice = AnalyzerAdvice {
+                tool: self.name().to_string(),
+                ..Default::default()
+            };
+            return Ok((Box::new(advice), logs));
+        };
+        // cppcheck writes its XML report to stderr
+        let xml = String::from_utf8_lossy(&output.stderr);
+        logs.push((log::Level::Debug, format!("Output from cppcheck:\n{xml}")));
+        if !output.status.success() {
+            logs.push((
+                log::Level::Warn,
+                format!(
+                    "cppcheck failed to analyze {} ({})",
+                    file.name.to_string_lossy(),
+                    output.status
+                ),
+            ));
+        }
+        let mut diagnostics = parse_cppcheck_output(&xml, &file.name, &root)?;
+        if clang_params.lines_changed_only != LinesChangedOnly::Off {
+            let ranges = file.get_ranges(&clang_params.lines_changed_only);
+            diagnostics.retain(|d| ranges.iter().any(|r| r.contains(&d.line)));
+        }
+        let advice = AnalyzerAdvice {
+            tool: self.name().to_string(),
+            diagnostics,
+            patched: None,
+        };
+        Ok((Box::new(advice), logs))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{parse_cppcheck_output, Cppcheck};
+    use crate::{
+        clang_tools::compilation_db::CompilationUnit, cli::ClangParams, common_fs::normalize_path,
+    };
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<results version="2">
+    <cppcheck version="2.13.0"/>
+    <errors>
+        <error id="missingIncludeSystem" severity="information" msg="Include file: &lt;vector&gt; not found."/>
+        <error id="nullPointer" severity="error" msg="Null pointer dereference: p" verbose="Null pointer dereference: p" cwe="476" file0="src/demo.cpp">
+            <location file="src/demo.cpp" line="7" column="6" info="Null pointer dereference"/>
+            <location file="src/demo.cpp" line="5" column="14" info="Assignment &apos;p=nullptr&apos;"/>
+        </error>
+        <error id="unusedFunction" severity="style" msg="The function &apos;helper&apos; is never used." file0="src/demo.cpp">
+            <location file="/repo/src/demo.cpp" line="12" column="0"/>
+        </error>
+        <error id="shadowVariable" severity="style" msg="Local variable &apos;x&apos; shadows outer variable" file0="src/demo.cpp">
+            <location file="src/demo.hpp" line="3" column="9"/>
+        </error>
+    </errors>
+</results>
+"#;
+
+    #[test]
+    fn parse_xml() {
+        let root = Path::new("/repo");
+        let diagnostics = parse_cppcheck_output(XML, Path::new("src/demo.cpp"), root).unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].line, 7);
+        assert_eq!(diagnostics[0].cols, 6);
+        assert_eq!(diagnostics[0].severity, "error");
+        assert_eq!(diagnostics[0].check, "nullPointer");
+        assert_eq!(diagnostics[0].message, "Null pointer dereference: p");
+        assert_eq!(diagnostics[1].check, "unusedFunction");
+        assert_eq!(
+            diagnostics[1].message,
+            "The function 'helper' is never used."
+        );
+    }
+
+    fn args(cmd: &std::process::Command) -> Vec<String> {
+        cmd.get_args()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn command_per_file() {
+        let root = Path::new("/repo");
+        let clang_params = ClangParams {
+            extra_args: vec!["-Iinclude".to_string(), "-Wall".to_string()],
+            ..Default::default()
+        };
+        let cmd = Cppcheck::command(
+            Path::new("cppcheck"),
+            Path::new("src/demo.cpp"),
+            root,
+            &clang_params,
+        );
+        let args = args(&cmd);
+        assert!(args.contains(&"-Iinclude".to_string()));
+        assert!(!args.contains(&"-Wall".to_string()));
+        assert_eq!(args.last().unwrap(), "src/demo.cpp");
+    }
+
+    #[test]
+    fn command_with_project() {
+        let root = Path::new("/repo");
+        let clang_params = ClangParams {
+            database: Some(PathBuf::from("/repo/build/compile_commands.json")),
+            database_json: Some(vec![CompilationUnit {
+                directory: "/repo/build".to_string(),
+                file: "../src/demo.cpp".to_string(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let cmd = Cppcheck::command(
+            Path::new("cppcheck"),
+            Path::new("src/demo.cpp"),
+            root,
+            &clang_params,
+        );
+        let args = args(&cmd);
+        assert!(args.contains(&"--project=/repo/build/compile_commands.json".to_string()));
+        assert!(args.contains(&format!(
+            "--file-filter={}",
+            normalize_path(Path::new("/repo/src/demo.cpp")).to_string_lossy()
+        )));
+    }
+}
@@ -0,0 +1,279 @@
+//! This module holds the [`Analyzer`] implementation for
+//! [clang-include-cleaner](https://clangd.llvm.org/design/include-cleaner).
+//!
+//! clang-include-cleaner is an alternative to include-what-you-use that ships with
+//! recent LLVM releases. Its suggestions are reported like include-what-you-use's.
+//!
+//! Only changed files are analyzed (if the diff is available). A changed header is
+//! analyzed with the compiler flags of a translation unit that includes it.
+
+use std::{
+    env::current_dir,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+
+use super::{
+    analyzer::{run_command, timeout_log, Analysis, Analyzer, AnalyzerAdvice},
+    get_clang_tool_exe,
+    include_what_you_use::{make_include_advice, IncludeRemoval, IncludeReport},
+};
+use crate::{
+    cli::ClangParams,
+    common_fs::{normalize_path, FileObj},
+};
+
+/// The name of clang-include-cleaner's executable.
+pub const INCLUDE_CLEANER_NAME: &str = "clang-include-cleaner";
+
+/// Parse the output of `clang-include-cleaner --print=changes`.
+///
+/// Unused includes are listed as `- "foo.h" @Line:3`, and missing includes are listed
+/// as `+ <vector>`.
+fn parse_include_cleaner_output(output: &str) -> IncludeReport {
+    let removal_pattern = Regex::new(r"^- (\S+) @Line:(\d+)$").unwrap();
+    let mut report = IncludeReport::default();
+    for line in output.lines().map(str::trim_end) {
+        if let Some(captures) = removal_pattern.captures(line) {
+            let line_number = captures[2].parse().unwrap_or_default();
+            report.removals.push(IncludeRemoval {
+                directive: format!("#include {}", &captures[1]),
+                start_line: line_number,
+                end_line: line_number,
+            });
+        } else if let Some(header) = line.strip_prefix("+ ") {
+            report.additions.push(format!("#include {header}"));
+        }
+    }
+    report
+}
+
+/// Run [clang-include-cleaner](https://clangd.llvm.org/design/include-cleaner) on files.
+#[derive(Debug, Default)]
+pub struct IncludeCleaner;
+
+impl IncludeCleaner {
+    /// Create a clang-include-cleaner command for the given `file` (relative to `root`).
+    ///
+    /// Like clang-tidy, the compilation database and compiler arguments are passed
+    /// via `-p` and `--extra-arg`. A header analyzed through a translation unit (that
+    /// is in the compilation database) uses the unit's compiler flags instead.
+    fn command(exe: &Path, file: &Path, root: &Path, clang_params: &ClangParams) -> Command {
+        let mut cmd = Command::new(exe);
+        cmd.arg("--print=changes");
+        let header_unit = clang_params
+            .header_units
+            .get(file)
+            .map(|unit| normalize_path(&root.join(unit)))
+            .and_then(|unit_path| {
+                clang_params
+                    .database_json
+                    .as_ref()?
+                    .iter()
+                    .find(|unit| unit.path() == unit_path)
+            });
+        if let Some(unit) = header_unit {
+            for arg in unit.reusable_flags().iter().chain(&clang_params.extra_args) {
+                cmd.arg(format!("--extra-arg={arg}"));
+            }
+            // use the unit's flags instead of searching for a compilation database
+            cmd.arg(file.as_os_str()).arg("--");
+            return cmd;
+        }
+        if let Some(db) = &clang_params.database {
+            cmd.args(["-p", &db.to_string_lossy()]);
+        }
+        let inferred_flags = clang_params
+            .inferred_flags
+            .get(file)
+            .map(|flags| flags.as_slice())
+            .unwrap_or_default();
+        for arg in clang_params.extra_args.iter().chain(inferred_flags) {
+            cmd.arg(format!("--extra-arg={arg}"));
+        }
+        cmd.arg(file.as_os_str());
+        cmd
+    }
+}
+
+impl Analyzer for IncludeCleaner {
+    fn name(&self) -> &'static str {
+        INCLUDE_CLEANER_NAME
+    }
+
+    fn find_executable(&self, version: &str) -> Result<Option<PathBuf>> {
+        get_clang_tool_exe(INCLUDE_CLEANER_NAME, version)
+            .map(Some)
+            .map_err(|_| anyhow!("Could not find {INCLUDE_CLEANER_NAME}"))
+    }
+
+    fn analyze(
+        &self,
+        exe: Option<&Path>,
+        file: &FileObj,
+        clang_params: &ClangParams,
+    ) -> Result<Analysis> {
+        let exe = exe.ok_or(anyhow!("{INCLUDE_CLEANER_NAME} executable was not found"))?;
+        let mut logs = vec![];
+        if clang_params.diff_available && file.diff_chunks.is_empty() {
+            logs.push((
+                log::Level::Info,
+                format!(
+                    "{} was not changed, so it is not analyzed by {INCLUDE_CLEANER_NAME}",
+                    file.name.to_string_lossy()
+                ),
+            ));
+            let advice = AnalyzerAdvice {
+                tool: self.name().to_string(),
+                ..Default::default()
+            };
+            return Ok((Box::new(advice), logs));
+        }
+        let root = current_dir().with_context(|| "Failed to get current working directory")?;
+        let mut cmd = Self::command(exe, &file.name, &root, clang_params);
+        logs.push((
+            log::Level::Info,
+            format!(
+                "Running \"{} {}\"",
+                cmd.get_program().to_string_lossy(),
+                cmd.get_args()
+                    .map(|x| x.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        ));
+        let Some(output) = run_command(&mut cmd, clang_params.analyzer_timeout)? else {
+            logs.push(timeout_log(
+                self.name(),
+                &file.name,
+                clang_params.analyzer_timeout,
+            ));
+            let advice = AnalyzerAdvice {
+                tool: self.name().to_string(),
+                ..Default::default()
+            };
+            return Ok((Box::new(advice), logs));
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        logs.push((
+            log::Level::Debug,
+            format!("Output from {INCLUDE_CLEANER_NAME}:\n{stdout}"),
+        ));
+        if !output.status.success() {
+            logs.push((
+                log::Level::Warn,
+                format!(
+                    "{INCLUDE_CLEANER_NAME} failed to analyze {} ({}): {}",
+                    file.name.to_string_lossy(),
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        let report = parse_include_cleaner_output(&stdout);
+        let advice = make_include_advice(self.name(), file, report, clang_params)?;
+        Ok((Box::new(advice), logs))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+    };
+
+    use super::{parse_include_cleaner_output, IncludeCleaner};
+    use crate::{
+        clang_tools::{
+            compilation_db::CompilationUnit,
+            include_what_you_use::{apply_report, IncludeRemoval},
+        },
+        cli::ClangParams,
+    };
+
+    #[test]
+    fn parse_output() {
+        let output = "- \"unused.h\" @Line:2\n- <vector> @Line:4\n+ <string>\n";
+        let report = parse_include_cleaner_output(output);
+        assert_eq!(report.additions, vec!["#include <string>"]);
+        assert_eq!(
+            report.removals,
+            vec![
+                IncludeRemoval {
+                    directive: String::from("#include \"unused.h\""),
+                    start_line: 2,
+                    end_line: 2,
+                },
+                IncludeRemoval {
+                    directive: String::from("#include <vector>"),
+                    start_line: 4,
+                    end_line: 4,
+                },
+            ]
+        );
+        let original = "#include \"demo.h\"\n#include \"unused.h\"\n\n#include <vector>\n";
+        let (patched, line) = apply_report(original.as_bytes(), &report);
+        assert_eq!(line, 1);
+        assert_eq!(
+            String::from_utf8(patched).unwrap(),
+            "#include \"demo.h\"\n#include <string>\n\n"
+        );
+    }
+
+    #[test]
+    fn command_args() {
+        let root = Path::new("/proj");
+        let mut clang_params = ClangParams {
+            database: Some(PathBuf::from("build")),
+            extra_args: vec![String::from("-std=c++17")],
+            ..Default::default()
+        };
+        let get_args = |file: &str, clang_params: &ClangParams| {
+            IncludeCleaner::command(
+                Path::new("clang-include-cleaner"),
+                Path::new(file),
+                root,
+                clang_params,
+            )
+            .get_args()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            get_args("src/demo.cpp", &clang_params),
+            vec![
+                "--print=changes",
+                "-p",
+                "build",
+                "--extra-arg=-std=c++17",
+                "src/demo.cpp"
+            ]
+        );
+
+        // a header uses the flags of its translation unit
+        clang_params.database_json = Some(vec![CompilationUnit {
+            directory: String::from("/proj/build"),
+            file: String::from("../src/demo.cpp"),
+            command: Some(String::from("c++ -I../include -c ../src/demo.cpp")),
+            ..Default::default()
+        }]);
+        clang_params.header_units = HashMap::from([(
+            PathBuf::from("include/demo.h"),
+            PathBuf::from("/proj/src/demo.cpp"),
+        )]);
+        assert_eq!(
+            get_args("include/demo.h", &clang_params),
+            vec![
+                "--print=changes",
+                "--extra-arg=-I/proj/include",
+                "--extra-arg=-std=c++17",
+                "include/demo.h",
+                "--"
+            ]
+        );
+    }
+}
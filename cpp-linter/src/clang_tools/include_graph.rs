@@ -0,0 +1,294 @@
+//! This module holds functionality to find a translation unit (from a compilation
+//! database) that includes a certain header file.
+//!
+//! Header files are not listed in a compilation database, so clang-tidy does not know
+//! which compiler flags to use when a header is analyzed directly. Instead, a header can
+//! be analyzed through a translation unit that includes it.
+//!
+//! Candidate translation units are first found with a simple scan of each file's
+//! `#include` directives, which is fast enough to run on every translation unit. That
+//! scan treats conditional includes (ie within `#if` blocks) as if they were always
+//! included. So, a candidate is then confirmed with a dependency scan (`-MM -MG`)
+//! using the unit's compile command. Only if the dependency scan cannot be run (ie the
+//! compiler is not available), the text scan's result is used as a fallback.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+
+use super::compilation_db::CompilationUnit;
+use crate::common_fs::normalize_path;
+
+/// The file extensions that are considered header files.
+pub const HEADER_EXTENSIONS: [&str; 9] = ["h", "H", "hh", "hpp", "hxx", "h++", "inl", "ipp", "tpp"];
+
+/// Is the given `path` a header file (according to its extension)?
+pub fn is_header(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| HEADER_EXTENSIONS.contains(&ext.to_string_lossy().as_ref()))
+}
+
+/// A lazily built graph of `#include` directives.
+///
+/// Included files are resolved relative to the including file's directory, the
+/// include directories of the translation unit (ie from `-I` flags), or the `root`
+/// directory. Any included file that cannot be resolved (ie a system header) is
+/// not followed.
+pub struct IncludeGraph {
+    root: PathBuf,
+    include_pattern: Regex,
+    includes: HashMap<PathBuf, Vec<String>>,
+}
+
+impl IncludeGraph {
+    /// Create an empty graph for a project located in the given `root` directory.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            include_pattern: Regex::new(r#"(?m)^\s*#\s*include\s*[<"]([^>"]+)[>"]"#).unwrap(),
+            includes: HashMap::new(),
+        }
+    }
+
+    /// Get the paths as written in the `#include` directives of the given (absolute)
+    /// `file`.
+    ///
+    /// The file is only scanned once; the result is cached for subsequent calls.
+    fn includes_of(&mut self, file: &Path) -> Vec<String> {
+        if let Some(includes) = self.includes.get(file) {
+            return includes.clone();
+        }
+        let content = fs::read(file)
+            .map(|buf| String::from_utf8_lossy(&buf).to_string())
+            .unwrap_or_default();
+        let includes = self
+            .include_pattern
+            .captures_iter(&content)
+            .map(|captured| captured[1].to_string())
+            .collect::<Vec<_>>();
+        self.includes.insert(file.to_path_buf(), includes.clone());
+        includes
+    }
+
+    /// Does the given `unit` include the `header` (directly or transitively)?
+    fn is_included_by(&mut self, header: &Path, unit: &CompilationUnit) -> bool {
+        let include_dirs = unit.include_dirs();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::from([unit.path()]);
+        while let Some(file) = queue.pop_front() {
+            if !visited.insert(file.clone()) {
+                continue;
+            }
+            let parent = file.parent().unwrap_or(&self.root).to_path_buf();
+            for spec in self.includes_of(&file) {
+                let resolved = [&parent]
+                    .into_iter()
+                    .chain(&include_dirs)
+                    .chain([&self.root])
+                    .map(|dir| normalize_path(&dir.join(&spec)))
+                    .find(|path| path.is_file());
+                match resolved {
+                    Some(resolved) if resolved == header => return true,
+                    Some(resolved) => queue.push_back(resolved),
+                    None => {}
+                }
+            }
+        }
+        false
+    }
+
+    /// Find a translation unit in the given `units` that includes the given `header`.
+    ///
+    /// Units with the same file stem as the `header` (ie `demo.cpp` for `demo.hpp`) are
+    /// preferred. Each candidate is confirmed with [`scan_dependencies()`] (if
+    /// possible). Returns the absolute path to the translation unit's file.
+    pub fn find_including_unit(
+        &mut self,
+        header: &Path,
+        units: &[CompilationUnit],
+    ) -> Option<PathBuf> {
+        let header = normalize_path(&self.root.join(header));
+        let mut seen = HashSet::new();
+        let mut candidates = units
+            .iter()
+            .filter(|unit| seen.insert(unit.path()))
+            .collect::<Vec<_>>();
+        // stable sort keeps the database's order otherwise
+        candidates.sort_by_key(|unit| unit.path().file_stem() != header.file_stem());
+        candidates
+            .into_iter()
+            .filter(|unit| self.is_included_by(&header, unit))
+            .find(|unit| match scan_dependencies(unit) {
+                Ok(dependencies) => {
+                    let is_dependency = dependencies.contains(&header);
+                    if !is_dependency {
+                        log::debug!(
+                            "{} is not included by {} with its compile command",
+                            header.to_string_lossy(),
+                            unit.path().to_string_lossy()
+                        );
+                    }
+                    is_dependency
+                }
+                Err(e) => {
+                    log::debug!("Falling back to scanning #include directives: {e:?}");
+                    true
+                }
+            })
+            .map(CompilationUnit::path)
+    }
+}
+
+/// Run a dependency scan (`-MM -MG`) with the given `unit`'s compile command.
+///
+/// Returns the (absolute) paths of the files that the unit depends on.
+pub fn scan_dependencies(unit: &CompilationUnit) -> Result<Vec<PathBuf>> {
+    let args = unit.args();
+    let compiler = args
+        .first()
+        .ok_or(anyhow!("The compile command for {} is empty", unit.file))?;
+    let directory = unit.directory_path();
+    let output = Command::new(compiler)
+        .args(unit.reusable_flags())
+        .args(["-MM", "-MG"])
+        .arg(unit.path())
+        .current_dir(&directory)
+        .output()
+        .with_context(|| format!("Failed to run {compiler}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Dependency scan of {} failed: {}",
+            unit.file,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_make_dependencies(
+        &String::from_utf8_lossy(&output.stdout),
+        &directory,
+    ))
+}
+
+/// Parse the make rule output by a dependency scan (`-MM`).
+///
+/// Relative paths are resolved against the given `directory`.
+fn parse_make_dependencies(output: &str, directory: &Path) -> Vec<PathBuf> {
+    let joined = output.replace("\\\r\n", " ").replace("\\\n", " ");
+    let Some((_, prerequisites)) = joined.split_once(": ") else {
+        return vec![];
+    };
+    // escaped spaces are part of a path
+    prerequisites
+        .replace("\\ ", "\0")
+        .split_whitespace()
+        .map(|path| normalize_path(&directory.join(path.replace('\0', " "))))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use tempfile::tempdir;
+    use which::which;
+
+    use super::{is_header, parse_make_dependencies, IncludeGraph};
+    use crate::clang_tools::compilation_db::CompilationUnit;
+
+    #[test]
+    fn header_extensions() {
+        assert!(is_header(&PathBuf::from("src/demo.hpp")));
+        assert!(is_header(&PathBuf::from("src/demo.h")));
+        assert!(!is_header(&PathBuf::from("src/demo.cpp")));
+        assert!(!is_header(&PathBuf::from("Makefile")));
+    }
+
+    #[test]
+    fn find_unit() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path();
+        for dir in ["src", "include/proj", "build"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(
+            root.join("src/main.cpp"),
+            "#include \"util.hpp\"\nint main() {}\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/util.hpp"),
+            "#pragma once\n#include <proj/demo.hpp>\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/other.cpp"),
+            "#include <vector>\n#include \"log.hpp\"\n",
+        )
+        .unwrap();
+        fs::write(root.join("include/proj/demo.hpp"), "#pragma once\n").unwrap();
+        fs::write(root.join("include/proj/unused.hpp"), "#pragma once\n").unwrap();
+        fs::write(root.join("include/proj/log.hpp"), "#pragma once\n").unwrap();
+        fs::write(
+            root.join("src/win.cpp"),
+            "#ifdef _WIN32\n#include \"win.hpp\"\n#endif\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/win.hpp"), "#pragma once\n").unwrap();
+        let unit = |file: &str| CompilationUnit {
+            directory: root.join("build").to_string_lossy().to_string(),
+            file: file.to_string(),
+            command: Some(format!("c++ -I../include -c {file}")),
+            ..Default::default()
+        };
+        let units = vec![
+            unit("../src/other.cpp"),
+            unit("../src/main.cpp"),
+            unit("../src/win.cpp"),
+        ];
+
+        let mut graph = IncludeGraph::new(root);
+        let main = root.join("src").join("main.cpp");
+        assert_eq!(
+            graph.find_including_unit(&PathBuf::from("src/util.hpp"), &units),
+            Some(main.clone())
+        );
+        // transitively included via an include path
+        assert_eq!(
+            graph.find_including_unit(&PathBuf::from("include/proj/demo.hpp"), &units),
+            Some(main)
+        );
+        assert!(graph
+            .find_including_unit(&PathBuf::from("include/proj/unused.hpp"), &units)
+            .is_none());
+        // "log.hpp" is not found in the include paths, so it is not a match by name
+        assert!(graph
+            .find_including_unit(&PathBuf::from("include/proj/log.hpp"), &units)
+            .is_none());
+        // the dependency scan (if a compiler is available) skips conditional includes
+        if cfg!(not(windows)) && which("c++").is_ok() {
+            assert!(graph
+                .find_including_unit(&PathBuf::from("src/win.hpp"), &units)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn make_dependencies() {
+        let output =
+            "main.o: ../src/main.cpp ../src/util.hpp \\\n  /proj/include/my\\ dir/demo.hpp\n";
+        assert_eq!(
+            parse_make_dependencies(output, &PathBuf::from("/proj/build")),
+            vec![
+                PathBuf::from("/proj/src/main.cpp"),
+                PathBuf::from("/proj/src/util.hpp"),
+                PathBuf::from("/proj/include/my dir/demo.hpp"),
+            ]
+        );
+        assert!(parse_make_dependencies("", &PathBuf::from("/proj")).is_empty());
+    }
+}
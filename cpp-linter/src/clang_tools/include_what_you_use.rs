@@ -0,0 +1,457 @@
+//! This module holds the [`Analyzer`] implementation for
+//! [include-what-you-use](https://include-what-you-use.org/).
+//!
+//! include-what-you-use reports which `#include` lines a file should add or remove.
+//! These reports are turned into diagnostics and a patched version of the file, so
+//! they can be posted as PR review suggestions.
+
+use std::{
+    env::current_dir,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+
+use which::which;
+
+use super::analyzer::{
+    capture_version, run_command, timeout_log, Analysis, Analyzer, AnalyzerAdvice,
+    AnalyzerDiagnostic,
+};
+use crate::{
+    cli::{ClangParams, LinesChangedOnly},
+    common_fs::{normalize_path, FileObj},
+};
+
+/// The name of include-what-you-use's executable.
+const IWYU_NAME: &str = "include-what-you-use";
+
+/// An `#include` line that should be removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct IncludeRemoval {
+    /// The `#include` directive (without any trailing comment).
+    pub directive: String,
+
+    /// The first line of the directive.
+    pub start_line: u32,
+
+    /// The last line of the directive.
+    pub end_line: u32,
+}
+
+/// The `#include` lines that should be added to or removed from a single file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct IncludeReport {
+    /// The `#include` (or forward declaration) lines to add.
+    pub additions: Vec<String>,
+
+    /// The `#include` (or forward declaration) lines to remove.
+    pub removals: Vec<IncludeRemoval>,
+}
+
+/// Remove the trailing `// ...` comment that include-what-you-use appends to lines.
+fn strip_comment(line: &str) -> &str {
+    line.split_once("//").map(|(l, _)| l).unwrap_or(line).trim()
+}
+
+/// Parse include-what-you-use's output into a report about the given `file`.
+///
+/// Relative paths in the output are resolved against `root`. Reports about other
+/// files are ignored.
+fn parse_iwyu_output(output: &str, file: &Path, root: &Path) -> IncludeReport {
+    let section_pattern = Regex::new(r"^(.+) should (add|remove) these lines:$").unwrap();
+    let removal_pattern = Regex::new(r"^- (.+?)\s*// lines (\d+)-(\d+)$").unwrap();
+    let file_path = normalize_path(&root.join(file));
+    let mut report = IncludeReport::default();
+    // the current section: `Some(true)` for additions, `Some(false)` for removals
+    let mut section = None;
+    for line in output.lines() {
+        if let Some(captures) = section_pattern.captures(line) {
+            let name = Path::new(captures.get(1).unwrap().as_str());
+            section = if normalize_path(&root.join(name)) == file_path {
+                Some(&captures[2] == "add")
+            } else {
+                None
+            };
+            continue;
+        }
+        if line.trim().is_empty() || line.starts_with("The full include-list for ") {
+            section = None;
+            continue;
+        }
+        match section {
+            Some(true) => report.additions.push(strip_comment(line).to_string()),
+            Some(false) => {
+                if let Some(captures) = removal_pattern.captures(line) {
+                    report.removals.push(IncludeRemoval {
+                        directive: strip_comment(&captures[1]).to_string(),
+                        start_line: captures[2].parse().unwrap_or_default(),
+                        end_line: captures[3].parse().unwrap_or_default(),
+                    });
+                }
+            }
+            None => {}
+        }
+    }
+    report
+}
+
+/// Apply the given `report` to the file's `original` content.
+///
+/// Removed lines are deleted. Added lines are inserted after the last `#include` line
+/// that is kept (or at the start of the file if there is none).
+///
+/// Returns the patched content and the line number at which lines are added.
+pub(super) fn apply_report(original: &[u8], report: &IncludeReport) -> (Vec<u8>, u32) {
+    let text = String::from_utf8_lossy(original);
+    let lines = text.split_inclusive('\n').collect::<Vec<_>>();
+    let is_removed = |number: u32| {
+        report
+            .removals
+            .iter()
+            .any(|r| (r.start_line..=r.end_line).contains(&number))
+    };
+    let insert_after = lines
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(index, line)| {
+            line.trim_start().starts_with("#include") && !is_removed(*index as u32 + 1)
+        })
+        .map(|(index, _)| index + 1)
+        .unwrap_or_default();
+    let line_ending = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut patched = String::with_capacity(text.len());
+    for (index, line) in lines.iter().enumerate() {
+        if index == insert_after {
+            for addition in &report.additions {
+                patched.push_str(addition);
+                patched.push_str(line_ending);
+            }
+        }
+        if !is_removed(index as u32 + 1) {
+            patched.push_str(line);
+        }
+    }
+    if insert_after >= lines.len() {
+        if !patched.is_empty() && !patched.ends_with('\n') {
+            patched.push_str(line_ending);
+        }
+        for addition in &report.additions {
+            patched.push_str(addition);
+            patched.push_str(line_ending);
+        }
+    }
+    (patched.into_bytes(), insert_after.max(1) as u32)
+}
+
+/// Create the [`AnalyzerAdvice`] (from the given `tool`) for a `file` from its
+/// [`IncludeReport`].
+///
+/// With `--lines-changed-only`, only the removals are filtered. A missing include is
+/// needed regardless of which lines were changed.
+///
+/// Additions are inserted after the last kept `#include` line, which may not be part
+/// of the diff. So, with `--lines-changed-only`, the diagnostics about additions are
+/// reported at the first changed `#include` line instead (if there is one).
+pub(super) fn make_include_advice(
+    tool: &str,
+    file: &FileObj,
+    mut report: IncludeReport,
+    clang_params: &ClangParams,
+) -> Result<AnalyzerAdvice> {
+    let ranges = if clang_params.lines_changed_only != LinesChangedOnly::Off {
+        let ranges = file.get_ranges(&clang_params.lines_changed_only);
+        report
+            .removals
+            .retain(|r| ranges.iter().any(|range| range.contains(&r.start_line)));
+        Some(ranges)
+    } else {
+        None
+    };
+    let mut advice = AnalyzerAdvice {
+        tool: tool.to_string(),
+        ..Default::default()
+    };
+    if report.additions.is_empty() && report.removals.is_empty() {
+        return Ok(advice);
+    }
+    let original = fs::read(&file.name).with_context(|| {
+        format!(
+            "Failed to read the contents of {}",
+            file.name.to_string_lossy()
+        )
+    })?;
+    let (patched, insert_line) = apply_report(&original, &report);
+    let addition_line = ranges
+        .and_then(|ranges| {
+            String::from_utf8_lossy(&original)
+                .lines()
+                .enumerate()
+                .map(|(index, line)| (index as u32 + 1, line))
+                .find(|(number, line)| {
+                    line.trim_start().starts_with("#include")
+                        && ranges.iter().any(|range| range.contains(number))
+                })
+                .map(|(number, _)| number)
+        })
+        .unwrap_or(insert_line);
+    for addition in &report.additions {
+        advice.diagnostics.push(AnalyzerDiagnostic {
+            line: addition_line,
+            cols: 1,
+            severity: String::from("warning"),
+            check: String::from("add-include"),
+            message: format!("`{addition}` should be added"),
+            related: vec![],
+        });
+    }
+    for removal in &report.removals {
+        advice.diagnostics.push(AnalyzerDiagnostic {
+            line: removal.start_line,
+            cols: 1,
+            severity: String::from("warning"),
+            check: String::from("remove-include"),
+            message: format!("`{}` should be removed", removal.directive),
+            related: vec![],
+        });
+    }
+    advice.patched = Some(patched);
+    Ok(advice)
+}
+
+/// Run [include-what-you-use](https://include-what-you-use.org/) on files.
+#[derive(Debug, Default)]
+pub struct IncludeWhatYouUse;
+
+impl IncludeWhatYouUse {
+    /// Create an include-what-you-use command for the given `file` (relative to `root`).
+    ///
+    /// The compiler flags are taken from the compilation database (if the `file` is a
+    /// translation unit in it). A header analyzed through a translation unit is checked
+    /// via `--check_also`.
+    fn command(exe: &Path, file: &Path, root: &Path, clang_params: &ClangParams) -> Command {
+        let mut cmd = Command::new(exe);
+        let header_unit = clang_params.header_units.get(file);
+        let unit_name = header_unit.map(PathBuf::as_path).unwrap_or(file);
+        let unit_path = normalize_path(&root.join(unit_name));
+        let db_unit = clang_params
+            .database_json
+            .as_ref()
+            .and_then(|units| units.iter().find(|unit| unit.path() == unit_path));
+        let inferred_flags = clang_params
+            .inferred_flags
+            .get(file)
+            .map(|flags| flags.as_slice())
+            .unwrap_or_default();
+        match db_unit {
+            Some(unit) => {
+                cmd.args(unit.reusable_flags());
+            }
+            None => {
+                cmd.args(inferred_flags);
+            }
+        }
+        cmd.args(&clang_params.extra_args);
+        if header_unit.is_some() {
+            cmd.args([
+                "-Xiwyu",
+                &format!("--check_also={}", file.to_string_lossy()),
+            ]);
+        }
+        cmd.arg(unit_name.as_os_str());
+        cmd
+    }
+}
+
+impl Analyzer for IncludeWhatYouUse {
+    fn name(&self) -> &'static str {
+        IWYU_NAME
+    }
+
+    fn find_executable(&self, _version: &str) -> Result<Option<PathBuf>> {
+        // include-what-you-use is not versioned like the clang tools
+        which(IWYU_NAME)
+            .map(Some)
+            .map_err(|_| anyhow!("Could not find {IWYU_NAME}"))
+    }
+
+    fn version(&self, exe: Option<&Path>) -> Result<String> {
+        let exe = exe.ok_or(anyhow!("{IWYU_NAME} executable was not found"))?;
+        // ie "include-what-you-use 0.21 based on clang version 17.0.6"
+        capture_version(exe, r"include-what-you-use\s+([\d.]+)")
+    }
+
+    fn analyze(
+        &self,
+        exe: Option<&Path>,
+        file: &FileObj,
+        clang_params: &ClangParams,
+    ) -> Result<Analysis> {
+        let exe = exe.ok_or(anyhow!("{IWYU_NAME} executable was not found"))?;
+        let root = current_dir().with_context(|| "Failed to get current working directory")?;
+        let mut logs = vec![];
+        let mut cmd = Self::command(exe, &file.name, &root, clang_params);
+        logs.push((
+            log::Level::Info,
+            format!(
+                "Running \"{} {}\"",
+                cmd.get_program().to_string_lossy(),
+                cmd.get_args()
+                    .map(|x| x.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        ));
+        let Some(output) = run_command(&mut cmd, clang_params.analyzer_timeout)? else {
+            logs.push(timeout_log(
+                self.name(),
+                &file.name,
+                clang_params.analyzer_timeout,
+            ));
+            let advice = AnalyzerAdvice {
+                tool: self.name().to_string(),
+                ..Default::default()
+            };
+            return Ok((Box::new(advice), logs));
+        };
+        // include-what-you-use writes its report to stderr
+        // and exits with a non-zero status when it has suggestions.
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        logs.push((
+            log::Level::Debug,
+            format!("Output from {IWYU_NAME}:\n{stderr}"),
+        ));
+        let report = parse_iwyu_output(&stderr, &file.name, &root);
+        let advice = make_include_advice(self.name(), file, report, clang_params)?;
+        Ok((Box::new(advice), logs))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use tempfile::tempdir;
+
+    use super::{apply_report, make_include_advice, parse_iwyu_output, IncludeRemoval};
+    use crate::{
+        cli::{ClangParams, LinesChangedOnly},
+        common_fs::FileObj,
+    };
+
+    const OUTPUT: &str = "
+src/demo.cpp should add these lines:
+#include <string>  // for string
+
+src/demo.cpp should remove these lines:
+- #include <vector>  // lines 2-2
+- #include \"unused.h\"  // lines 4-4
+
+The full include-list for src/demo.cpp:
+#include <string>  // for string
+#include \"demo.h\"
+---
+
+src/demo.h should add these lines:
+#include <map>  // for map
+
+src/demo.h should remove these lines:
+
+The full include-list for src/demo.h:
+#include <map>  // for map
+---
+";
+
+    #[test]
+    fn parse_output() {
+        let root = Path::new("/repo");
+        let report = parse_iwyu_output(OUTPUT, Path::new("src/demo.cpp"), root);
+        assert_eq!(report.additions, vec!["#include <string>"]);
+        assert_eq!(
+            report.removals,
+            vec![
+                IncludeRemoval {
+                    directive: String::from("#include <vector>"),
+                    start_line: 2,
+                    end_line: 2,
+                },
+                IncludeRemoval {
+                    directive: String::from("#include \"unused.h\""),
+                    start_line: 4,
+                    end_line: 4,
+                },
+            ]
+        );
+        let report = parse_iwyu_output(OUTPUT, Path::new("src/demo.h"), root);
+        assert_eq!(report.additions, vec!["#include <map>"]);
+        assert!(report.removals.is_empty());
+        let report = parse_iwyu_output(
+            "(src/other.cpp has correct #includes/fwd-decls)\n",
+            Path::new("src/other.cpp"),
+            root,
+        );
+        assert!(report.additions.is_empty() && report.removals.is_empty());
+    }
+
+    #[test]
+    fn patch_includes() {
+        let root = Path::new("/repo");
+        let report = parse_iwyu_output(OUTPUT, Path::new("src/demo.cpp"), root);
+        let original =
+            "#include \"demo.h\"\n#include <vector>\n#include <iostream>\n#include \"unused.h\"\n\nint main() {}\n";
+        let (patched, line) = apply_report(original.as_bytes(), &report);
+        assert_eq!(line, 3);
+        assert_eq!(
+            String::from_utf8(patched).unwrap(),
+            "#include \"demo.h\"\n#include <iostream>\n#include <string>\n\nint main() {}\n"
+        );
+    }
+
+    #[test]
+    fn patch_without_includes() {
+        let root = Path::new("/repo");
+        let report = parse_iwyu_output(OUTPUT, Path::new("src/demo.h"), root);
+        let (patched, line) = apply_report(b"int x;\n", &report);
+        assert_eq!(line, 1);
+        assert_eq!(
+            String::from_utf8(patched).unwrap(),
+            "#include <map>\nint x;\n"
+        );
+    }
+
+    #[test]
+    fn additions_at_changed_include() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("demo.cpp");
+        fs::write(
+            &path,
+            "#include \"demo.h\"\n#include <vector>\n#include \"unused.h\"\n\nint main() {}\n",
+        )
+        .unwrap();
+        let report = parse_iwyu_output(OUTPUT, Path::new("src/demo.cpp"), Path::new("/repo"));
+        let mut file = FileObj::new(path);
+        file.added_lines = vec![2, 5];
+        file.added_ranges = vec![2..=2, 5..=5];
+        file.diff_chunks = vec![1..=5];
+        let mut clang_params = ClangParams::default();
+
+        // the additions are inserted after the last kept include (line 3)
+        let advice = make_include_advice("iwyu", &file, report.clone(), &clang_params).unwrap();
+        assert_eq!(advice.diagnostics[0].line, 3);
+        assert_eq!(advice.diagnostics.len(), 3);
+
+        // but reported at the first changed include
+        clang_params.lines_changed_only = LinesChangedOnly::On;
+        let advice = make_include_advice("iwyu", &file, report, &clang_params).unwrap();
+        let lines = advice
+            .diagnostics
+            .iter()
+            .map(|d| (d.check.as_str(), d.line))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![("add-include", 2), ("remove-include", 2)]);
+    }
+}
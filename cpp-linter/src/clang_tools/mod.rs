@@ -2,8 +2,8 @@
 //! clang-tidy.
 
 use std::{
+    collections::HashMap,
     env::current_dir,
-    fs,
     path::{Path, PathBuf},
     process::Command,
     sync::{Arc, Mutex},
@@ -24,10 +24,24 @@ use crate::{
     cli::ClangParams,
     rest_api::{RestApiClient, COMMENT_MARKER, USER_OUTREACH},
 };
+pub mod analyzer;
+use analyzer::AnalyzerRegistry;
 pub mod clang_format;
-use clang_format::run_clang_format;
 pub mod clang_tidy;
-use clang_tidy::{run_clang_tidy, CompilationUnit};
+use clang_tidy::CLANG_TIDY_NAME;
+pub mod compilation_db;
+pub mod config_source;
+pub mod cppcheck;
+use compilation_db::{infer_flags, load_database};
+pub mod include_cleaner;
+pub mod include_graph;
+pub mod include_what_you_use;
+use include_graph::{is_header, IncludeGraph};
+pub mod nolint;
+pub mod rules;
+pub mod static_analyzer;
+pub mod stats;
+use nolint::audit_nolint;
 
 /// Fetch the path to a clang tool by `name` (ie `"clang-tidy"` or `"clang-format"`) and
 /// `version`.
@@ -65,9 +79,9 @@ pub fn get_clang_tool_exe(name: &str, version: &str) -> Result<PathBuf> {
             // On Unix systems, this block is not likely reached. Typically, installing clang
             // will produce a symlink to the executable with the major version appended to the
             // name.
-            return Ok(cmd);
+            Ok(cmd)
         } else {
-            return Err(anyhow!("Could not find clang tool by name and version"));
+            Err(anyhow!("Could not find clang tool by name and version"))
         }
     } else {
         // `version` specified is not a semantic version; treat as path/to/bin
@@ -79,7 +93,8 @@ pub fn get_clang_tool_exe(name: &str, version: &str) -> Result<PathBuf> {
     }
 }
 
-/// This creates a task to run clang-tidy and clang-format on a single file.
+/// This creates a task to run the enabled analyzers (ie clang-format and clang-tidy)
+/// on a single file.
 ///
 /// Returns a Future that infallibly resolves to a 2-tuple that contains
 ///
@@ -95,55 +110,98 @@ fn analyze_single_file(
         .lock()
         .map_err(|_| anyhow!("Failed to lock file mutex"))?;
     let mut logs = vec![];
-    if clang_params.clang_format_command.is_some() {
-        if clang_params
-            .format_filter
-            .as_ref()
-            .is_some_and(|f| f.is_source_or_ignored(file.name.as_path()))
-            || clang_params.format_filter.is_none()
-        {
-            let format_result = run_clang_format(&mut file, &clang_params)?;
-            logs.extend(format_result);
-        } else {
-            logs.push((
-                log::Level::Info,
-                format!(
-                    "{} not scanned by clang-format due to `--ignore-format`",
-                    file.name.as_os_str().to_string_lossy()
-                ),
-            ));
+    let clang_params = clang_params.resolve_for(&file.name);
+    for analyzer in &clang_params.analyzers {
+        let name = analyzer.analyzer.name();
+        if !analyzer.analyzer.is_enabled(&clang_params) {
+            continue;
         }
-    }
-    if clang_params.clang_tidy_command.is_some() {
-        if clang_params
-            .tidy_filter
-            .as_ref()
-            .is_some_and(|f| f.is_source_or_ignored(file.name.as_path()))
-            || clang_params.tidy_filter.is_none()
-        {
-            let tidy_result = run_clang_tidy(&mut file, &clang_params)?;
-            logs.extend(tidy_result);
-        } else {
+        if let Some(reason) = analyzer.analyzer.ignored_because(&file.name, &clang_params) {
             logs.push((
                 log::Level::Info,
                 format!(
-                    "{} not scanned by clang-tidy due to `--ignore-tidy`",
+                    "{} not scanned by {name} due to {reason}",
                     file.name.as_os_str().to_string_lossy()
                 ),
             ));
+            continue;
         }
+        match analyzer.run(&mut file, &clang_params) {
+            Ok(analyzer_result) => logs.extend(analyzer_result),
+            // don't discard the logs of other tools
+            Err(e) => logs.push((
+                log::Level::Warn,
+                format!("{name} failed to analyze the file: {e:?}"),
+            )),
+        }
+    }
+    // the NOLINT markers are audited with clang-tidy (see `--nolint-audit`)
+    let is_tidy_used = file
+        .advice
+        .iter()
+        .any(|advice| advice.get_tool_name() == CLANG_TIDY_NAME);
+    if clang_params.nolint_audit && is_tidy_used {
+        let audit_result = audit_nolint(&mut file, &clang_params)?;
+        logs.extend(audit_result);
     }
     Ok((file.name.clone(), logs))
 }
 
+/// The version (and configuration) of a tool used.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolVersion {
+    /// The name of the tool (see [`Analyzer::name()`](analyzer::Analyzer::name)).
+    pub name: String,
+
+    /// The version of the tool.
+    pub version: String,
+
+    /// A summary of the configuration sources used by the tool (if any are known).
+    pub config: Option<String>,
+}
+
 /// A struct to contain the version numbers of the clang-tools used
 #[derive(Default)]
 pub struct ClangVersions {
-    /// The clang-format version used.
-    pub format_version: Option<String>,
+    /// The tools used (in the order they were run).
+    pub tools: Vec<ToolVersion>,
+}
+
+impl ClangVersions {
+    /// Summarize the configuration sources used by each of the `analyzers` for the
+    /// given `files`.
+    ///
+    /// Each distinct source is listed once (in order of appearance).
+    fn describe_config(
+        &mut self,
+        files: &[Arc<Mutex<FileObj>>],
+        clang_params: &ClangParams,
+        root: &Path,
+    ) -> Result<()> {
+        let mut sources: Vec<Vec<String>> = vec![vec![]; clang_params.analyzers.len()];
+        for file in files {
+            let file = file
+                .lock()
+                .map_err(|_| anyhow!("Failed to lock file mutex"))?;
+            let params = clang_params.resolve_for(&file.name);
+            for (analyzer, sources) in clang_params.analyzers.iter().zip(&mut sources) {
+                if let Some(source) = analyzer.analyzer.describe_config(&file.name, root, &params) {
+                    if !sources.contains(&source) {
+                        sources.push(source);
+                    }
+                }
+            }
+        }
+        for (tool, sources) in self.tools.iter_mut().zip(sources) {
+            tool.config = (!sources.is_empty()).then(|| sources.join(", "));
+        }
+        Ok(())
+    }
 
-    /// The clang-tidy version used.
-    pub tidy_version: Option<String>,
+    /// Get the version of the tool with the given `name` (if it was used).
+    pub fn get(&self, name: &str) -> Option<&ToolVersion> {
+        self.tools.iter().find(|tool| tool.name == name)
+    }
 }
 
 /// Run `clang-tool --version`, then extract and return the version number.
@@ -158,10 +216,12 @@ fn capture_clang_version(clang_tool: &PathBuf) -> Result<String> {
     Ok(captures.get(1).unwrap().as_str().to_string())
 }
 
-/// Runs clang-tidy and/or clang-format and returns the parsed output from each.
+/// Runs the enabled analyzers (ie clang-format and clang-tidy) and returns the
+/// versions of the tools used.
 ///
 /// If `tidy_checks` is `"-*"` then clang-tidy is not executed.
 /// If `style` is a blank string (`""`), then clang-format is not executed.
+/// These values can be overridden for certain files with [`ClangParams::overrides`].
 pub async fn capture_clang_tools_output(
     files: &mut Vec<Arc<Mutex<FileObj>>>,
     version: &str,
@@ -169,59 +229,105 @@ pub async fn capture_clang_tools_output(
     rest_api_client: &impl RestApiClient,
 ) -> Result<ClangVersions> {
     let mut clang_versions = ClangVersions::default();
-    // find the executable paths for clang-tidy and/or clang-format and show version
-    // info as debugging output.
-    if clang_params.tidy_checks != "-*" {
-        let exe_path = get_clang_tool_exe("clang-tidy", version)?;
-        let version_found = capture_clang_version(&exe_path)?;
-        log::debug!(
-            "{} --version: v{version_found}",
-            &exe_path.to_string_lossy()
-        );
-        clang_versions.tidy_version = Some(version_found);
-        clang_params.clang_tidy_command = Some(exe_path);
+    // find the executable path of each analyzer and show version info as debugging
+    // output.
+    for (analyzer, version_found) in
+        AnalyzerRegistry::new().resolve(&clang_params.analyzer_names, version)?
+    {
+        let name = analyzer.analyzer.name();
+        let logs = analyzer.analyzer.prepare(
+            analyzer.exe.as_deref(),
+            &version_found,
+            clang_params,
+            files,
+        )?;
+        if !logs.is_empty() {
+            rest_api_client.start_log_group(format!("Preparing {name}"));
+            for (level, msg) in logs {
+                log::log!(level, "{}", msg);
+            }
+            rest_api_client.end_log_group();
+        }
+        clang_versions.tools.push(ToolVersion {
+            name: name.to_string(),
+            version: version_found,
+            config: None,
+        });
+        clang_params.analyzers.push(analyzer);
     }
-    if !clang_params.style.is_empty() {
-        let exe_path = get_clang_tool_exe("clang-format", version)?;
-        let version_found = capture_clang_version(&exe_path)?;
-        log::debug!(
-            "{} --version: v{version_found}",
-            &exe_path.to_string_lossy()
-        );
-        clang_versions.format_version = Some(version_found);
-        clang_params.clang_format_command = Some(exe_path);
+    if !clang_versions.tools.is_empty() {
+        clang_versions.describe_config(files, clang_params, &current_dir()?)?;
     }
 
     // parse database (if provided) to match filenames when parsing clang-tidy's stdout
-    if let Some(db_path) = &clang_params.database {
-        if let Ok(db_str) = fs::read(db_path.join("compile_commands.json")) {
-            clang_params.database_json = Some(
-                // A compilation database should be UTF-8 encoded, but file paths are not; use lossy conversion.
-                serde_json::from_str::<Vec<CompilationUnit>>(&String::from_utf8_lossy(&db_str))
-                    .with_context(|| "Failed to parse compile_commands.json")?,
-            )
-        }
+    if let (Some(db_path), None) = (&clang_params.database, &clang_params.database_json) {
+        clang_params.database_json = Some(load_database(db_path)?);
     };
 
+    // Find translation units through which changed headers can be analyzed by clang-tidy.
+    // For other files missing from the database, infer the compiler flags.
+    if let (Some(units), true) = (&clang_params.database_json, clang_params.is_tidy_enabled()) {
+        let root = current_dir()?;
+        let mut graph = IncludeGraph::new(&root);
+        for file in files.iter() {
+            let file = file
+                .lock()
+                .map_err(|_| anyhow!("Failed to lock file mutex"))?;
+            if is_header(&file.name) {
+                if let Some(unit) = graph.find_including_unit(&file.name, units) {
+                    clang_params.header_units.insert(file.name.clone(), unit);
+                    continue;
+                }
+                log::debug!(
+                    "No translation unit found that includes {}",
+                    file.name.to_string_lossy()
+                );
+            }
+            if let Some((source, flags)) = infer_flags(&file.name, units, &root) {
+                log::info!(
+                    "Inferred compile flags for {} from {source}",
+                    file.name.to_string_lossy()
+                );
+                clang_params.inferred_flags.insert(file.name.clone(), flags);
+            }
+        }
+    }
+
     let mut executors = JoinSet::new();
     // iterate over the discovered files and run the clang tools
     for file in files {
+        if clang_params.jobs > 0 && executors.len() >= clang_params.jobs {
+            // wait for a file to finish before starting another (see `--jobs`)
+            if let Some(output) = executors.join_next().await {
+                show_analysis_logs(output?, rest_api_client);
+            }
+        }
         let arc_params = Arc::new(clang_params.clone());
         let arc_file = Arc::clone(file);
         executors.spawn(async move { analyze_single_file(arc_file, arc_params) });
     }
 
     while let Some(output) = executors.join_next().await {
-        if let Ok(out) = output? {
-            let (file_name, logs) = out;
+        show_analysis_logs(output?, rest_api_client);
+    }
+    Ok(clang_versions)
+}
+
+/// Show the cached logs from [`analyze_single_file()`] in a log group.
+fn show_analysis_logs(
+    output: Result<(PathBuf, Vec<(log::Level, String)>)>,
+    rest_api_client: &impl RestApiClient,
+) {
+    match output {
+        Ok((file_name, logs)) => {
             rest_api_client.start_log_group(format!("Analyzing {}", file_name.to_string_lossy()));
             for (level, msg) in logs {
                 log::log!(level, "{}", msg);
             }
             rest_api_client.end_log_group();
         }
+        Err(e) => log::error!("Failed to analyze a file: {e:?}"),
     }
-    Ok(clang_versions)
 }
 
 /// A struct to describe a single suggestion in a pull_request review.
@@ -239,42 +345,30 @@ pub struct Suggestion {
 /// A struct to describe the Pull Request review suggestions.
 #[derive(Default)]
 pub struct ReviewComments {
-    /// The total count of suggestions from clang-tidy and clang-format.
+    /// The total count of suggestions from each tool (by name).
     ///
     /// This differs from `comments.len()` because some suggestions may
     /// not fit within the file's diff.
-    pub tool_total: [Option<u32>; 2],
+    pub tool_total: HashMap<String, u32>,
     /// A list of comment suggestions to be posted.
     ///
     /// These suggestions are guaranteed to fit in the file's diff.
     pub comments: Vec<Suggestion>,
-    /// The complete patch of changes to all files scanned.
-    ///
-    /// This includes changes from both clang-tidy and clang-format
-    /// (assembled in that order).
-    pub full_patch: [String; 2],
+    /// The complete patch of changes to all files scanned from each tool (by name).
+    pub full_patch: HashMap<String, String>,
 }
 
 impl ReviewComments {
     pub fn summarize(&self, clang_versions: &ClangVersions) -> String {
         let mut body = format!("{COMMENT_MARKER}## Cpp-linter Review\n");
-        for t in 0_usize..=1 {
+        for tool in &clang_versions.tools {
+            let tool_name = tool.name.as_str();
             let mut total = 0;
-            let (tool_name, tool_version) = if t == 0 {
-                ("clang-format", clang_versions.format_version.as_ref())
-            } else {
-                ("clang-tidy", clang_versions.tidy_version.as_ref())
-            };
-            if tool_version.is_none() {
-                // this tool was not used at all
-                continue;
-            }
-            let tool_total = self.tool_total[t].unwrap_or_default();
+            let tool_total = self.tool_total.get(tool_name).copied().unwrap_or_default();
 
-            // If the tool's version is unknown, then we don't need to output this line.
-            // NOTE: If the tool was invoked at all, then the tool's version shall be known.
-            if let Some(ver_str) = tool_version {
-                body.push_str(format!("\n### Used {tool_name} v{ver_str}\n").as_str());
+            body.push_str(format!("\n### Used {tool_name} v{}\n", tool.version).as_str());
+            if let Some(config) = &tool.config {
+                body.push_str(format!("\nConfigured by {config}\n").as_str());
             }
             for comment in &self.comments {
                 if comment
@@ -293,11 +387,11 @@ impl ReviewComments {
                     .as_str(),
                 );
             }
-            if !self.full_patch[t].is_empty() {
+            let full_patch = self.full_patch.get(tool_name).cloned().unwrap_or_default();
+            if !full_patch.is_empty() {
                 body.push_str(
                     format!(
-                        "\n<details><summary>Click here for the full {tool_name} patch</summary>\n\n```diff\n{}```\n\n</details>\n",
-                        self.full_patch[t]
+                        "\n<details><summary>Click here for the full {tool_name} patch</summary>\n\n```diff\n{full_patch}```\n\n</details>\n",
                     ).as_str()
                 );
             } else {
@@ -368,7 +462,7 @@ pub trait MakeSuggestions {
         patch: &mut Patch,
         summary_only: bool,
     ) -> Result<()> {
-        let is_tidy_tool = (&self.get_tool_name() == "clang-tidy") as usize;
+        let tool_name = self.get_tool_name();
         let hunks_total = patch.num_hunks();
         let mut hunks_in_patch = 0u32;
         let file_name = file_obj
@@ -381,13 +475,17 @@ pub trait MakeSuggestions {
             .to_buf()
             .with_context(|| "Failed to convert patch to byte array")?
             .to_vec();
-        review_comments.full_patch[is_tidy_tool].push_str(
-            String::from_utf8(patch_buf.to_owned())
-                .with_context(|| format!("Failed to convert patch to string: {file_name}"))?
-                .as_str(),
-        );
+        review_comments
+            .full_patch
+            .entry(tool_name.clone())
+            .or_default()
+            .push_str(
+                String::from_utf8(patch_buf.to_owned())
+                    .with_context(|| format!("Failed to convert patch to string: {file_name}"))?
+                    .as_str(),
+            );
         if summary_only {
-            review_comments.tool_total[is_tidy_tool].get_or_insert(0);
+            review_comments.tool_total.entry(tool_name).or_insert(0);
             return Ok(());
         }
         for hunk_id in 0..hunks_total {
@@ -444,8 +542,7 @@ pub trait MakeSuggestions {
                 review_comments.comments.push(comment);
             }
         }
-        review_comments.tool_total[is_tidy_tool] =
-            Some(review_comments.tool_total[is_tidy_tool].unwrap_or_default() + hunks_in_patch);
+        *review_comments.tool_total.entry(tool_name).or_default() += hunks_in_patch;
         Ok(())
     }
 }
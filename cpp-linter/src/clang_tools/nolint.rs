@@ -0,0 +1,425 @@
+//! This module holds functionality to audit the `NOLINT` comments that suppress
+//! clang-tidy diagnostics.
+//!
+//! Each `NOLINT`, `NOLINTNEXTLINE`, and `NOLINTBEGIN`/`NOLINTEND` marker (on the
+//! lines of interest) is checked for a check name and a justification. To find unused
+//! suppressions, clang-tidy is run again on a copy of the file without any markers.
+
+use std::{
+    env::current_dir,
+    fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use fast_glob::glob_match;
+use regex::Regex;
+
+use super::clang_tidy::{parse_tidy_output, tidy_command};
+use crate::{
+    cli::{ClangParams, LinesChangedOnly},
+    common_fs::FileObj,
+};
+
+/// The kinds of `NOLINT` markers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NolintKind {
+    /// `NOLINT` suppresses diagnostics on the same line.
+    Line,
+    /// `NOLINTNEXTLINE` suppresses diagnostics on the following line.
+    NextLine,
+    /// `NOLINTBEGIN` suppresses diagnostics until a matching `NOLINTEND`.
+    Begin,
+    /// `NOLINTEND` closes a preceding `NOLINTBEGIN`.
+    End,
+}
+
+/// A `NOLINT` marker found in a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NolintMarker {
+    /// The line number of the marker.
+    pub line: u32,
+
+    /// The kind of marker.
+    pub kind: NolintKind,
+
+    /// The marker as written (ie `NOLINTNEXTLINE(bugprone-*)`).
+    pub text: String,
+
+    /// The check names (or globs) that are suppressed.
+    ///
+    /// This is empty if all checks are suppressed.
+    pub checks: Vec<String>,
+
+    /// The text following the marker that explains why diagnostics are suppressed.
+    pub justification: String,
+}
+
+impl NolintMarker {
+    /// Does this marker suppress the diagnostic with the given `check` name?
+    fn suppresses(&self, check: &str) -> bool {
+        self.checks.is_empty() || self.checks.iter().any(|glob| glob_match(glob, check))
+    }
+}
+
+/// The problems that a [`NolintMarker`] can have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NolintProblem {
+    /// The marker does not name the check(s) it suppresses.
+    NoCheckName,
+    /// The marker does not explain why diagnostics are suppressed.
+    NoJustification,
+    /// No diagnostic would be reported if the marker was removed.
+    Unused,
+    /// A `NOLINTBEGIN` without a `NOLINTEND` (or vice versa).
+    Unmatched,
+}
+
+impl NolintProblem {
+    /// A human readable description of the problem.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            NolintProblem::NoCheckName => "does not name the suppressed check(s)",
+            NolintProblem::NoJustification => "does not give a justification",
+            NolintProblem::Unused => "does not suppress any diagnostic",
+            NolintProblem::Unmatched => "has no matching NOLINTBEGIN/NOLINTEND",
+        }
+    }
+}
+
+/// A problem found with a [`NolintMarker`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NolintFinding {
+    /// The line number of the marker.
+    pub line: u32,
+
+    /// The marker as written.
+    pub marker: String,
+
+    /// The problem found with the marker.
+    pub problem: NolintProblem,
+}
+
+/// Parse all `NOLINT` markers in the given source file `content`.
+///
+/// Markers are only recognized in comments. A justification is any text that follows
+/// the marker (ignoring leading punctuation and the end of a block comment).
+pub fn parse_markers(content: &str) -> Vec<NolintMarker> {
+    let marker_pattern =
+        Regex::new(r"(?://|/\*).*?\b(NOLINT(NEXTLINE|BEGIN|END)?(?:\(([^)]*)\))?)(.*)").unwrap();
+    let mut markers = vec![];
+    for (index, line) in content.lines().enumerate() {
+        let Some(captured) = marker_pattern.captures(line) else {
+            continue;
+        };
+        let kind = match captured.get(2).map(|m| m.as_str()) {
+            Some("NEXTLINE") => NolintKind::NextLine,
+            Some("BEGIN") => NolintKind::Begin,
+            Some("END") => NolintKind::End,
+            _ => NolintKind::Line,
+        };
+        let checks = captured
+            .get(3)
+            .map(|m| {
+                m.as_str()
+                    .split(',')
+                    .map(|check| check.trim().to_string())
+                    .filter(|check| !check.is_empty() && check != "*")
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let justification = captured[4]
+            .trim()
+            .trim_end_matches("*/")
+            .trim_start_matches([':', '-', '.', ';', ' '])
+            .trim()
+            .to_string();
+        markers.push(NolintMarker {
+            line: index as u32 + 1,
+            kind,
+            text: captured[1].to_string(),
+            checks,
+            justification,
+        });
+    }
+    markers
+}
+
+/// Get the range of lines suppressed by each marker in the given `markers`.
+///
+/// The returned vector is parallel to the given `markers`. A `NOLINTEND` marker
+/// shares the range of its `NOLINTBEGIN`. An unmatched marker has no range.
+fn suppressed_ranges(markers: &[NolintMarker]) -> Vec<Option<RangeInclusive<u32>>> {
+    let mut ranges = vec![None; markers.len()];
+    let mut open: Vec<usize> = vec![];
+    for (index, marker) in markers.iter().enumerate() {
+        match marker.kind {
+            NolintKind::Line => ranges[index] = Some(marker.line..=marker.line),
+            NolintKind::NextLine => ranges[index] = Some(marker.line + 1..=marker.line + 1),
+            NolintKind::Begin => open.push(index),
+            NolintKind::End => {
+                // an END closes the nearest BEGIN with the same checks
+                if let Some(pos) = open
+                    .iter()
+                    .rposition(|begin| markers[*begin].checks == marker.checks)
+                {
+                    let begin = open.remove(pos);
+                    let range = markers[begin].line..=marker.line;
+                    ranges[begin] = Some(range.clone());
+                    ranges[index] = Some(range);
+                }
+            }
+        }
+    }
+    ranges
+}
+
+/// Audit the given `markers` without running clang-tidy.
+///
+/// The given `diagnostics` (a list of line numbers and check names) are those that
+/// clang-tidy reports when no markers are present. If [`None`], then unused
+/// suppressions are not reported.
+pub fn audit_markers(
+    markers: &[NolintMarker],
+    diagnostics: Option<&[(u32, String)]>,
+) -> Vec<NolintFinding> {
+    let ranges = suppressed_ranges(markers);
+    let mut findings = vec![];
+    for (marker, range) in markers.iter().zip(ranges) {
+        let mut add_finding = |problem| {
+            findings.push(NolintFinding {
+                line: marker.line,
+                marker: marker.text.clone(),
+                problem,
+            })
+        };
+        let Some(range) = range else {
+            add_finding(NolintProblem::Unmatched);
+            continue;
+        };
+        if marker.kind == NolintKind::End {
+            // the matching NOLINTBEGIN is audited instead
+            continue;
+        }
+        if marker.checks.is_empty() {
+            add_finding(NolintProblem::NoCheckName);
+        }
+        if marker.justification.is_empty() {
+            add_finding(NolintProblem::NoJustification);
+        }
+        if let Some(diagnostics) = diagnostics {
+            if !diagnostics
+                .iter()
+                .any(|(line, check)| range.contains(line) && marker.suppresses(check))
+            {
+                add_finding(NolintProblem::Unused);
+            }
+        }
+    }
+    findings
+}
+
+/// Replace the `NOLINT` markers in the given `content` without changing any line or
+/// column positions.
+fn strip_markers(content: &str) -> String {
+    content.replace("NOLINT", "NOLIN_")
+}
+
+/// Run clang-tidy on a copy of the given `file` (without any `NOLINT` markers) and
+/// return the line numbers and check names of the diagnostics reported about it.
+///
+/// The copy is used via a virtual file system overlay, so the `file`'s compile
+/// command (from the compilation database) still applies.
+fn find_suppressed_diagnostics(
+    file: &FileObj,
+    content: &str,
+    clang_params: &ClangParams,
+) -> Result<Vec<(u32, String)>> {
+    let tmp_dir =
+        tempfile::tempdir().with_context(|| "Failed to create a directory for the NOLINT audit")?;
+    let file_path = current_dir()?.join(&file.name);
+    let stripped_path = tmp_dir
+        .path()
+        .join(file.name.file_name().unwrap_or_default());
+    fs::write(&stripped_path, strip_markers(content))
+        .with_context(|| "Failed to write a copy of the file without NOLINT markers")?;
+    let overlay = serde_json::json!({
+        "version": 0,
+        "case-sensitive": "false",
+        "roots": [{
+            "name": file_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            "type": "directory",
+            "contents": [{
+                "name": file_path.file_name().map(PathBuf::from).unwrap_or_default(),
+                "type": "file",
+                "external-contents": stripped_path,
+            }],
+        }],
+    });
+    let overlay_path = tmp_dir.path().join("overlay.yaml");
+    fs::write(&overlay_path, overlay.to_string())
+        .with_context(|| "Failed to write the virtual file system overlay")?;
+
+    let mut cmd = tidy_command(&file.name, clang_params);
+    cmd.arg(format!("--vfsoverlay={}", overlay_path.to_string_lossy()));
+    let file_name = file.name.to_string_lossy().replace('\\', "/");
+    let header_unit = clang_params.header_units.get(&file.name);
+    if header_unit.is_some() {
+        cmd.arg(format!("--header-filter={}$", regex::escape(&file_name)));
+    }
+    cmd.arg(header_unit.unwrap_or(&file.name).as_os_str());
+    let output = cmd
+        .output()
+        .with_context(|| "Failed to run clang-tidy for the NOLINT audit")?;
+    let advice = parse_tidy_output(&output.stdout, &clang_params.database_json)?;
+    Ok(advice
+        .notes
+        .into_iter()
+        .filter(|note| note.filename == file_name)
+        // a diagnostic reported by multiple aliases lists all their names
+        .flat_map(|note| {
+            note.diagnostic
+                .split(',')
+                .map(|check| (note.line, check.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+/// Audit the `NOLINT` markers on the lines of interest in the given `file`.
+///
+/// The findings are stored in [`FileObj::nolint_advice`].
+pub fn audit_nolint(
+    file: &mut FileObj,
+    clang_params: &ClangParams,
+) -> Result<Vec<(log::Level, String)>> {
+    let mut logs = vec![];
+    let content = fs::read(&file.name)
+        .map(|buf| String::from_utf8_lossy(&buf).to_string())
+        .with_context(|| format!("Failed to read {}", file.name.to_string_lossy()))?;
+    let ranges = file.get_ranges(&clang_params.lines_changed_only);
+    let is_of_interest = |line: &u32| {
+        clang_params.lines_changed_only == LinesChangedOnly::Off
+            || ranges.iter().any(|range| range.contains(line))
+    };
+    // all markers are needed to pair each NOLINTBEGIN with a NOLINTEND
+    let markers = parse_markers(&content);
+    let audited = markers
+        .iter()
+        .filter(|marker| is_of_interest(&marker.line))
+        .count();
+    if audited == 0 {
+        file.nolint_advice = Some(vec![]);
+        return Ok(logs);
+    }
+    let diagnostics = match find_suppressed_diagnostics(file, &content, clang_params) {
+        Ok(diagnostics) => Some(diagnostics),
+        Err(e) => {
+            logs.push((
+                log::Level::Warn,
+                format!("Unused NOLINT markers could not be identified: {e:?}"),
+            ));
+            None
+        }
+    };
+    let findings = audit_markers(&markers, diagnostics.as_deref())
+        .into_iter()
+        .filter(|finding| is_of_interest(&finding.line))
+        .collect::<Vec<_>>();
+    logs.push((
+        log::Level::Info,
+        format!(
+            "Audited {audited} NOLINT marker(s) in {}: {} problem(s) found",
+            file.name.to_string_lossy(),
+            findings.len()
+        ),
+    ));
+    file.nolint_advice = Some(findings);
+    Ok(logs)
+}
+
+/// Get a total count of NOLINT audit findings from the given list of [FileObj]s.
+pub fn tally_nolint_advice(files: &[Arc<Mutex<FileObj>>]) -> u64 {
+    let mut total = 0;
+    for file in files {
+        let file = file.lock().unwrap();
+        if let Some(findings) = &file.nolint_advice {
+            total += findings.len() as u64;
+        }
+    }
+    total
+}
+
+#[cfg(test)]
+mod test {
+    use super::{audit_markers, parse_markers, strip_markers, NolintKind, NolintProblem};
+
+    const SOURCE: &str = r#"int a; // NOLINT
+int b; // NOLINT(readability-*): legacy naming
+// NOLINTNEXTLINE(bugprone-narrowing-conversions, cert-*) -- intentional
+int c = 1.5;
+/* NOLINTBEGIN(modernize-*) generated code */
+int d;
+// NOLINTEND(modernize-*)
+// NOLINTBEGIN
+const char *s = "NOLINT in a string is ignored";
+"#;
+
+    #[test]
+    fn parse() {
+        let markers = parse_markers(SOURCE);
+        assert_eq!(markers.len(), 6);
+        assert_eq!(markers[0].kind, NolintKind::Line);
+        assert!(markers[0].checks.is_empty());
+        assert!(markers[0].justification.is_empty());
+        assert_eq!(markers[1].checks, vec!["readability-*"]);
+        assert_eq!(markers[1].justification, "legacy naming");
+        assert_eq!(markers[2].kind, NolintKind::NextLine);
+        assert_eq!(
+            markers[2].checks,
+            vec!["bugprone-narrowing-conversions", "cert-*"]
+        );
+        assert_eq!(markers[2].justification, "intentional");
+        assert_eq!(markers[3].kind, NolintKind::Begin);
+        assert_eq!(markers[3].justification, "generated code");
+        assert_eq!(markers[4].kind, NolintKind::End);
+        assert_eq!(markers[5].line, 8);
+    }
+
+    #[test]
+    fn audit() {
+        let markers = parse_markers(SOURCE);
+        let diagnostics = vec![
+            (1, "readability-identifier-length".to_string()),
+            (4, "bugprone-narrowing-conversions".to_string()),
+        ];
+        let findings = audit_markers(&markers, Some(&diagnostics));
+        let summary = findings
+            .iter()
+            .map(|f| (f.line, f.problem))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (1, NolintProblem::NoCheckName),
+                (1, NolintProblem::NoJustification),
+                (2, NolintProblem::Unused),
+                (5, NolintProblem::Unused),
+                (8, NolintProblem::Unmatched),
+            ]
+        );
+        // unused markers are not reported without diagnostics
+        assert!(audit_markers(&markers, None)
+            .iter()
+            .all(|f| f.problem != NolintProblem::Unused));
+    }
+
+    #[test]
+    fn strip() {
+        let stripped = strip_markers(SOURCE);
+        assert!(parse_markers(&stripped).is_empty());
+        assert_eq!(stripped.len(), SOURCE.len());
+    }
+}
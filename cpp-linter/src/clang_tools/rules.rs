@@ -0,0 +1,265 @@
+//! This module holds a lightweight rule engine for house rules that neither
+//! clang-format nor clang-tidy can express (ie banned functions or a TODO format).
+//!
+//! Rules are defined in the `[[rules]]` tables of a configuration file. Each rule
+//! matches a regular expression (or a whole-word token) against the changed lines
+//! of a file. A required rule instead must match within the first lines of each file
+//! (ie a copyright header). The rule engine is an [`Analyzer`] named `rules`, which
+//! is used automatically when any rules are configured.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+
+use super::analyzer::{Analysis, Analyzer, AnalyzerAdvice, AnalyzerDiagnostic};
+use crate::{
+    cli::{ClangParams, LinesChangedOnly},
+    common_fs::FileObj,
+};
+
+/// The name of the rule engine (as used with `--analyzers`).
+pub const RULES_NAME: &str = "rules";
+
+/// The severities that a [`Rule`] can have.
+pub const RULE_SEVERITIES: [&str; 3] = ["error", "warning", "note"];
+
+/// A house rule that is checked on the changed lines of each file.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    /// The rule's id (reported like a clang-tidy check name).
+    pub id: String,
+
+    /// The message reported for each violation.
+    pub message: String,
+
+    /// The severity of each violation (see [`RULE_SEVERITIES`]).
+    pub severity: String,
+
+    /// The pattern that matches a violation.
+    pub pattern: Regex,
+
+    /// If set, the `pattern` is required to match within this many lines at the
+    /// start of each file. A violation is then reported if it does not match.
+    pub required_within: Option<u32>,
+}
+
+impl Rule {
+    /// Create a rule from a regular expression `pattern`.
+    pub fn new(id: &str, pattern: &str, message: &str, severity: &str) -> Result<Self> {
+        if !RULE_SEVERITIES.contains(&severity) {
+            return Err(anyhow!(
+                "Invalid severity '{severity}' for rule '{id}'. Possible values are: {}",
+                RULE_SEVERITIES.join(", ")
+            ));
+        }
+        let pattern = Regex::new(pattern)
+            .with_context(|| format!("Invalid pattern for rule '{id}': {pattern}"))?;
+        Ok(Self {
+            id: id.to_string(),
+            message: message.to_string(),
+            severity: severity.to_string(),
+            pattern,
+            required_within: None,
+        })
+    }
+
+    /// Require this rule's pattern to match within the first `lines` of each file.
+    pub fn required_within(mut self, lines: u32) -> Self {
+        self.required_within = Some(lines);
+        self
+    }
+
+    /// Create a rule that matches the given `token` as a whole word.
+    pub fn from_token(id: &str, token: &str, message: &str, severity: &str) -> Result<Self> {
+        Self::new(
+            id,
+            &format!(r"\b{}\b", regex::escape(token)),
+            message,
+            severity,
+        )
+    }
+}
+
+/// Check the given `rules` against the lines of a file's `content`.
+///
+/// Only the lines for which `is_checked` returns true are checked for violations.
+/// Required rules are checked regardless of which lines changed; a missing match is
+/// reported on the first line.
+fn check_rules(
+    rules: &[Rule],
+    content: &str,
+    is_checked: impl Fn(u32) -> bool,
+) -> Vec<AnalyzerDiagnostic> {
+    let mut diagnostics = vec![];
+    for rule in rules {
+        if let Some(within) = rule.required_within {
+            if !content
+                .lines()
+                .take(within as usize)
+                .any(|line| rule.pattern.is_match(line))
+            {
+                diagnostics.push(AnalyzerDiagnostic {
+                    line: 1,
+                    cols: 1,
+                    severity: rule.severity.clone(),
+                    check: rule.id.clone(),
+                    message: rule.message.clone(),
+                    related: vec![],
+                });
+            }
+        }
+    }
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index as u32 + 1;
+        if !is_checked(line_number) {
+            continue;
+        }
+        for rule in rules.iter().filter(|rule| rule.required_within.is_none()) {
+            if let Some(found) = rule.pattern.find(line) {
+                diagnostics.push(AnalyzerDiagnostic {
+                    line: line_number,
+                    // a column counts characters (not bytes)
+                    cols: line[..found.start()].chars().count() as u32 + 1,
+                    severity: rule.severity.clone(),
+                    check: rule.id.clone(),
+                    message: rule.message.clone(),
+                    related: vec![],
+                });
+            }
+        }
+    }
+    diagnostics
+}
+
+/// The built-in rule engine that checks the [`ClangParams::rules`].
+///
+/// With `--lines-changed-only=diff`, the rules are checked on the lines in the diff.
+/// Otherwise, only the added lines are checked. So, a file without a diff (ie when
+/// `--lines-changed-only=false` analyzes all files) is only checked against the
+/// required rules.
+#[derive(Debug, Default)]
+pub struct RuleEngine;
+
+impl Analyzer for RuleEngine {
+    fn name(&self) -> &'static str {
+        RULES_NAME
+    }
+
+    fn find_executable(&self, _version: &str) -> Result<Option<PathBuf>> {
+        Ok(None)
+    }
+
+    fn analyze(
+        &self,
+        _exe: Option<&Path>,
+        file: &FileObj,
+        clang_params: &ClangParams,
+    ) -> Result<Analysis> {
+        let mut advice = AnalyzerAdvice {
+            tool: self.name().to_string(),
+            ..Default::default()
+        };
+        if clang_params.rules.is_empty() {
+            return Ok((Box::new(advice), vec![]));
+        }
+        let content = fs::read(&file.name).with_context(|| {
+            format!(
+                "Failed to read the contents of {}",
+                file.name.to_string_lossy()
+            )
+        })?;
+        let content = String::from_utf8_lossy(&content);
+        let mut logs = vec![];
+        if file.added_lines.is_empty()
+            && clang_params.lines_changed_only != LinesChangedOnly::Diff
+            && clang_params
+                .rules
+                .iter()
+                .any(|r| r.required_within.is_none())
+        {
+            logs.push((
+                log::Level::Debug,
+                format!(
+                    "{} has no added lines to check against the house rules",
+                    file.name.to_string_lossy()
+                ),
+            ));
+        }
+        advice.diagnostics = if clang_params.lines_changed_only == LinesChangedOnly::Diff {
+            check_rules(&clang_params.rules, &content, |line| {
+                file.diff_chunks.iter().any(|r| r.contains(&line))
+            })
+        } else {
+            check_rules(&clang_params.rules, &content, |line| {
+                file.added_lines.contains(&line)
+            })
+        };
+        Ok((Box::new(advice), logs))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_rules, Rule};
+
+    #[test]
+    fn invalid_rules() {
+        assert!(Rule::new("bad-regex", "(", "message", "warning").is_err());
+        assert!(Rule::new("bad-severity", "x", "message", "fatal").is_err());
+    }
+
+    #[test]
+    fn check_changed_lines() {
+        let rules = vec![
+            Rule::from_token("banned-strcpy", "strcpy", "Use strncpy instead", "error").unwrap(),
+            Rule::new(
+                "todo-format",
+                r"TODO([^(]|$)",
+                "Use the TODO(name) format",
+                "warning",
+            )
+            .unwrap(),
+        ];
+        let content = "// TODO fix this\nstrcpy(a, b);\nmy_strcpy(a, b);\n// TODO(me): ok\n";
+        let diagnostics = check_rules(&rules, content, |line| line != 1);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 2);
+        assert_eq!(diagnostics[0].cols, 1);
+        assert_eq!(diagnostics[0].check, "banned-strcpy");
+        assert_eq!(diagnostics[0].severity, "error");
+
+        let diagnostics = check_rules(&rules, content, |_| true);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].check, "todo-format");
+        assert_eq!(diagnostics[0].cols, 4);
+
+        // columns count characters, not bytes
+        let diagnostics = check_rules(&rules, "/* ä */ strcpy(a, b);\n", |_| true);
+        assert_eq!(diagnostics[0].cols, 9);
+    }
+
+    #[test]
+    fn required_header() {
+        let rules = vec![Rule::new(
+            "copyright",
+            r"Copyright \(c\) \d{4}",
+            "Add a copyright header",
+            "error",
+        )
+        .unwrap()
+        .required_within(3)];
+        let content = "// SPDX-License-Identifier: MIT\n// Copyright (c) 2024 Me\nint x;\n";
+        // required rules are checked even if no lines changed
+        assert!(check_rules(&rules, content, |_| false).is_empty());
+
+        let content = "int x;\n\n\n// Copyright (c) 2024 Me\n";
+        let diagnostics = check_rules(&rules, content, |_| false);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 1);
+        assert_eq!(diagnostics[0].check, "copyright");
+    }
+}
@@ -0,0 +1,494 @@
+//! This module holds the [`Analyzer`] implementation for the
+//! [Clang Static Analyzer](https://clang.llvm.org/docs/ClangStaticAnalyzer.html).
+//!
+//! This is a deeper (and slower) analysis than clang-tidy's `clang-analyzer-*` checks.
+//! Each file (or the translation unit through which a header is analyzed) is compiled
+//! with `clang --analyze`, and the resulting SARIF report is parsed. The bug path of
+//! each report is kept as the diagnostic's related locations.
+
+use std::{
+    env::current_dir,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use super::{
+    analyzer::{
+        run_command, timeout_log, Analysis, Analyzer, AnalyzerAdvice, AnalyzerDiagnostic,
+        RelatedLocation,
+    },
+    get_clang_tool_exe,
+};
+use crate::{
+    cli::{ClangParams, LinesChangedOnly},
+    common_fs::{normalize_path, FileObj},
+};
+
+/// The name of the analyzer (as used with `--analyzers`).
+const STATIC_ANALYZER_NAME: &str = "clang-static-analyzer";
+
+/// The name of the SARIF report written by `clang --analyze`.
+const REPORT_FILE_NAME: &str = "report.sarif";
+
+/// Used to deserialize the relevant parts of a SARIF report.
+#[derive(Debug, Deserialize, Default)]
+struct SarifLog {
+    #[serde(default)]
+    runs: Vec<SarifRun>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct SarifRun {
+    #[serde(default)]
+    artifacts: Vec<SarifArtifact>,
+    #[serde(default)]
+    results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SarifArtifact {
+    location: SarifArtifactLocation,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct SarifArtifactLocation {
+    uri: Option<String>,
+    index: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct SarifMessage {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    #[serde(default)]
+    rule_id: String,
+    level: Option<String>,
+    #[serde(default)]
+    message: SarifMessage,
+    #[serde(default)]
+    locations: Vec<SarifLocation>,
+    #[serde(default)]
+    code_flows: Vec<SarifCodeFlow>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifLocation {
+    physical_location: Option<SarifPhysicalLocation>,
+    message: Option<SarifMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifPhysicalLocation {
+    #[serde(default)]
+    artifact_location: SarifArtifactLocation,
+    region: Option<SarifRegion>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifRegion {
+    #[serde(default)]
+    start_line: u32,
+    #[serde(default)]
+    start_column: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifCodeFlow {
+    #[serde(default)]
+    thread_flows: Vec<SarifThreadFlow>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SarifThreadFlow {
+    #[serde(default)]
+    locations: Vec<SarifThreadFlowLocation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SarifThreadFlowLocation {
+    location: SarifLocation,
+}
+
+/// Convert a `file://` URI into a path.
+///
+/// Percent-encoded characters (ie `%20`) are decoded.
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    // a Windows path is given as `file:///C:/...`
+    let path = match path.as_bytes() {
+        [b'/', _, b':', ..] => &path[1..],
+        _ => path,
+    };
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' && index + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).unwrap_or_default();
+            if let Ok(value) = u8::from_str_radix(hex, 16) {
+                decoded.push(value);
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    PathBuf::from(String::from_utf8_lossy(&decoded).to_string())
+}
+
+impl SarifLocation {
+    /// Get the (normalized absolute) path, line, and column of this location.
+    ///
+    /// Relative paths are resolved against `root`.
+    fn resolve(&self, artifacts: &[SarifArtifact], root: &Path) -> Option<(PathBuf, u32, u32)> {
+        let physical = self.physical_location.as_ref()?;
+        let artifact = &physical.artifact_location;
+        let uri = match (&artifact.uri, artifact.index) {
+            (Some(uri), _) => uri,
+            (None, Some(index)) => artifacts.get(index)?.location.uri.as_ref()?,
+            (None, None) => return None,
+        };
+        let region = physical.region.as_ref()?;
+        Some((
+            normalize_path(&root.join(uri_to_path(uri))),
+            region.start_line,
+            region.start_column,
+        ))
+    }
+}
+
+/// Parse a SARIF `report` into diagnostics about the given `file`.
+///
+/// Only results whose primary location is in the `file` are kept. The steps of each
+/// result's bug path become the diagnostic's related locations. Relative paths are
+/// resolved against `root`.
+fn parse_sarif_report(report: &str, file: &Path, root: &Path) -> Result<Vec<AnalyzerDiagnostic>> {
+    let log = serde_json::from_str::<SarifLog>(report)
+        .with_context(|| "Failed to parse the Clang Static Analyzer's SARIF report")?;
+    let file_path = normalize_path(&root.join(file));
+    let display = |path: &Path| {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    };
+    let mut diagnostics = vec![];
+    for run in &log.runs {
+        for result in &run.results {
+            let Some((path, line, cols)) = result
+                .locations
+                .first()
+                .and_then(|loc| loc.resolve(&run.artifacts, root))
+            else {
+                continue;
+            };
+            if path != file_path {
+                continue;
+            }
+            let related = result
+                .code_flows
+                .iter()
+                .flat_map(|flow| &flow.thread_flows)
+                .flat_map(|thread| &thread.locations)
+                .filter_map(|step| {
+                    let (path, line, cols) = step.location.resolve(&run.artifacts, root)?;
+                    Some(RelatedLocation {
+                        file: display(&path),
+                        line,
+                        cols,
+                        message: step
+                            .location
+                            .message
+                            .as_ref()
+                            .map(|m| m.text.clone())
+                            .unwrap_or_default(),
+                    })
+                })
+                .collect();
+            diagnostics.push(AnalyzerDiagnostic {
+                line,
+                cols,
+                severity: result.level.clone().unwrap_or(String::from("warning")),
+                check: result.rule_id.clone(),
+                message: result.message.text.clone(),
+                related,
+            });
+        }
+    }
+    Ok(diagnostics)
+}
+
+/// Run the [Clang Static Analyzer](https://clang.llvm.org/docs/ClangStaticAnalyzer.html)
+/// on files.
+#[derive(Debug, Default)]
+pub struct StaticAnalyzer;
+
+impl StaticAnalyzer {
+    /// Create a `clang --analyze` command for the given `file` (relative to `root`)
+    /// that writes a SARIF report to the given `report` path.
+    ///
+    /// The compiler flags are taken from the compilation database. A header is
+    /// analyzed through a translation unit that includes it (if any).
+    fn command(
+        exe: &Path,
+        file: &Path,
+        root: &Path,
+        report: &Path,
+        clang_params: &ClangParams,
+    ) -> Command {
+        let mut cmd = Command::new(exe);
+        cmd.args(["--analyze", "--analyzer-output", "sarif", "-o"])
+            .arg(report.as_os_str());
+        let unit_name = clang_params
+            .header_units
+            .get(file)
+            .map(PathBuf::as_path)
+            .unwrap_or(file);
+        let unit_path = normalize_path(&root.join(unit_name));
+        let db_unit = clang_params
+            .database_json
+            .as_ref()
+            .and_then(|units| units.iter().find(|unit| unit.path() == unit_path));
+        match db_unit {
+            Some(unit) => {
+                cmd.args(unit.reusable_flags());
+            }
+            None => {
+                let inferred_flags = clang_params
+                    .inferred_flags
+                    .get(file)
+                    .map(|flags| flags.as_slice())
+                    .unwrap_or_default();
+                cmd.args(inferred_flags);
+            }
+        }
+        cmd.args(&clang_params.extra_args);
+        cmd.arg(unit_name.as_os_str());
+        cmd
+    }
+}
+
+impl Analyzer for StaticAnalyzer {
+    fn name(&self) -> &'static str {
+        STATIC_ANALYZER_NAME
+    }
+
+    fn find_executable(&self, version: &str) -> Result<Option<PathBuf>> {
+        get_clang_tool_exe("clang", version)
+            .map(Some)
+            .map_err(|_| anyhow!("Could not find clang (for the Clang Static Analyzer)"))
+    }
+
+    fn analyze(
+        &self,
+        exe: Option<&Path>,
+        file: &FileObj,
+        clang_params: &ClangParams,
+    ) -> Result<Analysis> {
+        let exe = exe.ok_or(anyhow!("clang executable was not found"))?;
+        let root = current_dir().with_context(|| "Failed to get current working directory")?;
+        let report_dir = tempfile::tempdir()
+            .with_context(|| "Failed to create a directory for the analyzer's report")?;
+        let report = report_dir.path().join(REPORT_FILE_NAME);
+        let mut logs = vec![];
+        let mut advice = AnalyzerAdvice {
+            tool: self.name().to_string(),
+            ..Default::default()
+        };
+        let mut cmd = Self::command(exe, &file.name, &root, &report, clang_params);
+        logs.push((
+            log::Level::Info,
+            format!(
+                "Running \"{} {}\"",
+                cmd.get_program().to_string_lossy(),
+                cmd.get_args()
+                    .map(|x| x.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        ));
+        let Some(output) = run_command(&mut cmd, clang_params.analyzer_timeout)? else {
+            logs.push(timeout_log(
+                self.name(),
+                &file.name,
+                clang_params.analyzer_timeout,
+            ));
+            return Ok((Box::new(advice), logs));
+        };
+        if !output.status.success() {
+            logs.push((
+                log::Level::Warn,
+                format!(
+                    "The Clang Static Analyzer failed to analyze {} ({}):\n{}",
+                    file.name.to_string_lossy(),
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        if !report.exists() {
+            return Ok((Box::new(advice), logs));
+        }
+        let sarif = fs::read_to_string(&report)
+            .with_context(|| "Failed to read the Clang Static Analyzer's SARIF report")?;
+        logs.push((
+            log::Level::Debug,
+            format!("Report from the Clang Static Analyzer:\n{sarif}"),
+        ));
+        let mut diagnostics = parse_sarif_report(&sarif, &file.name, &root)?;
+        if clang_params.lines_changed_only != LinesChangedOnly::Off {
+            // keep a bug whose path passes through any changed line of the file
+            let ranges = file.get_ranges(&clang_params.lines_changed_only);
+            let file_name = file.name.to_string_lossy().replace('\\', "/");
+            let is_changed = |line: &u32| ranges.iter().any(|r| r.contains(line));
+            diagnostics.retain(|d| {
+                is_changed(&d.line)
+                    || d.related
+                        .iter()
+                        .any(|step| step.file == file_name && is_changed(&step.line))
+            });
+        }
+        advice.diagnostics = diagnostics;
+        Ok((Box::new(advice), logs))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{parse_sarif_report, uri_to_path, StaticAnalyzer};
+    use crate::{clang_tools::compilation_db::CompilationUnit, cli::ClangParams};
+
+    const SARIF: &str = r#"{
+  "version": "2.1.0",
+  "runs": [{
+    "artifacts": [
+      {"location": {"uri": "file:///repo/src/demo.cpp"}, "length": 120},
+      {"location": {"uri": "file:///repo/src/other%20file.cpp"}}
+    ],
+    "results": [
+      {
+        "ruleId": "core.NullDereference",
+        "level": "warning",
+        "message": {"text": "Dereference of null pointer (loaded from variable 'p')"},
+        "locations": [{"physicalLocation": {
+          "artifactLocation": {"index": 0, "uri": "file:///repo/src/demo.cpp"},
+          "region": {"startLine": 7, "startColumn": 6, "endColumn": 7}
+        }}],
+        "codeFlows": [{"threadFlows": [{"locations": [
+          {"location": {"message": {"text": "'p' initialized to a null pointer value"}, "physicalLocation": {
+            "artifactLocation": {"index": 0},
+            "region": {"startLine": 5, "startColumn": 3}
+          }}, "importance": "important"},
+          {"location": {"message": {"text": "Dereference of null pointer (loaded from variable 'p')"}, "physicalLocation": {
+            "artifactLocation": {"index": 0},
+            "region": {"startLine": 7, "startColumn": 6}
+          }}, "importance": "essential"}
+        ]}]}]
+      },
+      {
+        "ruleId": "deadcode.DeadStores",
+        "message": {"text": "Value stored to 'x' is never read"},
+        "locations": [{"physicalLocation": {
+          "artifactLocation": {"index": 1},
+          "region": {"startLine": 3, "startColumn": 5}
+        }}]
+      }
+    ]
+  }]
+}"#;
+
+    #[test]
+    fn parse_report() {
+        let root = Path::new("/repo");
+        let diagnostics = parse_sarif_report(SARIF, Path::new("src/demo.cpp"), root).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.check, "core.NullDereference");
+        assert_eq!(diagnostic.severity, "warning");
+        assert_eq!((diagnostic.line, diagnostic.cols), (7, 6));
+        assert_eq!(diagnostic.related.len(), 2);
+        assert_eq!(diagnostic.related[0].file, "src/demo.cpp");
+        assert_eq!(diagnostic.related[0].line, 5);
+        assert_eq!(
+            diagnostic.describe_related(""),
+            "1. src/demo.cpp:5:3: 'p' initialized to a null pointer value\n\
+             2. src/demo.cpp:7:6: Dereference of null pointer (loaded from variable 'p')\n"
+        );
+
+        let diagnostics = parse_sarif_report(SARIF, Path::new("src/other file.cpp"), root).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, "warning");
+        assert!(diagnostics[0].related.is_empty());
+    }
+
+    #[test]
+    fn decode_uri() {
+        assert_eq!(
+            uri_to_path("file:///repo/a%20b.cpp"),
+            PathBuf::from("/repo/a b.cpp")
+        );
+        assert_eq!(
+            uri_to_path("file:///C:/repo/a.cpp"),
+            PathBuf::from("C:/repo/a.cpp")
+        );
+        assert_eq!(uri_to_path("src/100%.cpp"), PathBuf::from("src/100%.cpp"));
+    }
+
+    #[test]
+    fn command_from_database() {
+        let root = Path::new("/repo");
+        let clang_params = ClangParams {
+            database_json: Some(vec![CompilationUnit {
+                directory: "/repo/build".to_string(),
+                file: "../src/demo.cpp".to_string(),
+                arguments: Some(vec![
+                    "clang++".to_string(),
+                    "-I../include".to_string(),
+                    "-c".to_string(),
+                    "../src/demo.cpp".to_string(),
+                ]),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let cmd = StaticAnalyzer::command(
+            Path::new("clang"),
+            Path::new("src/demo.cpp"),
+            root,
+            Path::new("/tmp/report.sarif"),
+            &clang_params,
+        );
+        let args = cmd
+            .get_args()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            args,
+            vec![
+                "--analyze",
+                "--analyzer-output",
+                "sarif",
+                "-o",
+                "/tmp/report.sarif",
+                "-I/repo/include",
+                "src/demo.cpp"
+            ]
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! This module aggregates the concerns reported by clang-tidy and any additional
+//! analyzers into statistics per diagnostic name and per severity.
+//!
+//! These statistics give an overview of which checks fire most often (and where),
+//! which helps decide which checks need tuning.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+
+use crate::common_fs::FileObj;
+
+/// The number of concerns reported for a diagnostic name or severity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckStat {
+    /// The diagnostic name or severity.
+    pub name: String,
+
+    /// The number of concerns reported.
+    pub count: u64,
+
+    /// The files in which concerns were reported (sorted alphabetically).
+    pub files: Vec<String>,
+}
+
+/// The statistics about all concerns reported for a set of files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CheckStats {
+    /// The statistics per diagnostic name (sorted by frequency).
+    pub checks: Vec<CheckStat>,
+
+    /// The statistics per severity (sorted by frequency).
+    pub severities: Vec<CheckStat>,
+}
+
+/// The maximum number of files listed per row of the [`CheckStats::make_table()`].
+const MAX_LISTED_FILES: usize = 3;
+
+/// Escape the given `text` for use in a cell of a markdown table.
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// Sort the tallies into a list of [`CheckStat`]s (most frequent first).
+fn sort_stats(tallies: HashMap<String, (u64, BTreeSet<String>)>) -> Vec<CheckStat> {
+    let mut stats = tallies
+        .into_iter()
+        .map(|(name, (count, files))| CheckStat {
+            name,
+            count,
+            files: files.into_iter().collect(),
+        })
+        .collect::<Vec<_>>();
+    stats.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    stats
+}
+
+impl CheckStats {
+    /// Aggregate the concerns that the analyzers (ie clang-tidy) reported about the
+    /// given `files` (see [`Advice::checks()`](super::analyzer::Advice::checks)).
+    ///
+    /// Like the thread comment, only clang-tidy notes about the files themselves
+    /// are counted. A clang-tidy note reported by multiple aliases of the same check
+    /// (ie `hicpp-avoid-c-arrays,modernize-avoid-c-arrays`) is counted for each alias
+    /// (see [`TidyNotification::diagnostic_link()`](super::clang_tidy::TidyNotification::diagnostic_link)), but only once for its severity.
+    pub fn new(files: &[Arc<Mutex<FileObj>>]) -> Self {
+        let mut checks: HashMap<String, (u64, BTreeSet<String>)> = HashMap::new();
+        let mut severities: HashMap<String, (u64, BTreeSet<String>)> = HashMap::new();
+        let mut tally = |check: &str, severity: &str, file_name: &str| {
+            let names = check.split(',').map(|name| (true, name));
+            for (is_check, name) in names.chain([(false, severity)]) {
+                let tallies = if is_check {
+                    &mut checks
+                } else {
+                    &mut severities
+                };
+                let (count, files) = tallies.entry(name.to_string()).or_default();
+                *count += 1;
+                files.insert(file_name.to_string());
+            }
+        };
+        for file in files {
+            let file = file.lock().unwrap();
+            let file_name = file.name.to_string_lossy().replace('\\', "/");
+            for advice in &file.advice {
+                for (check, severity) in advice.checks(&file) {
+                    tally(&check, &severity, &file_name);
+                }
+            }
+        }
+        Self {
+            checks: sort_stats(checks),
+            severities: sort_stats(severities),
+        }
+    }
+
+    /// Are there no concerns to describe?
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+
+    /// Describe the statistics as markdown tables (within a collapsible section).
+    ///
+    /// Each row lists up to [`MAX_LISTED_FILES`] of the affected files.
+    pub fn make_table(&self) -> String {
+        let mut table = String::from("\n<details><summary>Check statistics</summary>\n\n");
+        for (heading, stats) in [("Diagnostic", &self.checks), ("Severity", &self.severities)] {
+            table.push_str(&format!("| {heading} | Count | Files |\n|:--|--:|:--|\n"));
+            for stat in stats {
+                let mut files = stat
+                    .files
+                    .iter()
+                    .take(MAX_LISTED_FILES)
+                    .map(|file| escape_cell(file))
+                    .collect::<Vec<_>>();
+                if stat.files.len() > MAX_LISTED_FILES {
+                    files.push(format!(
+                        "… and {} more",
+                        stat.files.len() - MAX_LISTED_FILES
+                    ));
+                }
+                table.push_str(&format!(
+                    "| {} | {} | {} |\n",
+                    escape_cell(&stat.name),
+                    stat.count,
+                    files.join(", ")
+                ));
+            }
+            table.push('\n');
+        }
+        table.push_str("</details>\n");
+        table
+    }
+
+    /// Serialize the statistics as a single-line JSON string.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    use super::CheckStats;
+    use crate::{
+        clang_tools::{
+            analyzer::{AnalyzerAdvice, AnalyzerDiagnostic},
+            clang_tidy::{TidyAdvice, TidyNotification},
+        },
+        common_fs::FileObj,
+    };
+
+    fn tidy_note(filename: &str, severity: &str, diagnostic: &str) -> TidyNotification {
+        TidyNotification {
+            filename: filename.to_string(),
+            line: 1,
+            cols: 1,
+            severity: severity.to_string(),
+            rationale: String::from("A test dummy rationale"),
+            diagnostic: diagnostic.to_string(),
+            suggestion: vec![],
+            fixed_lines: vec![],
+            doc_urls: vec![],
+        }
+    }
+
+    #[test]
+    fn aggregate() {
+        let mut demo = FileObj::new(PathBuf::from("src/demo.cpp"));
+        demo.advice.push(Arc::new(TidyAdvice {
+            notes: vec![
+                tidy_note("src/demo.cpp", "warning", "readability-magic-numbers"),
+                tidy_note("src/demo.cpp", "warning", "readability-magic-numbers"),
+                tidy_note("src/demo.cpp", "error", "bugprone-use-after-move"),
+                // notes about other files are not counted
+                tidy_note("src/demo.hpp", "warning", "readability-magic-numbers"),
+            ],
+            patched: None,
+        }));
+        let mut other = FileObj::new(PathBuf::from("src/other.cpp"));
+        other.advice.push(Arc::new(TidyAdvice {
+            notes: vec![tidy_note(
+                "src/other.cpp",
+                "warning",
+                "readability-magic-numbers",
+            )],
+            patched: None,
+        }));
+        other.advice.push(Arc::new(AnalyzerAdvice {
+            tool: String::from("cppcheck"),
+            diagnostics: vec![AnalyzerDiagnostic {
+                line: 2,
+                cols: 1,
+                severity: String::from("error"),
+                check: String::from("nullPointer"),
+                message: String::from("Null pointer dereference"),
+                related: vec![],
+            }],
+            patched: None,
+        }));
+        let files = vec![Arc::new(Mutex::new(demo)), Arc::new(Mutex::new(other))];
+        let stats = CheckStats::new(&files);
+
+        let checks = stats
+            .checks
+            .iter()
+            .map(|s| (s.name.as_str(), s.count, s.files.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            checks,
+            vec![
+                ("readability-magic-numbers", 3, 2),
+                ("bugprone-use-after-move", 1, 1),
+                ("nullPointer", 1, 1),
+            ]
+        );
+        let severities = stats
+            .severities
+            .iter()
+            .map(|s| (s.name.as_str(), s.count, s.files.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            severities,
+            vec![
+                (
+                    "warning",
+                    3,
+                    vec![String::from("src/demo.cpp"), String::from("src/other.cpp")]
+                ),
+                (
+                    "error",
+                    2,
+                    vec![String::from("src/demo.cpp"), String::from("src/other.cpp")]
+                ),
+            ]
+        );
+
+        let table = stats.make_table();
+        assert!(table.contains("| readability-magic-numbers | 3 | src/demo.cpp, src/other.cpp |\n"));
+        assert!(table.contains("| error | 2 | src/demo.cpp, src/other.cpp |\n"));
+        let json = stats.to_json();
+        assert!(!json.contains('\n'));
+        assert!(json.starts_with(
+            r#"{"checks":[{"name":"readability-magic-numbers","count":3,"files":["src/demo.cpp","src/other.cpp"]}"#
+        ));
+    }
+
+    #[test]
+    fn aliases_and_table_cells() {
+        let files = (1..=5)
+            .map(|index| {
+                let name = format!("src/demo|{index}.cpp");
+                let mut file = FileObj::new(PathBuf::from(&name));
+                file.advice.push(Arc::new(TidyAdvice {
+                    notes: vec![tidy_note(
+                        &name,
+                        "warning",
+                        "hicpp-avoid-c-arrays,modernize-avoid-c-arrays",
+                    )],
+                    patched: None,
+                }));
+                Arc::new(Mutex::new(file))
+            })
+            .collect::<Vec<_>>();
+        let stats = CheckStats::new(&files);
+        let checks = stats
+            .checks
+            .iter()
+            .map(|s| (s.name.as_str(), s.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            checks,
+            vec![("hicpp-avoid-c-arrays", 5), ("modernize-avoid-c-arrays", 5)]
+        );
+        assert_eq!(stats.severities[0].count, 5);
+
+        let table = stats.make_table();
+        assert!(table.contains(
+            "| hicpp-avoid-c-arrays | 5 | src/demo\\|1.cpp, src/demo\\|2.cpp, src/demo\\|3.cpp, … and 2 more |\n"
+        ));
+    }
+
+    #[test]
+    fn no_concerns() {
+        let files = vec![Arc::new(Mutex::new(FileObj::new(PathBuf::from(
+            "src/demo.cpp",
+        ))))];
+        let stats = CheckStats::new(&files);
+        assert!(stats.is_empty());
+        assert_eq!(stats.to_json(), r#"{"checks":[],"severities":[]}"#);
+    }
+}
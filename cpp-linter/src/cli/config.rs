@@ -0,0 +1,628 @@
+//! This module holds the functionality to read options from a project's
+//! configuration file.
+//!
+//! Options can be stored in a `cpp-linter.toml` file or in the `[tool.cpp-linter]`
+//! table of a `pyproject.toml` file. Each key is named after the long form of a
+//! CLI option (without the leading `--`). Options passed on the command line take
+//! precedence over values in the configuration file.
+//!
+//! ```toml
+//! tidy-checks = ["-*", "bugprone-*", "performance-*"]
+//! extensions = ["cpp", "hpp"]
+//! ignore = ["build", "third_party"]
+//! lines-changed-only = "diff"
+//! thread-comments = "update"
+//! tidy-review = true
+//!
+//! # options that only apply to certain paths
+//! [[overrides]]
+//! paths = ["third_party/**"]
+//! tidy-checks = "-*,bugprone-*"
+//! style = ""
+//! lines-changed-only = false
+//!
+//! # house rules checked on the changed lines
+//! [[rules]]
+//! id = "banned-strcpy"
+//! token = "strcpy"
+//! message = "Use strncpy() instead"
+//! severity = "error"
+//!
+//! # a pattern that must match within the first lines of each file
+//! [[rules]]
+//! id = "copyright-header"
+//! pattern = 'Copyright \(c\) \d{4}'
+//! must-match-within = 5
+//! message = "Add a copyright header"
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Context, Result};
+use clap::{parser::ValueSource, ArgMatches};
+use serde::Deserialize;
+
+use super::{ClangOverride, Cli, LinesChangedOnly, ThreadComments};
+use crate::{
+    clang_tools::{clang_tidy::TidyDocLink, rules::Rule},
+    common_fs::FileFilter,
+};
+
+/// The name of a dedicated configuration file.
+pub const CONFIG_FILE_NAME: &str = "cpp-linter.toml";
+
+/// The name of a python project's configuration file.
+///
+/// Options are read from this file's `[tool.cpp-linter]` table.
+pub const PYPROJECT_FILE_NAME: &str = "pyproject.toml";
+
+/// A value that can be given as either a boolean or a string.
+///
+/// This is used for options like `lines-changed-only` that accept a boolean or a
+/// keyword (like `"diff"`).
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum BoolOrString {
+    Bool(bool),
+    String(String),
+}
+
+impl BoolOrString {
+    fn as_string(&self) -> String {
+        match self {
+            Self::Bool(val) => val.to_string(),
+            Self::String(val) => val.to_owned(),
+        }
+    }
+}
+
+/// A value that can be given as either a list of strings or a single delimited string.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum StringOrList {
+    List(Vec<String>),
+    String(String),
+}
+
+impl StringOrList {
+    /// Get the value as a list of strings.
+    ///
+    /// A single string is split at the given `delimiter`.
+    fn to_list(&self, delimiter: char) -> Vec<String> {
+        match self {
+            Self::List(val) => val.to_owned(),
+            Self::String(val) => val.split(delimiter).map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// A structure to hold the options parsed from a configuration file.
+///
+/// All fields are optional. See [`ConfigFile::apply()`] about how these values are
+/// merged with the CLI options.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ConfigFile {
+    pub verbosity: Option<String>,
+    pub version: Option<String>,
+    pub extensions: Option<StringOrList>,
+    pub analyzers: Option<StringOrList>,
+    pub jobs: Option<usize>,
+    pub analyzer_timeout: Option<u64>,
+    pub ignore: Option<StringOrList>,
+    pub lines_changed_only: Option<BoolOrString>,
+    pub files_changed_only: Option<bool>,
+    pub tracked_only: Option<bool>,
+    pub files_from_database: Option<bool>,
+    pub style: Option<String>,
+    pub ignore_format: Option<StringOrList>,
+    pub tidy_checks: Option<StringOrList>,
+    pub strict_checks: Option<bool>,
+    pub nolint_audit: Option<bool>,
+    pub database: Option<PathBuf>,
+    pub database_config: Option<String>,
+    pub extra_arg: Option<StringOrList>,
+    pub ignore_tidy: Option<StringOrList>,
+    pub tidy_load: Option<Vec<PathBuf>>,
+    pub tidy_doc_link: Option<StringOrList>,
+    pub thread_comments: Option<BoolOrString>,
+    pub no_lgtm: Option<bool>,
+    pub step_summary: Option<bool>,
+    pub file_annotations: Option<bool>,
+    pub tidy_review: Option<bool>,
+    pub format_review: Option<bool>,
+    pub passive_reviews: Option<bool>,
+    pub overrides: Option<Vec<OverrideConfig>>,
+    pub rules: Option<Vec<RuleConfig>>,
+}
+
+/// A structure to hold the options parsed from an `[[overrides]]` table of a
+/// configuration file.
+///
+/// See [`ClangOverride`] for the resolved form.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct OverrideConfig {
+    pub paths: StringOrList,
+    pub tidy_checks: Option<StringOrList>,
+    pub style: Option<String>,
+    pub extra_arg: Option<StringOrList>,
+    pub lines_changed_only: Option<BoolOrString>,
+}
+
+/// A structure to hold the options parsed from a `[[rules]]` table of a
+/// configuration file.
+///
+/// Exactly one of `pattern` (a regular expression) or `token` (a whole word) is
+/// required. With `must-match-within`, the rule is required to match within that
+/// many lines at the start of each file. See [`Rule`] for the resolved form.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RuleConfig {
+    pub id: String,
+    pub pattern: Option<String>,
+    pub token: Option<String>,
+    pub message: String,
+    pub severity: Option<String>,
+    pub must_match_within: Option<u32>,
+}
+
+/// A structure used to deserialize the relevant part of a `pyproject.toml` file.
+#[derive(Debug, Deserialize, Default)]
+struct PyProject {
+    #[serde(default)]
+    tool: PyProjectTool,
+}
+
+/// A structure used to deserialize the `[tool]` table of a `pyproject.toml` file.
+#[derive(Debug, Deserialize, Default)]
+struct PyProjectTool {
+    #[serde(rename = "cpp-linter")]
+    cpp_linter: Option<ConfigFile>,
+}
+
+/// The accepted values of the `lines-changed-only` option.
+const LINES_CHANGED_ONLY_VALUES: &[&str] = &["true", "on", "1", "false", "off", "0", "diff"];
+
+/// Ensure the `value` of option `name` is one of the `possible` values.
+fn validate_choice(name: &str, value: &str, possible: &[&str]) -> Result<()> {
+    if possible.contains(&value) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Invalid value '{value}' for '{name}' in configuration file. Possible values are: {}",
+            possible.join(", ")
+        ))
+    }
+}
+
+impl ConfigFile {
+    /// Parse the `contents` of a configuration file.
+    ///
+    /// If `is_pyproject` is true, then the options are read from the `[tool.cpp-linter]`
+    /// table. In this case, [`None`] is returned if the table does not exist.
+    pub fn parse(contents: &str, is_pyproject: bool) -> Result<Option<Self>> {
+        if is_pyproject {
+            let pyproject: PyProject = toml::from_str(contents)?;
+            Ok(pyproject.tool.cpp_linter)
+        } else {
+            Ok(Some(toml::from_str(contents)?))
+        }
+    }
+
+    /// Read and parse the configuration file at the given `path`.
+    ///
+    /// If the file is named `pyproject.toml`, then the options are read from the
+    /// `[tool.cpp-linter]` table.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read configuration file: {}", path.display()))?;
+        let is_pyproject = path
+            .file_name()
+            .is_some_and(|name| name == PYPROJECT_FILE_NAME);
+        Self::parse(&contents, is_pyproject)
+            .with_context(|| format!("Failed to parse configuration file: {}", path.display()))
+    }
+
+    /// Search the given `root` directory for a configuration file.
+    ///
+    /// A `cpp-linter.toml` file takes precedence over a `pyproject.toml` file.
+    /// Returns the path to the discovered file along with its parsed options.
+    pub fn discover(root: &Path) -> Result<Option<(PathBuf, Self)>> {
+        for name in [CONFIG_FILE_NAME, PYPROJECT_FILE_NAME] {
+            let path = root.join(name);
+            if path.is_file() {
+                if let Some(config) = Self::load(&path)? {
+                    return Ok(Some((path, config)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Merge the options from this configuration into the given `cli` options.
+    ///
+    /// A value from the configuration is only used if the corresponding option was not
+    /// explicitly passed on the command line (as described by the given `args`).
+    pub fn apply(&self, cli: &mut Cli, args: &ArgMatches) -> Result<()> {
+        let is_unset = |id: &str| args.value_source(id) != Some(ValueSource::CommandLine);
+
+        if let (Some(verbosity), true) = (&self.verbosity, is_unset("verbosity")) {
+            validate_choice("verbosity", verbosity, &["debug", "info"])?;
+            cli.verbosity = verbosity == "debug";
+        }
+        if let (Some(version), true) = (&self.version, is_unset("version")) {
+            cli.version = version.to_owned();
+        }
+        if let (Some(extensions), true) = (&self.extensions, is_unset("extensions")) {
+            cli.extensions = extensions
+                .to_list(',')
+                .into_iter()
+                .filter(|ext| !ext.is_empty())
+                .collect();
+        }
+        if let (Some(analyzers), true) = (&self.analyzers, is_unset("analyzers")) {
+            cli.analyzers = analyzers
+                .to_list(',')
+                .into_iter()
+                .filter(|name| !name.is_empty())
+                .collect();
+        }
+        if let (Some(tidy_load), true) = (&self.tidy_load, is_unset("tidy-load")) {
+            cli.tidy_load = tidy_load.to_owned();
+        }
+        if let (Some(tidy_doc_link), true) = (&self.tidy_doc_link, is_unset("tidy-doc-link")) {
+            cli.tidy_doc_links = tidy_doc_link
+                .to_list('|')
+                .iter()
+                .map(|link| {
+                    TidyDocLink::from_str(link)
+                        .with_context(|| "Invalid value for 'tidy-doc-link' in configuration file")
+                })
+                .collect::<Result<Vec<_>>>()?;
+        }
+        if let (Some(jobs), true) = (self.jobs, is_unset("jobs")) {
+            cli.jobs = jobs;
+        }
+        if let (Some(analyzer_timeout), true) =
+            (self.analyzer_timeout, is_unset("analyzer-timeout"))
+        {
+            cli.analyzer_timeout = analyzer_timeout;
+        }
+        if let (Some(ignore), true) = (&self.ignore, is_unset("ignore")) {
+            cli.ignore = ignore.to_list('|');
+        }
+        if let (Some(lines_changed_only), true) =
+            (&self.lines_changed_only, is_unset("lines-changed-only"))
+        {
+            let value = lines_changed_only.as_string();
+            validate_choice("lines-changed-only", &value, LINES_CHANGED_ONLY_VALUES)?;
+            cli.lines_changed_only = LinesChangedOnly::from_string(&value);
+            if self.files_changed_only.is_none() && is_unset("files-changed-only") {
+                // mirror the CLI's default behavior for `--files-changed-only`
+                cli.files_changed_only = cli.lines_changed_only == LinesChangedOnly::On;
+            }
+        }
+        if let (Some(files_changed_only), true) =
+            (self.files_changed_only, is_unset("files-changed-only"))
+        {
+            cli.files_changed_only = files_changed_only;
+        }
+        if let (Some(tracked_only), true) = (self.tracked_only, is_unset("tracked-only")) {
+            cli.tracked_only = tracked_only;
+        }
+        if let (Some(files_from_database), true) =
+            (self.files_from_database, is_unset("files-from-database"))
+        {
+            cli.files_from_database = files_from_database;
+        }
+        if let (Some(style), true) = (&self.style, is_unset("style")) {
+            cli.style = style.to_owned();
+        }
+        if let (Some(ignore_format), true) = (&self.ignore_format, is_unset("ignore-format")) {
+            cli.ignore_format = Some(ignore_format.to_list('|'));
+        }
+        if let (Some(tidy_checks), true) = (&self.tidy_checks, is_unset("tidy-checks")) {
+            cli.tidy_checks = tidy_checks.to_list(',').join(",");
+        }
+        if let (Some(database), true) = (&self.database, is_unset("database")) {
+            cli.database = Some(database.to_owned());
+        }
+        if let (Some(database_config), true) = (&self.database_config, is_unset("database-config"))
+        {
+            cli.database_config = Some(database_config.to_owned());
+        }
+        if let (Some(extra_arg), true) = (&self.extra_arg, is_unset("extra-arg")) {
+            cli.extra_arg = extra_arg.to_list(' ');
+        }
+        if let (Some(ignore_tidy), true) = (&self.ignore_tidy, is_unset("ignore-tidy")) {
+            cli.ignore_tidy = Some(ignore_tidy.to_list('|'));
+        }
+        if let (Some(thread_comments), true) = (&self.thread_comments, is_unset("thread-comments"))
+        {
+            let value = thread_comments.as_string();
+            validate_choice(
+                "thread-comments",
+                &value,
+                &["true", "on", "1", "false", "off", "0", "update"],
+            )?;
+            cli.thread_comments = ThreadComments::from_string(&value);
+        }
+        let flags = [
+            (self.strict_checks, "strict-checks", &mut cli.strict_checks),
+            (self.nolint_audit, "nolint-audit", &mut cli.nolint_audit),
+            (self.no_lgtm, "no-lgtm", &mut cli.no_lgtm),
+            (self.step_summary, "step-summary", &mut cli.step_summary),
+            (
+                self.file_annotations,
+                "file-annotations",
+                &mut cli.file_annotations,
+            ),
+            (self.tidy_review, "tidy-review", &mut cli.tidy_review),
+            (self.format_review, "format-review", &mut cli.format_review),
+            (
+                self.passive_reviews,
+                "passive-reviews",
+                &mut cli.passive_reviews,
+            ),
+        ];
+        for (value, id, field) in flags {
+            if let (Some(value), true) = (value, is_unset(id)) {
+                *field = value;
+            }
+        }
+        if let Some(overrides) = &self.overrides {
+            cli.overrides = overrides
+                .iter()
+                .map(|over| over.to_clang_override(&cli.extensions))
+                .collect::<Result<Vec<_>>>()?;
+        }
+        if let Some(rules) = &self.rules {
+            cli.rules = rules
+                .iter()
+                .map(RuleConfig::to_rule)
+                .collect::<Result<Vec<_>>>()?;
+        }
+        Ok(())
+    }
+}
+
+impl RuleConfig {
+    /// Convert this table into a [`Rule`].
+    fn to_rule(&self) -> Result<Rule> {
+        let severity = self.severity.as_deref().unwrap_or("warning");
+        let rule = match (&self.pattern, &self.token) {
+            (Some(pattern), None) => Rule::new(&self.id, pattern, &self.message, severity),
+            (None, Some(token)) => Rule::from_token(&self.id, token, &self.message, severity),
+            _ => Err(anyhow!(
+                "Rule '{}' in configuration file needs either a 'pattern' or a 'token'",
+                self.id
+            )),
+        }?;
+        Ok(match self.must_match_within {
+            Some(lines) => rule.required_within(lines),
+            None => rule,
+        })
+    }
+}
+
+impl OverrideConfig {
+    /// Convert this table into a [`ClangOverride`].
+    fn to_clang_override(&self, extensions: &[String]) -> Result<ClangOverride> {
+        let lines_changed_only = match &self.lines_changed_only {
+            Some(value) => {
+                let value = value.as_string();
+                validate_choice(
+                    "overrides.lines-changed-only",
+                    &value,
+                    LINES_CHANGED_ONLY_VALUES,
+                )?;
+                Some(LinesChangedOnly::from_string(&value))
+            }
+            None => None,
+        };
+        Ok(ClangOverride {
+            filter: FileFilter::new(&self.paths.to_list('|'), extensions.to_vec()),
+            tidy_checks: self
+                .tidy_checks
+                .as_ref()
+                .map(|checks| checks.to_list(',').join(",")),
+            style: self.style.clone(),
+            extra_args: self.extra_arg.as_ref().map(|args| args.to_list(' ')),
+            lines_changed_only,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use tempfile::tempdir;
+
+    use super::{ConfigFile, CONFIG_FILE_NAME, PYPROJECT_FILE_NAME};
+    use crate::cli::{get_arg_parser, ClangParams, Cli, LinesChangedOnly, ThreadComments};
+
+    const CONFIG: &str = r#"
+tidy-checks = ["-*", "bugprone-*"]
+extensions = "cpp,hpp"
+ignore = ["build", "third_party"]
+lines-changed-only = "diff"
+thread-comments = true
+database = "build"
+tidy-review = true
+jobs = 2
+analyzer-timeout = 300
+"#;
+
+    fn apply_config(config: &str, cli_args: Vec<&str>) -> Cli {
+        let config = ConfigFile::parse(config, false).unwrap().unwrap();
+        let args = get_arg_parser().get_matches_from(cli_args);
+        let mut cli = Cli::from(&args);
+        config.apply(&mut cli, &args).unwrap();
+        cli
+    }
+
+    #[test]
+    fn config_values() {
+        let cli = apply_config(CONFIG, vec!["cpp-linter"]);
+        assert_eq!(cli.tidy_checks, "-*,bugprone-*");
+        assert_eq!(cli.extensions, vec!["cpp", "hpp"]);
+        assert_eq!(cli.ignore, vec!["build", "third_party"]);
+        assert_eq!(cli.lines_changed_only, LinesChangedOnly::Diff);
+        assert!(!cli.files_changed_only);
+        assert_eq!(cli.thread_comments, ThreadComments::On);
+        assert_eq!(cli.database, Some(PathBuf::from("build")));
+        assert!(cli.tidy_review);
+        assert_eq!(cli.jobs, 2);
+        assert_eq!(cli.analyzer_timeout, 300);
+        // untouched values keep the CLI defaults
+        assert_eq!(cli.style, "llvm");
+        assert!(cli.file_annotations);
+    }
+
+    #[test]
+    fn cli_overrides_config() {
+        let cli = apply_config(
+            CONFIG,
+            vec![
+                "cpp-linter",
+                "--tidy-checks=-*",
+                "--lines-changed-only=false",
+                "--tidy-review=false",
+            ],
+        );
+        assert_eq!(cli.tidy_checks, "-*");
+        assert_eq!(cli.lines_changed_only, LinesChangedOnly::Off);
+        assert!(!cli.tidy_review);
+        assert_eq!(cli.extensions, vec!["cpp", "hpp"]);
+    }
+
+    #[test]
+    fn invalid_config() {
+        assert!(ConfigFile::parse("unknown-option = true", false).is_err());
+        let config = ConfigFile::parse("lines-changed-only = \"maybe\"", false)
+            .unwrap()
+            .unwrap();
+        let args = get_arg_parser().get_matches_from(vec!["cpp-linter"]);
+        let mut cli = Cli::from(&args);
+        assert!(config.apply(&mut cli, &args).is_err());
+    }
+
+    #[test]
+    fn discover_config() {
+        let tmp = tempdir().unwrap();
+        assert!(ConfigFile::discover(tmp.path()).unwrap().is_none());
+
+        // a pyproject.toml without a `[tool.cpp-linter]` table is not used
+        fs::write(
+            tmp.path().join(PYPROJECT_FILE_NAME),
+            "[project]\nname = \"example\"\n",
+        )
+        .unwrap();
+        assert!(ConfigFile::discover(tmp.path()).unwrap().is_none());
+
+        fs::write(
+            tmp.path().join(PYPROJECT_FILE_NAME),
+            "[project]\nname = \"example\"\n\n[tool.cpp-linter]\nstyle = \"file\"\n",
+        )
+        .unwrap();
+        let (path, config) = ConfigFile::discover(tmp.path()).unwrap().unwrap();
+        assert_eq!(path, tmp.path().join(PYPROJECT_FILE_NAME));
+        assert_eq!(config.style.as_deref(), Some("file"));
+
+        // cpp-linter.toml takes precedence
+        fs::write(tmp.path().join(CONFIG_FILE_NAME), "style = \"google\"\n").unwrap();
+        let (path, config) = ConfigFile::discover(tmp.path()).unwrap().unwrap();
+        assert_eq!(path, tmp.path().join(CONFIG_FILE_NAME));
+        assert_eq!(config.style.as_deref(), Some("google"));
+    }
+
+    #[test]
+    fn path_overrides() {
+        let cli = apply_config(
+            r#"
+tidy-checks = "-*"
+style = "file"
+
+[[overrides]]
+paths = ["third_party/**"]
+style = ""
+lines-changed-only = false
+
+[[overrides]]
+paths = "src/**|!src/generated/**"
+tidy-checks = ["-*", "bugprone-*"]
+extra-arg = ["-std=c++17"]
+"#,
+            vec!["cpp-linter"],
+        );
+        let clang_params = ClangParams::from(&cli);
+        assert!(clang_params.is_tidy_enabled());
+        assert!(clang_params.is_format_enabled());
+
+        let third_party = clang_params.resolve_for(&PathBuf::from("third_party/lib/dep.cpp"));
+        assert!(third_party.style.is_empty());
+        assert_eq!(third_party.tidy_checks, "-*");
+        assert_eq!(third_party.lines_changed_only, LinesChangedOnly::Off);
+
+        let src = clang_params.resolve_for(&PathBuf::from("src/main.cpp"));
+        assert_eq!(src.style, "file");
+        assert_eq!(src.tidy_checks, "-*,bugprone-*");
+        assert_eq!(src.extra_args, vec!["-std=c++17"]);
+        assert_eq!(src.lines_changed_only, LinesChangedOnly::On);
+
+        let generated = clang_params.resolve_for(&PathBuf::from("src/generated/code.cpp"));
+        assert_eq!(generated.tidy_checks, "-*");
+        assert!(generated.extra_args.is_empty());
+    }
+
+    #[test]
+    fn house_rules() {
+        let cli = apply_config(
+            r#"
+[[rules]]
+id = "banned-strcpy"
+token = "strcpy"
+message = "Use strncpy() instead"
+severity = "error"
+
+[[rules]]
+id = "todo-format"
+pattern = 'TODO([^(]|$)'
+message = "Use the TODO(name) format"
+
+[[rules]]
+id = "copyright-header"
+pattern = 'Copyright'
+must-match-within = 5
+message = "Add a copyright header"
+"#,
+            vec!["cpp-linter"],
+        );
+        assert_eq!(cli.rules.len(), 3);
+        assert_eq!(cli.rules[0].severity, "error");
+        assert_eq!(cli.rules[1].severity, "warning");
+        assert_eq!(cli.rules[1].required_within, None);
+        assert_eq!(cli.rules[2].required_within, Some(5));
+        let clang_params = ClangParams::from(&cli);
+        assert_eq!(
+            clang_params.analyzer_names,
+            vec!["clang-format", "clang-tidy", "rules"]
+        );
+
+        let config = ConfigFile::parse(
+            "[[rules]]\nid = \"no-pattern\"\nmessage = \"oops\"\n",
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        let args = get_arg_parser().get_matches_from(vec!["cpp-linter"]);
+        let mut cli = Cli::from(&args);
+        assert!(config.apply(&mut cli, &args).is_err());
+    }
+}
@@ -1,12 +1,18 @@
 //! This module holds the Command Line Interface design.
-use std::path::PathBuf;
+use std::{path::PathBuf, str::FromStr};
 
 // non-std crates
 use clap::builder::{ArgPredicate, BoolishValueParser, FalseyValueParser};
 use clap::{value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command};
 
+use crate::clang_tools::clang_tidy::TidyDocLink;
+
+mod config;
+pub use config::{ConfigFile, CONFIG_FILE_NAME, PYPROJECT_FILE_NAME};
 mod structs;
-pub use structs::{ClangParams, Cli, FeedbackInput, LinesChangedOnly, ThreadComments};
+pub use structs::{
+    ClangOverride, ClangParams, Cli, FeedbackInput, LinesChangedOnly, ThreadComments,
+};
 
 /// Builds and returns the Command Line Interface's argument parsing object.
 pub fn get_arg_parser() -> Command {
@@ -15,6 +21,21 @@ pub fn get_arg_parser() -> Command {
             Command::new("version")
                 .about("Display the cpp-linter version and exit.")
         )
+        .subcommand(
+            Command::new("config")
+                .about(
+                    "Display the effective clang-tidy and clang-format configuration
+for a given file, then exit.
+
+This shows which `.clang-tidy` and `.clang-format` files apply
+to the file (after merging in `--tidy-checks` and `--style`).",
+                )
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .help("The path to the file to explain the configuration for."),
+                ),
+        )
         .arg(
             Arg::new("verbosity")
                 .long("verbosity")
@@ -37,12 +58,40 @@ thread comments or file annotations.\n\n",
                     "The path that is used to read a compile command database.
 For example, it can be a CMake build directory in which a file named
 compile_commands.json exists (set `CMAKE_EXPORT_COMPILE_COMMANDS` to `ON`).
-When no build path is specified, a search for compile_commands.json will be
-attempted through all parent paths of the first input file. See [LLVM docs about
+When no build path is specified, a compile_commands.json file is searched
+for in the `binaryDir` of CMake configure presets, `build/`, `out/*/`,
+`cmake-build-*/`, and the repo-root (the most recently modified database
+is used). An invalid database or one that references non-existent files
+is reported as an error. See [LLVM docs about
 setup tooling](https://clang.llvm.org/docs/HowToSetupToolingForLLVM.html)
-for an example of setting up Clang Tooling on a source tree.",
+for an example of setting up Clang Tooling on a source tree.
+
+Header files are not listed in a compilation database. When a database is
+used, clang-tidy analyzes a header file through a translation unit (from the
+database) that includes it. Only the diagnostics about the header are reported.
+
+For other source files that are missing from the database, the compiler flags
+are inferred from an entry of a file in the same directory. Otherwise, the flags
+are read from the nearest `compile_flags.txt` file (if any).",
             )
         )
+        .arg(
+            Arg::new("database-config")
+                .long("database-config")
+                .short('P')
+                .help_heading("clang-tidy options")
+                .help(
+                    "Select a build configuration from the compilation database.
+
+If the database (see [`--database`](#-p-database)) has multiple entries
+for the same file (ie for different build configurations), then the
+first entry whose `output` or `directory` has a path component equal
+to this value is used. If no entry matches, then the first entry with
+a compile argument equal to (or assigning) this value is used, ie
+`-DCMAKE_BUILD_TYPE=Release`. By default, the first entry for each
+file is used.\n\n",
+                ),
+        )
         .arg(
             Arg::new("style")
                 .short('s')
@@ -88,6 +137,42 @@ option in a .clang-tidy file (if any).
 See also clang-tidy docs for more info.\n\n",
             ),
         )
+        .arg(
+            Arg::new("strict-checks")
+                .short('k')
+                .long("strict-checks")
+                .value_parser(BoolishValueParser::new())
+                .default_value("false")
+                .help_heading("clang-tidy options")
+                .help(
+                    "Set this option to `true` to abort when a glob in
+[`--tidy-checks`](#-c-tidy-checks) (or in the `Checks` of a
+`.clang-tidy` config file) does not match any of the checks that
+clang-tidy supports (ie because of a typo). Globs for the
+`clang-diagnostic-*` compiler warnings are not validated.
+
+By default, such globs are only reported as warnings.\n\n",
+                ),
+        )
+        .arg(
+            Arg::new("nolint-audit")
+                .short('n')
+                .long("nolint-audit")
+                .value_parser(BoolishValueParser::new())
+                .default_value("false")
+                .help_heading("clang-tidy options")
+                .help(
+                    "Set this option to `true` to audit the `NOLINT` comments
+(including `NOLINTNEXTLINE` and `NOLINTBEGIN`/`NOLINTEND`)
+found on the lines of interest (see
+[`--lines-changed-only`](#-l-lines-changed-only)).
+
+A suppression is reported if it does not name the suppressed
+check(s), does not give a justification, or does not suppress
+any diagnostic. The findings are shown in a separate section of
+the feedback.\n\n",
+                ),
+        )
         .arg(
             Arg::new("version")
                 .short('V')
@@ -107,6 +192,95 @@ strings which can be 8, 9, 10, 11, 12, 13, 14, 15, 16, 17.
   here are converted to absolute.\n\n",
             ),
         )
+        .arg(
+            Arg::new("config")
+                .short('C')
+                .long("config")
+                .value_parser(value_parser!(PathBuf))
+                .help(
+                    "The path to a configuration file. If not specified, then a
+`cpp-linter.toml` file or a `pyproject.toml` file (with a
+`[tool.cpp-linter]` table) is searched for in the
+[`--repo-root`](#-r-repo-root).
+
+- Each key in the configuration file is named after the long form of
+  an option (without the leading `--`), for example `tidy-checks`.
+- Options specified on the command line take precedence over values
+  in the configuration file.
+- The [`--repo-root`](#-r-repo-root) cannot be set from a
+  configuration file.
+- An `[[overrides]]` table can set `tidy-checks`, `style`, `extra-arg`,
+  and `lines-changed-only` for only the files matched by its `paths`
+  (interpreted like [`--ignore`](#-i-ignore) values). Later tables
+  take precedence over earlier ones. An overridden `lines-changed-only`
+  only changes which lines of the matched files are analyzed; which
+  files are analyzed is still decided by the global value.
+- A `[[rules]]` table defines a house rule with an `id`, a `message`,
+  an optional `severity` (`error`, `warning` (default), or `note`),
+  and either a regular expression `pattern` or a whole-word `token`.
+  Rules are checked on the added lines (or the lines in the diff
+  with `--lines-changed-only=diff`) of each file. So, files without
+  a diff (see `--lines-changed-only`) are not checked for violations.
+  A rule with `must-match-within = N` instead requires its pattern to
+  match within the first N lines of each file (ie a copyright
+  header), regardless of which lines changed.\n\n",
+                ),
+        )
+        .arg(
+            Arg::new("analyzers")
+                .short('A')
+                .long("analyzers")
+                .value_delimiter(',')
+                .default_value("")
+                .help(
+                    "A comma-separated list of additional analyzers to run on each
+file (after clang-format and clang-tidy).
+
+Their findings are reported alongside clang-tidy's diagnostics.
+By default, no additional analyzers are used.
+The supported analyzers are:
+
+- `cppcheck`: Uses the compilation database (if any) via
+  `--project`. Findings are filtered with `--lines-changed-only`.
+- `include-what-you-use`: Suggests `#include` lines to add or
+  remove. The suggestions are also posted in PR reviews
+  (see `--tidy-review`).
+- `clang-include-cleaner`: An alternative to `include-what-you-use`
+  that is found like the clang tools (see `--version`). Only changed
+  files are analyzed.
+- `rules`: The house rules defined in a configuration file. This is
+  used automatically when any rules are configured.
+- `clang-static-analyzer`: Runs `clang --analyze` on each file (or on
+  a translation unit that includes a changed header). The bug paths
+  are reported as numbered steps. This analysis can take long, so
+  consider using `--jobs` and `--analyzer-timeout`.\n\n",
+                ),
+        )
+        .arg(
+            Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .value_parser(value_parser!(usize))
+                .default_value("0")
+                .help(
+                    "The maximum number of files that are analyzed concurrently.
+By default (`0`), there is no limit. This is useful to limit the
+resources used by long-running analyzers (see `--analyzers`).\n\n",
+                ),
+        )
+        .arg(
+            Arg::new("analyzer-timeout")
+                .short('O')
+                .long("analyzer-timeout")
+                .value_parser(value_parser!(u64))
+                .default_value("0")
+                .help(
+                    "The number of seconds that an additional analyzer (see `--analyzers`)
+may take to analyze a single file. An analyzer that takes longer is
+stopped, and its findings about the file are not reported.
+By default (`0`), there is no time limit.\n\n",
+                ),
+        )
         .arg(
             Arg::new("extensions")
                 .short('e')
@@ -144,11 +318,19 @@ the current working directory if not using a CI runner).\n\n",
 - This can also have files, but the file's path (relative to
   the [`--repo-root`](#-r-repo-root)) has to be specified with the filename.
 - Submodules are automatically ignored. Hidden directories (beginning
-  with a `.`) are also ignored automatically.
+  with a `.`) are also ignored automatically. Paths excluded by
+  `.gitignore` files are not analyzed either.
 - Prefix a path with `!` to explicitly not ignore it. This can be
   applied to a submodule's path (if desired) but not hidden directories.
 - Glob patterns are supported here. Path separators in glob patterns should
-  use `/` because `\\` represents an escaped literal.\n\n",
+  use `/` because `\\` represents an escaped literal.
+- Additional patterns (in gitignore syntax) can be listed in a
+  `.cpp-linter-ignore` file located in the repo-root. Patterns listed
+  before any section header apply to all files. Patterns listed under
+  a `[tidy]` or `[format]` section header apply exclusively to clang-tidy
+  or clang-format respectively. Patterns listed under an `[analyzers]`
+  section header apply exclusively to the additional
+  [`--analyzers`](#-a-analyzers).\n\n",
             ),
         )
         .arg(
@@ -159,7 +341,13 @@ the current working directory if not using a CI runner).\n\n",
                 .help_heading("clang-tidy options")
                 .help(
                     "Similar to [`--ignore`](#-i-ignore) but applied
-exclusively to files analyzed by clang-tidy.\n\n",
+exclusively to files analyzed by clang-tidy.
+
+These patterns also apply to the additional
+[`--analyzers`](#-a-analyzers).
+
+Patterns listed under the `[tidy]` section of a
+`.cpp-linter-ignore` file are also applied.\n\n",
             ),
         )
         .arg(
@@ -170,7 +358,11 @@ exclusively to files analyzed by clang-tidy.\n\n",
                 .help_heading("clang-format options")
                 .help(
                     "Similar to [`--ignore`](#-i-ignore) but applied
-exclusively to files analyzed by clang-format.\n\n",
+exclusively to files analyzed by clang-format.
+
+Patterns listed under the `[format]` section of a
+`.cpp-linter-ignore` file are also applied. Files matched by
+clang-format's own `.clang-format-ignore` files are skipped as well.\n\n",
             ),
         )
         .arg(
@@ -212,6 +404,65 @@ This is automatically enabled if
 > https://docs.github.com/en/actions/reference/authentication-in-a-workflow).\n\n",
             ),
         )
+        .arg(
+            Arg::new("tracked-only")
+                .short('T')
+                .long("tracked-only")
+                .value_parser(BoolishValueParser::new())
+                .default_value("false")
+                .help_heading("Source options")
+                .help(
+                    "Set this option to `true` to only analyze files that are tracked
+in the git repository's index. Untracked files are not analyzed.
+
+This only applies when analyzing all source files in the repo
+(see [`--files-changed-only`](#-f-files-changed-only)).
+Regardless of this option, files excluded by `.gitignore` files,
+`.git/info/exclude`, or git's global excludes file are not analyzed.\n\n",
+            ),
+        )
+        .arg(
+            Arg::new("files-from-database")
+                .short('B')
+                .long("files-from-database")
+                .value_parser(BoolishValueParser::new())
+                .default_value("false")
+                .help_heading("Source options")
+                .help(
+                    "Set this option to `true` to only analyze the translation units
+listed in the compilation database (see [`--database`](#-p-database)).
+
+Instead of searching the repository for files with the specified
+[`--extensions`](#-e-extensions), the source files are taken from
+the database (still subject to [`--ignore`](#-i-ignore)). When
+analyzing only changed files, any changed source file that has no
+entry in the database is skipped with a warning.\n\n",
+            ),
+        )
+        .arg(
+            Arg::new("diff-file")
+                .short('F')
+                .long("diff-file")
+                .value_parser(value_parser!(PathBuf))
+                .help_heading("Source options")
+                .help(
+                    "A path to a file that contains a diff (as output by `git diff`).
+Set this to `-` to read the diff from stdin.
+
+When specified, the list of changed files (and changed lines) is
+parsed from the given diff instead of using a git server's REST API
+or the local repository's history. This is useful for CI systems
+other than GitHub:
+
+```shell
+git diff origin/main | cpp-linter --diff-file=-
+```
+
+> [!NOTE]
+> A relative path is resolved from the current working directory,
+> not the [`--repo-root`](#-r-repo-root).\n\n",
+            ),
+        )
         .arg(
             Arg::new("extra-arg")
                 .long("extra-arg")
@@ -229,6 +480,42 @@ cpp-linter --extra-arg="-std=c++17" --extra-arg="-Wall"
 ```"#,
             ),
         )
+        .arg(
+            Arg::new("tidy-load")
+                .long("tidy-load")
+                .short('L')
+                .action(ArgAction::Append)
+                .value_parser(value_parser!(PathBuf))
+                .help_heading("clang-tidy options")
+                .help(
+                    "The path to a clang-tidy plugin (a shared library with custom checks)
+that is passed to every clang-tidy invocation via `-load`.
+This can be specified more than once for each plugin.\n\n",
+                ),
+        )
+        .arg(
+            Arg::new("tidy-doc-link")
+                .long("tidy-doc-link")
+                .short('K')
+                .action(ArgAction::Append)
+                .value_parser(TidyDocLink::from_str)
+                .help_heading("clang-tidy options")
+                .help(
+                    r#"A URL template for the documentation of the clang-tidy checks
+with a certain prefix, in the form `prefix=template`. In the template,
+`{check}` is replaced with the check's name and `{name}` is replaced
+with the check's name (without the prefix). This can be specified
+more than once for each prefix. If multiple prefixes match a check,
+then the longest prefix is used.
+
+```shell
+cpp-linter --tidy-doc-link="acme-=https://docs.acme.dev/checks/{name}.html"
+```
+
+By default, checks link to the documentation of the clang-tidy version
+used, and `clang-diagnostic-*` checks link to the clang warning reference."#,
+                ),
+        )
         .arg(
             Arg::new("thread-comments")
                 .long("thread-comments")
@@ -336,7 +623,17 @@ Further filtering can still be applied (see [Source options](#source-options))."
         )
         .groups([
             ArgGroup::new("Clang-tidy options")
-                .args(["tidy-checks", "database", "extra-arg", "ignore-tidy"])
+                .args([
+                    "tidy-checks",
+                    "strict-checks",
+                    "nolint-audit",
+                    "database",
+                    "database-config",
+                    "extra-arg",
+                    "ignore-tidy",
+                    "tidy-load",
+                    "tidy-doc-link",
+                ])
                 .multiple(true)
                 .required(false),
             ArgGroup::new("Clang-format options")
@@ -344,11 +641,27 @@ Further filtering can still be applied (see [Source options](#source-options))."
                 .multiple(true)
                 .required(false),
             ArgGroup::new("General options")
-                .args(["verbosity", "version"])
+                .args([
+                    "verbosity",
+                    "version",
+                    "config",
+                    "analyzers",
+                    "jobs",
+                    "analyzer-timeout",
+                ])
                 .multiple(true)
                 .required(false),
             ArgGroup::new("Source options")
-                .args(["extensions", "repo-root", "ignore", "lines-changed-only", "files-changed-only"])
+                .args([
+                    "extensions",
+                    "repo-root",
+                    "ignore",
+                    "lines-changed-only",
+                    "files-changed-only",
+                    "tracked-only",
+                    "files-from-database",
+                    "diff-file",
+                ])
                 .multiple(true)
                 .required(false),
             ArgGroup::new("Feedback options")
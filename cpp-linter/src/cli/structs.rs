@@ -1,9 +1,24 @@
-use std::{fmt::Display, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use clap::ArgMatches;
 
 use super::convert_extra_arg_val;
-use crate::{clang_tools::clang_tidy::CompilationUnit, common_fs::FileFilter};
+use crate::{
+    clang_tools::{
+        analyzer::ResolvedAnalyzer,
+        clang_format::CLANG_FORMAT_NAME,
+        clang_tidy::{TidyDocLink, CLANG_TIDY_NAME},
+        compilation_db::CompilationUnit,
+        rules::{Rule, RULES_NAME},
+    },
+    common_fs::FileFilter,
+};
 
 /// An enum to describe `--lines-changed-only` CLI option's behavior.
 #[derive(PartialEq, Clone, Debug, Default)]
@@ -18,7 +33,7 @@ pub enum LinesChangedOnly {
 }
 
 impl LinesChangedOnly {
-    fn from_string(val: &str) -> LinesChangedOnly {
+    pub(super) fn from_string(val: &str) -> LinesChangedOnly {
         match val {
             "true" | "on" | "1" => LinesChangedOnly::On,
             "diff" => LinesChangedOnly::Diff,
@@ -50,16 +65,28 @@ pub struct Cli {
     pub version: String,
     pub verbosity: bool,
     pub extensions: Vec<String>,
+    pub analyzers: Vec<String>,
+    pub jobs: usize,
+    pub analyzer_timeout: u64,
     pub repo_root: String,
+    pub config: Option<PathBuf>,
     pub lines_changed_only: LinesChangedOnly,
     pub files_changed_only: bool,
+    pub tracked_only: bool,
+    pub files_from_database: bool,
+    pub diff_file: Option<PathBuf>,
     pub ignore: Vec<String>,
     pub style: String,
     pub ignore_format: Option<Vec<String>>,
     pub ignore_tidy: Option<Vec<String>>,
     pub tidy_checks: String,
+    pub strict_checks: bool,
+    pub nolint_audit: bool,
     pub database: Option<PathBuf>,
+    pub database_config: Option<String>,
     pub extra_arg: Vec<String>,
+    pub tidy_load: Vec<PathBuf>,
+    pub tidy_doc_links: Vec<TidyDocLink>,
     pub thread_comments: ThreadComments,
     pub no_lgtm: bool,
     pub step_summary: bool,
@@ -68,6 +95,8 @@ pub struct Cli {
     pub tidy_review: bool,
     pub format_review: bool,
     pub passive_reviews: bool,
+    pub overrides: Vec<ClangOverride>,
+    pub rules: Vec<Rule>,
 }
 
 impl From<&ArgMatches> for Cli {
@@ -113,16 +142,41 @@ impl From<&ArgMatches> for Cli {
             version: args.get_one::<String>("version").unwrap().to_owned(),
             verbosity: args.get_one::<String>("verbosity").unwrap().as_str() == "debug",
             extensions,
+            analyzers: args
+                .get_many::<String>("analyzers")
+                .unwrap()
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string())
+                .collect(),
+            jobs: *args.get_one::<usize>("jobs").unwrap(),
+            analyzer_timeout: *args.get_one::<u64>("analyzer-timeout").unwrap(),
             repo_root: args.get_one::<String>("repo-root").unwrap().to_owned(),
+            config: args.get_one::<PathBuf>("config").map(|v| v.to_owned()),
             lines_changed_only,
             files_changed_only: args.get_flag("files-changed-only"),
+            tracked_only: args.get_flag("tracked-only"),
+            files_from_database: args.get_flag("files-from-database"),
+            diff_file: args.get_one::<PathBuf>("diff-file").map(|v| v.to_owned()),
             ignore,
             style: args.get_one::<String>("style").unwrap().to_owned(),
             ignore_format,
             ignore_tidy,
             tidy_checks: args.get_one::<String>("tidy-checks").unwrap().to_owned(),
+            strict_checks: args.get_flag("strict-checks"),
+            nolint_audit: args.get_flag("nolint-audit"),
             database: args.get_one::<PathBuf>("database").map(|v| v.to_owned()),
+            database_config: args
+                .get_one::<String>("database-config")
+                .map(|v| v.to_owned()),
             extra_arg,
+            tidy_load: args
+                .get_many::<PathBuf>("tidy-load")
+                .map(|plugins| plugins.cloned().collect())
+                .unwrap_or_default(),
+            tidy_doc_links: args
+                .get_many::<TidyDocLink>("tidy-doc-link")
+                .map(|links| links.cloned().collect())
+                .unwrap_or_default(),
             no_lgtm: args.get_flag("no-lgtm"),
             step_summary: args.get_flag("step-summary"),
             thread_comments,
@@ -133,6 +187,8 @@ impl From<&ArgMatches> for Cli {
             tidy_review: args.get_flag("tidy-review"),
             format_review: args.get_flag("format-review"),
             passive_reviews: args.get_flag("passive-reviews"),
+            overrides: vec![],
+            rules: vec![],
         }
     }
 }
@@ -150,7 +206,7 @@ pub enum ThreadComments {
 }
 
 impl ThreadComments {
-    fn from_string(val: &str) -> ThreadComments {
+    pub(super) fn from_string(val: &str) -> ThreadComments {
         match val {
             "true" | "on" | "1" => ThreadComments::On,
             "update" => ThreadComments::Update,
@@ -169,11 +225,37 @@ impl Display for ThreadComments {
     }
 }
 
+/// A set of clang-tidy and clang-format options that only apply to certain paths.
+///
+/// These are parsed from the `[[overrides]]` tables of a configuration file.
+/// Any option that is [`None`] does not override the corresponding value in
+/// [`ClangParams`].
+#[derive(Debug, Clone)]
+pub struct ClangOverride {
+    /// The paths (or glob patterns) that this override applies to.
+    ///
+    /// These are interpreted like the values of the `--ignore` option.
+    pub filter: FileFilter,
+    pub tidy_checks: Option<String>,
+    pub style: Option<String>,
+    pub extra_args: Option<Vec<String>>,
+    pub lines_changed_only: Option<LinesChangedOnly>,
+}
+
+impl ClangOverride {
+    /// Does this override apply to the given `file`?
+    pub fn is_match(&self, file: &Path) -> bool {
+        self.filter.is_file_in_list(file, true) && !self.filter.is_file_in_list(file, false)
+    }
+}
+
 /// A data structure to contain CLI options that relate to
 /// clang-tidy or clang-format arguments.
 #[derive(Debug, Clone, Default)]
 pub struct ClangParams {
     pub tidy_checks: String,
+    pub strict_checks: bool,
+    pub nolint_audit: bool,
     pub lines_changed_only: LinesChangedOnly,
     pub database: Option<PathBuf>,
     pub extra_args: Vec<String>,
@@ -183,15 +265,113 @@ pub struct ClangParams {
     pub clang_format_command: Option<PathBuf>,
     pub tidy_filter: Option<FileFilter>,
     pub format_filter: Option<FileFilter>,
+
+    /// The filter applied to files analyzed by the [`ClangParams::analyzers`].
+    ///
+    /// Like clang-tidy, this uses the `--ignore-tidy` patterns (plus the patterns in
+    /// the `[analyzers]` section of a `.cpp-linter-ignore` file).
+    pub analyzer_filter: Option<FileFilter>,
     pub tidy_review: bool,
     pub format_review: bool,
+    pub overrides: Vec<ClangOverride>,
+
+    /// A map of header files to the (absolute) path of a translation unit that
+    /// includes them.
+    ///
+    /// A header in this map is analyzed by clang-tidy through its translation unit.
+    pub header_units: HashMap<PathBuf, PathBuf>,
+
+    /// A map of source files (missing from the compilation database) to the
+    /// compiler flags inferred for them.
+    ///
+    /// These flags are passed to clang-tidy via `--extra-arg`.
+    pub inferred_flags: HashMap<PathBuf, Vec<String>>,
+
+    /// The names of the analyzers to use.
+    ///
+    /// These are clang-format and clang-tidy (if enabled), followed by the analyzers
+    /// enabled with `--analyzers`.
+    pub analyzer_names: Vec<String>,
+
+    /// The analyzers (with their executables resolved).
+    pub analyzers: Vec<ResolvedAnalyzer>,
+
+    /// The house rules checked by the built-in rule engine.
+    pub rules: Vec<Rule>,
+
+    /// The maximum number of files analyzed concurrently (`0` means no limit).
+    pub jobs: usize,
+
+    /// The time that an additional analyzer may take to analyze a single file.
+    pub analyzer_timeout: Option<Duration>,
+
+    /// The clang-tidy plugins passed to clang-tidy via `-load`.
+    pub tidy_load: Vec<PathBuf>,
+
+    /// The URL templates for the documentation of clang-tidy checks.
+    pub tidy_doc_links: Vec<TidyDocLink>,
+
+    /// The version of clang-tidy used (to link to the matching documentation).
+    pub tidy_version: Option<String>,
+
+    /// Was the diff of the analyzed files fetched?
+    ///
+    /// If so, a file with no [`FileObj::diff_chunks`](crate::common_fs::FileObj) was
+    /// not changed.
+    pub diff_available: bool,
+}
+
+impl ClangParams {
+    /// Get the effective parameters for the given `file`.
+    ///
+    /// All [`ClangParams::overrides`] that match the `file` are applied in order,
+    /// so a later override takes precedence over an earlier one.
+    pub fn resolve_for(&self, file: &Path) -> Cow<'_, ClangParams> {
+        let mut resolved = Cow::Borrowed(self);
+        for over in self.overrides.iter().filter(|o| o.is_match(file)) {
+            let params = resolved.to_mut();
+            if let Some(tidy_checks) = &over.tidy_checks {
+                params.tidy_checks = tidy_checks.clone();
+            }
+            if let Some(style) = &over.style {
+                params.style = style.clone();
+            }
+            if let Some(extra_args) = &over.extra_args {
+                params.extra_args = extra_args.clone();
+            }
+            if let Some(lines_changed_only) = &over.lines_changed_only {
+                params.lines_changed_only = lines_changed_only.clone();
+            }
+        }
+        resolved
+    }
+
+    /// Is clang-tidy used for any file (considering all [`ClangParams::overrides`])?
+    pub fn is_tidy_enabled(&self) -> bool {
+        self.tidy_checks != "-*"
+            || self
+                .overrides
+                .iter()
+                .any(|o| o.tidy_checks.as_ref().is_some_and(|checks| checks != "-*"))
+    }
+
+    /// Is clang-format used for any file (considering all [`ClangParams::overrides`])?
+    pub fn is_format_enabled(&self) -> bool {
+        !self.style.is_empty()
+            || self
+                .overrides
+                .iter()
+                .any(|o| o.style.as_ref().is_some_and(|style| !style.is_empty()))
+    }
 }
 
 impl From<&Cli> for ClangParams {
     /// Construct a [`ClangParams`] instance from a [`Cli`] instance.
     fn from(args: &Cli) -> Self {
-        ClangParams {
+        let mut params = ClangParams {
             tidy_checks: args.tidy_checks.clone(),
+            strict_checks: args.strict_checks,
+            nolint_audit: args.nolint_audit,
             lines_changed_only: args.lines_changed_only.clone(),
             database: args.database.clone(),
             extra_args: args.extra_arg.clone(),
@@ -207,9 +387,43 @@ impl From<&Cli> for ClangParams {
                 .ignore_format
                 .as_ref()
                 .map(|ignore_format| FileFilter::new(ignore_format, args.extensions.clone())),
+            analyzer_filter: args
+                .ignore_tidy
+                .as_ref()
+                .map(|ignore_tidy| FileFilter::new(ignore_tidy, args.extensions.clone())),
             tidy_review: args.tidy_review,
             format_review: args.format_review,
+            overrides: args.overrides.clone(),
+            header_units: HashMap::new(),
+            inferred_flags: HashMap::new(),
+            analyzer_names: vec![],
+            analyzers: vec![],
+            rules: args.rules.clone(),
+            jobs: args.jobs,
+            tidy_load: args.tidy_load.clone(),
+            tidy_doc_links: args.tidy_doc_links.clone(),
+            tidy_version: None,
+            diff_available: false,
+            analyzer_timeout: match args.analyzer_timeout {
+                0 => None,
+                seconds => Some(Duration::from_secs(seconds)),
+            },
+        };
+        // clang-format and clang-tidy are used whenever any file is checked with them
+        if params.is_format_enabled() {
+            params.analyzer_names.push(CLANG_FORMAT_NAME.to_string());
+        }
+        if params.is_tidy_enabled() {
+            params.analyzer_names.push(CLANG_TIDY_NAME.to_string());
+        }
+        // the rule engine is used whenever any rules are configured
+        let rules = (!args.rules.is_empty()).then_some(RULES_NAME.to_string());
+        for name in args.analyzers.iter().cloned().chain(rules) {
+            if !params.analyzer_names.contains(&name) {
+                params.analyzer_names.push(name);
+            }
         }
+        params
     }
 }
 
@@ -1,17 +1,29 @@
 use anyhow::{anyhow, Context, Result};
 use fast_glob::glob_match;
+use git2::Repository;
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    DirEntry, Match, WalkBuilder, WalkState,
+};
 use std::{
-    fs,
-    path::{Path, PathBuf},
+    env, fs,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
 };
 
 use super::FileObj;
+use crate::clang_tools::compilation_db::CompilationUnit;
 
 #[derive(Debug, Clone)]
 pub struct FileFilter {
     pub ignored: Vec<String>,
     pub not_ignored: Vec<String>,
     pub extensions: Vec<String>,
+
+    /// Patterns (in gitignore syntax) added from an ignore file.
+    ///
+    /// See [`FileFilter::add_ignore_patterns()`].
+    pub ignore_file: Option<Gitignore>,
 }
 impl FileFilter {
     pub fn new(ignore: &[String], extensions: Vec<String>) -> Self {
@@ -20,6 +32,51 @@ impl FileFilter {
             ignored,
             not_ignored,
             extensions,
+            ignore_file: None,
+        }
+    }
+
+    /// Add the given `patterns` (in gitignore syntax) to this filter.
+    ///
+    /// The patterns are considered relative to the current working directory.
+    /// Any patterns previously added are replaced.
+    /// Negated patterns (prefixed with `!`) will explicitly include a path that
+    /// would otherwise be ignored.
+    pub fn add_ignore_patterns(&mut self, patterns: &[String]) -> Result<()> {
+        let root = env::current_dir().with_context(|| "Failed to get current working directory")?;
+        let mut builder = GitignoreBuilder::new(&root);
+        for pattern in patterns {
+            builder
+                .add_line(None, pattern)
+                .with_context(|| format!("Invalid ignore pattern: {pattern}"))?;
+        }
+        self.ignore_file = Some(
+            builder
+                .build()
+                .with_context(|| "Failed to compile ignore patterns")?,
+        );
+        Ok(())
+    }
+
+    /// Match the given `file_name` against the patterns added with
+    /// [`FileFilter::add_ignore_patterns()`].
+    fn match_ignore_file(&self, file_name: &Path) -> Match<()> {
+        let Some(ignore_file) = &self.ignore_file else {
+            return Match::None;
+        };
+        let path = if file_name.is_absolute() {
+            match file_name.strip_prefix(ignore_file.path()) {
+                Ok(rel_path) => rel_path,
+                // paths outside the root cannot be matched
+                Err(_) => return Match::None,
+            }
+        } else {
+            file_name.strip_prefix("./").unwrap_or(file_name)
+        };
+        match ignore_file.matched_path_or_any_parents(path, false) {
+            Match::Ignore(_) => Match::Ignore(()),
+            Match::Whitelist(_) => Match::Whitelist(()),
+            Match::None => Match::None,
         }
     }
 
@@ -61,7 +118,8 @@ impl FileFilter {
             for line in read_buf.split('\n') {
                 if line.trim_start().starts_with("path") {
                     assert!(line.find('=').unwrap() > 0);
-                    let submodule = String::from("./") + line.split('=').last().unwrap().trim();
+                    let submodule =
+                        String::from("./") + line.split('=').next_back().unwrap().trim();
                     log::debug!("Found submodule: {submodule}");
                     let mut is_ignored = true;
                     for pat in &self.not_ignored {
@@ -128,6 +186,8 @@ impl FileFilter {
     /// - Is `entry` *not* specified in list of `ignored` paths?
     /// - Is `entry` specified in the list of explicitly `not_ignored` paths? (supersedes
     ///   specified `ignored` paths)
+    /// - Is `entry` matched by any patterns added from an ignore file? (supersedes
+    ///   specified `ignored` paths, but not `not_ignored` paths)
     pub fn is_source_or_ignored(&self, entry: &Path) -> bool {
         let extension = entry
             .extension()
@@ -137,11 +197,17 @@ impl FileFilter {
         if !self.extensions.contains(&extension) {
             return false;
         }
-        let is_in_not_ignored = self.is_file_in_list(entry, false);
-        if is_in_not_ignored || !self.is_file_in_list(entry, true) {
+        if self.is_file_in_list(entry, false) {
             return true;
         }
-        false
+        match self.match_ignore_file(entry) {
+            Match::Ignore(_) => {
+                log::debug!("file {entry:?} is ignored by an ignore file.");
+                false
+            }
+            Match::Whitelist(_) => true,
+            Match::None => !self.is_file_in_list(entry, true),
+        }
     }
 
     /// Walks a given `root_path` recursively and returns a [`Vec<FileObj>`] that
@@ -149,31 +215,141 @@ impl FileFilter {
     /// - uses at least 1 of the given `extensions`
     /// - is not specified in the internal list of `ignored` paths
     /// - is specified in the internal list `not_ignored` paths (which supersedes `ignored` paths)
+    ///
+    /// The directory tree is traversed in parallel. Hidden directories are skipped, and
+    /// any paths excluded by `.gitignore` files, `.git/info/exclude`, or the user's
+    /// global git excludes file are not listed.
     pub fn list_source_files(&self, root_path: &str) -> Result<Vec<FileObj>> {
-        let mut files: Vec<FileObj> = Vec::new();
-        let entries = fs::read_dir(root_path)
+        fs::read_dir(root_path)
             .with_context(|| format!("Failed to read directory contents: {root_path}"))?;
-        for entry in entries.filter_map(|p| p.ok()) {
-            let path = entry.path();
-            if path.is_dir() {
-                let mut is_hidden = false;
-                let parent = path
-                    .components()
-                    .last()
-                    .ok_or(anyhow!("parent directory not known for {path:?}"))?;
-                if parent.as_os_str().to_str().unwrap().starts_with('.') {
-                    is_hidden = true;
-                }
-                if !is_hidden {
-                    files.extend(self.list_source_files(&path.to_string_lossy())?);
-                }
-            } else {
-                let is_valid_src = self.is_source_or_ignored(&path);
-                if is_valid_src {
-                    files.push(FileObj::new(
-                        path.clone().strip_prefix("./").unwrap().to_path_buf(),
-                    ));
+        let files = Mutex::new(Vec::new());
+        let walker = WalkBuilder::new(root_path)
+            // hidden files are allowed, but hidden directories are filtered out below
+            .hidden(false)
+            .ignore(false)
+            .git_ignore(true)
+            .git_exclude(true)
+            .git_global(true)
+            .filter_entry(|entry| !Self::is_hidden_dir(entry))
+            .build_parallel();
+        walker.run(|| {
+            let files = &files;
+            Box::new(move |entry| {
+                match entry {
+                    Ok(entry) => {
+                        if entry.file_type().is_some_and(|t| t.is_file()) {
+                            let path = entry.path();
+                            if self.is_source_or_ignored(path) {
+                                let name = path.strip_prefix("./").unwrap_or(path).to_path_buf();
+                                files.lock().unwrap().push(FileObj::new(name));
+                            }
+                        }
+                    }
+                    Err(e) => log::debug!("Skipping path while listing source files: {e}"),
                 }
+                WalkState::Continue
+            })
+        });
+        let mut files = files
+            .into_inner()
+            .map_err(|_| anyhow!("Failed to collect the list of source files"))?;
+        // parallel traversal yields an arbitrary order
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(files)
+    }
+
+    /// Is the given `entry` a hidden directory (beginning with a `.`)?
+    ///
+    /// The root path of a traversal is never considered hidden.
+    fn is_hidden_dir(entry: &DirEntry) -> bool {
+        entry.depth() > 0
+            && entry.file_type().is_some_and(|t| t.is_dir())
+            && entry.file_name().to_string_lossy().starts_with('.')
+    }
+
+    /// Is the given relative `path` located in a hidden directory (beginning with a `.`)?
+    fn is_in_hidden_dir(path: &Path) -> bool {
+        path.parent().is_some_and(|parent| {
+            parent.components().any(
+                |c| matches!(c, Component::Normal(name) if name.to_string_lossy().starts_with('.')),
+            )
+        })
+    }
+
+    /// Lists the translation units in the given compilation database `units` and returns
+    /// a [`Vec<FileObj>`] that satisfy the same conditions as
+    /// [`FileFilter::list_source_files()`].
+    ///
+    /// The listed paths are relative to the given `root` (the repository root). Units
+    /// that are located outside the `root` or no longer exist are not listed. Duplicate
+    /// entries (for example, a file compiled with multiple configurations) are only
+    /// listed once.
+    pub fn list_database_files(&self, units: &[CompilationUnit], root: &Path) -> Vec<FileObj> {
+        let mut files: Vec<FileObj> = Vec::new();
+        for unit in units {
+            let path = unit.path();
+            let Ok(rel_path) = path.strip_prefix(root) else {
+                log::debug!(
+                    "Skipping {} because it is outside the repository",
+                    path.to_string_lossy()
+                );
+                continue;
+            };
+            if !path.is_file() || files.iter().any(|f| f.name == rel_path) {
+                continue;
+            }
+            if !Self::is_in_hidden_dir(rel_path) && self.is_source_or_ignored(rel_path) {
+                files.push(FileObj::new(rel_path.to_path_buf()));
+            }
+        }
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        files
+    }
+
+    /// Lists the files tracked in the given `repo`'s index and returns a [`Vec<FileObj>`]
+    /// that satisfy the same conditions as [`FileFilter::list_source_files()`].
+    ///
+    /// Only files under the given `root` directory (which may be a subdirectory of the
+    /// repository's working tree) are listed, and their paths are relative to `root`.
+    ///
+    /// Files in hidden directories, submodules, and files that were removed from the
+    /// working tree are not listed.
+    pub fn list_tracked_files(&self, repo: &Repository, root: &Path) -> Result<Vec<FileObj>> {
+        let work_dir = repo
+            .workdir()
+            .ok_or(anyhow!("Cannot list tracked files of a bare repository"))?;
+        let canonical = |path: &Path| {
+            path.canonicalize()
+                .with_context(|| format!("Failed to resolve path {}", path.to_string_lossy()))
+        };
+        let prefix = canonical(root)?
+            .strip_prefix(canonical(work_dir)?)
+            .map(Path::to_path_buf)
+            .map_err(|_| {
+                anyhow!(
+                    "{} is not inside the repository's working tree",
+                    root.to_string_lossy()
+                )
+            })?;
+        let index = repo
+            .index()
+            .with_context(|| "Failed to read the repository's index")?;
+        let mut files = Vec::new();
+        for entry in index.iter() {
+            // skip submodules (gitlinks)
+            if entry.mode == 0o160000 {
+                continue;
+            }
+            let index_path = PathBuf::from(String::from_utf8_lossy(&entry.path).to_string());
+            // index paths are relative to the working tree, not the `root`
+            let Ok(path) = index_path.strip_prefix(&prefix) else {
+                continue;
+            };
+            if Self::is_in_hidden_dir(path) || !work_dir.join(&index_path).is_file() {
+                continue;
+            }
+            if self.is_source_or_ignored(path) {
+                files.push(FileObj::new(path.to_path_buf()));
             }
         }
         Ok(files)
@@ -183,8 +359,10 @@ impl FileFilter {
 #[cfg(test)]
 mod tests {
     use super::FileFilter;
-    use crate::cli::get_arg_parser;
-    use std::{env::set_current_dir, path::PathBuf};
+    use crate::{clang_tools::compilation_db::CompilationUnit, cli::get_arg_parser};
+    use git2::Repository;
+    use std::{env::set_current_dir, fs, path::PathBuf};
+    use tempfile::tempdir;
 
     // ************* tests for ignored paths
 
@@ -269,4 +447,125 @@ mod tests {
             ));
         }
     }
+
+    /// Creates a git repo in a temp directory with some tracked, untracked,
+    /// and git-ignored sources.
+    fn setup_git_tree() -> (tempfile::TempDir, Repository) {
+        let tmp = tempdir().unwrap();
+        let repo = Repository::init(tmp.path()).unwrap();
+        for dir in ["src", "build", ".hidden"] {
+            fs::create_dir(tmp.path().join(dir)).unwrap();
+        }
+        fs::write(tmp.path().join(".gitignore"), "build/\n").unwrap();
+        for file in [
+            "src/tracked.cpp",
+            "src/untracked.cpp",
+            "build/generated.cpp",
+            ".hidden/secret.cpp",
+        ] {
+            fs::write(tmp.path().join(file), "").unwrap();
+        }
+        let mut index = repo.index().unwrap();
+        index.add_path(&PathBuf::from("src/tracked.cpp")).unwrap();
+        index.write().unwrap();
+        (tmp, repo)
+    }
+
+    #[test]
+    fn walk_dir_respects_gitignore() {
+        let (tmp, _repo) = setup_git_tree();
+        let file_filter = FileFilter::new(&[], vec!["cpp".to_string()]);
+        let files = file_filter
+            .list_source_files(tmp.path().to_str().unwrap())
+            .unwrap();
+        let names = files
+            .iter()
+            .map(|f| f.name.strip_prefix(tmp.path()).unwrap().to_path_buf())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                PathBuf::from("src/tracked.cpp"),
+                PathBuf::from("src/untracked.cpp")
+            ]
+        );
+    }
+
+    #[test]
+    fn list_tracked_only() {
+        let (_tmp, repo) = setup_git_tree();
+        let file_filter = FileFilter::new(&[], vec!["cpp".to_string()]);
+        let files = file_filter
+            .list_tracked_files(&repo, repo.workdir().unwrap())
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, PathBuf::from("src/tracked.cpp"));
+    }
+
+    #[test]
+    fn list_tracked_in_nested_root() {
+        let (tmp, repo) = setup_git_tree();
+        fs::create_dir(tmp.path().join("src/lib")).unwrap();
+        fs::write(tmp.path().join("src/lib/nested.cpp"), "").unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_path(&PathBuf::from("src/lib/nested.cpp"))
+            .unwrap();
+        index.write().unwrap();
+        let file_filter = FileFilter::new(&[], vec!["cpp".to_string()]);
+        let files = file_filter
+            .list_tracked_files(&repo, &tmp.path().join("src"))
+            .unwrap();
+        let names = files.iter().map(|f| f.name.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                PathBuf::from("lib/nested.cpp"),
+                PathBuf::from("tracked.cpp")
+            ]
+        );
+        assert!(file_filter
+            .list_tracked_files(&repo, tempdir().unwrap().path())
+            .is_err());
+    }
+
+    #[test]
+    fn ignore_file_patterns() {
+        let mut file_filter =
+            FileFilter::new(&["!src/keep.cpp".to_string()], vec!["cpp".to_string()]);
+        file_filter
+            .add_ignore_patterns(&[
+                "third_party/".to_string(),
+                "src/*.cpp".to_string(),
+                "!src/main.cpp".to_string(),
+            ])
+            .unwrap();
+        assert!(!file_filter.is_source_or_ignored(&PathBuf::from("third_party/lib/dep.cpp")));
+        assert!(!file_filter.is_source_or_ignored(&PathBuf::from("./src/util.cpp")));
+        assert!(file_filter.is_source_or_ignored(&PathBuf::from("src/main.cpp")));
+        // `--ignore` with a `!` prefix supersedes the ignore file
+        assert!(file_filter.is_source_or_ignored(&PathBuf::from("src/keep.cpp")));
+        assert!(file_filter.is_source_or_ignored(&PathBuf::from("tests/test.cpp")));
+    }
+
+    #[test]
+    fn list_database_only() {
+        let (tmp, _repo) = setup_git_tree();
+        let unit = |file: &str| CompilationUnit {
+            directory: tmp.path().join("build").to_string_lossy().to_string(),
+            file: file.to_string(),
+            ..Default::default()
+        };
+        let units = vec![
+            unit("../src/tracked.cpp"),
+            unit("../src/tracked.cpp"), // duplicate entry
+            unit("generated.cpp"),
+            unit("../src/deleted.cpp"),
+            unit("/not/in/repo.cpp"),
+        ];
+        let file_filter = FileFilter::new(&["build/**".to_string()], vec!["cpp".to_string()]);
+        let files = file_filter.list_database_files(&units, tmp.path());
+        let names = files.iter().map(|f| f.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names, vec![PathBuf::from("src/tracked.cpp")]);
+    }
 }
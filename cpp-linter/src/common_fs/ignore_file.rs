@@ -0,0 +1,208 @@
+//! This module holds functionality for reading files that list ignored paths.
+//!
+//! This includes cpp-linter's own [`IGNORE_FILE_NAME`] (which uses gitignore syntax)
+//! and clang-format's native `.clang-format-ignore` files.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use fast_glob::glob_match;
+
+/// The name of the repository-level file that lists ignored paths.
+pub const IGNORE_FILE_NAME: &str = ".cpp-linter-ignore";
+
+/// The name of clang-format's native ignore file.
+pub const CLANG_FORMAT_IGNORE_FILE_NAME: &str = ".clang-format-ignore";
+
+/// A structure to hold the patterns parsed from a [`IGNORE_FILE_NAME`] file.
+///
+/// The file uses gitignore syntax. Patterns listed before any section header apply
+/// to all analysis. Patterns listed after a `[tidy]` or `[format]` section header
+/// apply exclusively to clang-tidy or clang-format (respectively). Patterns listed
+/// after an `[analyzers]` section header apply to the additional analyzers (see
+/// `--analyzers`).
+///
+/// ```text
+/// # ignored by all tools
+/// third_party/
+///
+/// [tidy]
+/// generated/*.cpp
+///
+/// [format]
+/// legacy/**
+/// !legacy/new_code.cpp
+///
+/// [analyzers]
+/// tests/**
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreFile {
+    /// The patterns that apply to all analysis.
+    pub general: Vec<String>,
+
+    /// The patterns in the `[tidy]` section.
+    pub tidy: Vec<String>,
+
+    /// The patterns in the `[format]` section.
+    pub format: Vec<String>,
+
+    /// The patterns in the `[analyzers]` section.
+    pub analyzers: Vec<String>,
+}
+
+impl IgnoreFile {
+    /// Parse the `contents` of an ignore file into its sections.
+    ///
+    /// Blank lines and comments (lines beginning with `#`) are skipped.
+    /// Lines in an unknown section are skipped (with a warning).
+    pub fn parse(contents: &str) -> Self {
+        let mut result = Self::default();
+        let mut section = Some(&mut result.general);
+        for line in contents.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            match trimmed {
+                "[tidy]" => section = Some(&mut result.tidy),
+                "[format]" => section = Some(&mut result.format),
+                "[analyzers]" => section = Some(&mut result.analyzers),
+                _ if trimmed.starts_with('[')
+                    && trimmed.ends_with(']')
+                    && !trimmed.contains('/') =>
+                {
+                    log::warn!("Unknown section {trimmed} in {IGNORE_FILE_NAME}; skipping it");
+                    section = None;
+                }
+                _ => {
+                    if let Some(patterns) = &mut section {
+                        patterns.push(line.trim_end().to_string());
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Read and parse the ignore file at the given `path`.
+    ///
+    /// Returns [`None`] if the file does not exist.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read ignore file: {}", path.display()))?;
+        Ok(Some(Self::parse(&contents)))
+    }
+}
+
+/// Is the given `file` ignored by a `.clang-format-ignore` file?
+///
+/// Like clang-format, only the nearest `.clang-format-ignore` file (found in the
+/// `file`'s directory or any of its parent directories up to `root`) is used.
+/// Each pattern is matched against the `file`'s path relative to the directory
+/// containing the `.clang-format-ignore` file. A pattern prefixed with `!` is negated.
+///
+/// A relative `file` path is assumed to be relative to `root`.
+pub fn is_clang_format_ignored(file: &Path, root: &Path) -> bool {
+    let file = if file.is_absolute() {
+        file.to_path_buf()
+    } else {
+        root.join(file)
+    };
+    let mut dir = file.parent().map(Path::to_path_buf);
+    while let Some(current) = dir {
+        if !current.starts_with(root) {
+            break;
+        }
+        let ignore_file = current.join(CLANG_FORMAT_IGNORE_FILE_NAME);
+        if let Ok(contents) = fs::read_to_string(&ignore_file) {
+            let rel_path = file
+                .strip_prefix(&current)
+                .unwrap_or(&file)
+                .to_string_lossy()
+                .replace('\\', "/");
+            return contents.lines().any(|line| {
+                let pattern = line.trim();
+                if pattern.is_empty() || pattern.starts_with('#') {
+                    return false;
+                }
+                let (is_negated, pattern) = match pattern.strip_prefix('!') {
+                    Some(negated) => (true, negated),
+                    None => (false, pattern),
+                };
+                glob_match(pattern.trim_start_matches("./"), &rel_path) != is_negated
+            });
+        }
+        dir = current.parent().map(PathBuf::from);
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::{is_clang_format_ignored, IgnoreFile, CLANG_FORMAT_IGNORE_FILE_NAME};
+
+    #[test]
+    fn parse_sections() {
+        let ignore_file = IgnoreFile::parse(
+            "# comment\nthird_party/\n\n[tidy]\ngenerated/*.cpp\n[format]\nlegacy/**\n!legacy/new.cpp\n[analyzers]\ntests/**\n[unknown]\nskipped\n",
+        );
+        assert_eq!(ignore_file.general, vec!["third_party/"]);
+        assert_eq!(ignore_file.tidy, vec!["generated/*.cpp"]);
+        assert_eq!(ignore_file.format, vec!["legacy/**", "!legacy/new.cpp"]);
+        assert_eq!(ignore_file.analyzers, vec!["tests/**"]);
+    }
+
+    #[test]
+    fn clang_format_ignore() {
+        let tmp = tempdir().unwrap();
+        let sub_dir = tmp.path().join("src");
+        fs::create_dir(&sub_dir).unwrap();
+        fs::write(
+            sub_dir.join(CLANG_FORMAT_IGNORE_FILE_NAME),
+            "# comment\ngen_*.cpp\n",
+        )
+        .unwrap();
+        assert!(is_clang_format_ignored(
+            &sub_dir.join("gen_code.cpp"),
+            tmp.path()
+        ));
+        assert!(!is_clang_format_ignored(
+            &sub_dir.join("code.cpp"),
+            tmp.path()
+        ));
+        // no .clang-format-ignore in the root folder
+        assert!(!is_clang_format_ignored(
+            &tmp.path().join("gen_code.cpp"),
+            tmp.path()
+        ));
+    }
+
+    #[test]
+    fn clang_format_ignore_negated() {
+        let tmp = tempdir().unwrap();
+        fs::write(
+            tmp.path().join(CLANG_FORMAT_IGNORE_FILE_NAME),
+            "!src/*.cpp\n",
+        )
+        .unwrap();
+        assert!(!is_clang_format_ignored(
+            &tmp.path().join("src/code.cpp"),
+            tmp.path()
+        ));
+        assert!(is_clang_format_ignored(
+            &tmp.path().join("other/code.cpp"),
+            tmp.path()
+        ));
+    }
+}
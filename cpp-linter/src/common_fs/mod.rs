@@ -3,17 +3,22 @@
 use std::fmt::Debug;
 use std::fs;
 use std::path::{Component, Path};
+use std::sync::Arc;
 use std::{ops::RangeInclusive, path::PathBuf};
 
 use anyhow::{Context, Result};
 
-use crate::clang_tools::clang_format::FormatAdvice;
-use crate::clang_tools::clang_tidy::TidyAdvice;
-use crate::clang_tools::{make_patch, MakeSuggestions, ReviewComments, Suggestion};
+use crate::clang_tools::analyzer::Advice;
+use crate::clang_tools::nolint::NolintFinding;
+use crate::clang_tools::{make_patch, ReviewComments, Suggestion};
 use crate::cli::LinesChangedOnly;
 mod file_filter;
 pub use file_filter::FileFilter;
+mod ignore_file;
 use git2::DiffHunk;
+pub use ignore_file::{
+    is_clang_format_ignored, IgnoreFile, CLANG_FORMAT_IGNORE_FILE_NAME, IGNORE_FILE_NAME,
+};
 
 /// A structure to represent a file's path and line changes.
 #[derive(Debug, Clone)]
@@ -30,11 +35,11 @@ pub struct FileObj {
     /// The list of ranges that span the lines present in diff chunks.
     pub diff_chunks: Vec<RangeInclusive<u32>>,
 
-    /// The collection of clang-format advice for this file.
-    pub format_advice: Option<FormatAdvice>,
+    /// The advice from each analyzer (ie clang-format and clang-tidy) for this file.
+    pub advice: Vec<Arc<dyn Advice>>,
 
-    /// The collection of clang-format advice for this file.
-    pub tidy_advice: Option<TidyAdvice>,
+    /// The problems found by auditing the `NOLINT` comments in this file.
+    pub nolint_advice: Option<Vec<NolintFinding>>,
 }
 
 impl FileObj {
@@ -47,8 +52,8 @@ impl FileObj {
             added_lines: Vec::<u32>::new(),
             added_ranges: Vec::<RangeInclusive<u32>>::new(),
             diff_chunks: Vec::<RangeInclusive<u32>>::new(),
-            format_advice: None,
-            tidy_advice: None,
+            advice: vec![],
+            nolint_advice: None,
         }
     }
 
@@ -64,8 +69,8 @@ impl FileObj {
             added_lines,
             added_ranges,
             diff_chunks,
-            format_advice: None,
-            tidy_advice: None,
+            advice: vec![],
+            nolint_advice: None,
         }
     }
 
@@ -136,7 +141,7 @@ impl FileObj {
     /// generated [`Patch`](struct@git2::Patch) and store them in the given
     /// [`ReviewComments`](struct@crate::clang_tools::ReviewComments).
     ///
-    /// The suggestions will also include diagnostics from clang-tidy that
+    /// The suggestions will also include diagnostics (ie from clang-tidy) that
     /// did not have a fix applied in the patch.
     pub fn make_suggestions_from_patch(
         &self,
@@ -147,78 +152,56 @@ impl FileObj {
             fs::read(&self.name).with_context(|| "Failed to read original contents of file")?;
         let file_name = self.name.to_str().unwrap_or_default().replace("\\", "/");
         let file_path = Path::new(&file_name);
-        if let Some(advice) = &self.format_advice {
-            if let Some(patched) = &advice.patched {
+        for advice in &self.advice {
+            if let Some(patched) = advice.patched() {
                 let mut patch = make_patch(file_path, patched, &original_content)?;
                 advice.get_suggestions(review_comments, self, &mut patch, summary_only)?;
             }
-        }
-
-        if let Some(advice) = &self.tidy_advice {
-            if let Some(patched) = &advice.patched {
-                let mut patch = make_patch(file_path, patched, &original_content)?;
-                advice.get_suggestions(review_comments, self, &mut patch, summary_only)?;
-            }
-
             if summary_only {
-                return Ok(());
+                continue;
             }
-
-            // now check for clang-tidy warnings with no fixes applied
-            let file_ext = self
-                .name
-                .extension()
-                .unwrap_or_default()
-                .to_str()
-                .unwrap_or_default();
-            // Count of clang-tidy diagnostics that had no fixes applied
+            // now check for diagnostics with no fixes applied
+            // Count of diagnostics that had no fixes applied
             let mut total = 0;
-            for note in &advice.notes {
-                if note.fixed_lines.is_empty() && self.is_line_in_diff(&note.line) {
-                    // notification had no suggestion applied in `patched`
-                    let mut suggestion = format!(
-                        "### clang-tidy diagnostic\n**{file_name}:{}:{}** {}: [{}]\n\n> {}\n",
-                        &note.line,
-                        &note.cols,
-                        &note.severity,
-                        note.diagnostic_link(),
-                        &note.rationale
-                    );
-                    if !note.suggestion.is_empty() {
-                        suggestion.push_str(
-                            format!("\n```{file_ext}\n{}\n```\n", &note.suggestion.join("\n"))
-                                .as_str(),
-                        );
-                    }
+            for (line, suggestion) in advice.unfixed_concerns(self, review_comments) {
+                if self.is_line_in_diff(&line) {
                     total += 1;
-                    let mut is_merged = false;
-                    for s in &mut review_comments.comments {
-                        if s.path == file_name
-                            && s.line_end >= note.line
-                            && s.line_start <= note.line
-                        {
-                            s.suggestion.push_str(suggestion.as_str());
-                            is_merged = true;
-                            break;
-                        }
-                    }
-                    if !is_merged {
-                        review_comments.comments.push(Suggestion {
-                            line_start: note.line,
-                            line_end: note.line,
-                            suggestion,
-                            path: file_name.to_owned(),
-                        });
-                    }
+                    add_diagnostic_comment(review_comments, &file_name, line, suggestion);
                 }
             }
-            review_comments.tool_total[1] =
-                Some(review_comments.tool_total[1].unwrap_or_default() + total);
+            *review_comments
+                .tool_total
+                .entry(advice.get_tool_name())
+                .or_default() += total;
         }
         Ok(())
     }
 }
 
+/// Add a `suggestion` about a diagnostic (on the given `line` of the given `file_name`)
+/// to the `review_comments`.
+///
+/// The `suggestion` is merged into any existing comment that spans the `line`.
+fn add_diagnostic_comment(
+    review_comments: &mut ReviewComments,
+    file_name: &str,
+    line: u32,
+    suggestion: String,
+) {
+    for s in &mut review_comments.comments {
+        if s.path == file_name && s.line_end >= line && s.line_start <= line {
+            s.suggestion.push_str(suggestion.as_str());
+            return;
+        }
+    }
+    review_comments.comments.push(Suggestion {
+        line_start: line,
+        line_end: line,
+        suggestion,
+        path: file_name.to_owned(),
+    });
+}
+
 /// Gets the line number for a given `offset` (of bytes) from the given
 /// buffer `contents`.
 ///
@@ -231,6 +214,21 @@ pub fn get_line_count_from_offset(contents: &[u8], offset: u32) -> u32 {
     lines.count() as u32
 }
 
+/// Gets the column number (in bytes) for a given `offset` (of bytes) from the given
+/// buffer `contents`.
+///
+/// Like [`get_line_count_from_offset()`], any `offset` out of bounds is clamped to the
+/// given `contents` buffer's length.
+pub fn get_column_from_offset(contents: &[u8], offset: u32) -> u32 {
+    let offset = (offset as usize).min(contents.len());
+    let line_start = contents[0..offset]
+        .iter()
+        .rposition(|byte| byte == &b'\n')
+        .map(|pos| pos + 1)
+        .unwrap_or_default();
+    (offset - line_start) as u32 + 1
+}
+
 /// This was copied from [cargo source code](https://github.com/rust-lang/cargo/blob/fede83ccf973457de319ba6fa0e36ead454d2e20/src/cargo/util/paths.rs#L61).
 ///
 /// NOTE: Rust [std::path] crate has no native functionality equivalent to this.
@@ -266,7 +264,7 @@ mod test {
     use std::path::PathBuf;
     use std::{env::current_dir, fs};
 
-    use super::{get_line_count_from_offset, normalize_path, FileObj};
+    use super::{get_column_from_offset, get_line_count_from_offset, normalize_path, FileObj};
     use crate::cli::LinesChangedOnly;
 
     // *********************** tests for normalized paths
@@ -326,6 +324,9 @@ mod test {
 
         // Offset beyond content length
         assert_eq!(get_line_count_from_offset(b"a\nb\n", 10), 3);
+        assert_eq!(get_column_from_offset(b"a\nbc", 4), 3);
+        assert_eq!(get_column_from_offset(b"abc", 0), 1);
+        assert_eq!(get_column_from_offset(b"a\n", 10), 1);
     }
     // *********************** tests for FileObj::get_ranges()
 
@@ -8,7 +8,12 @@
 //! (str or bytes) only happens in CI or when libgit2 cannot be used to initialize a
 //! repository.
 
-use std::{ops::RangeInclusive, path::PathBuf};
+use std::{
+    fs,
+    io::{self, Read},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Context, Result};
 // non-std crates
@@ -29,6 +34,12 @@ pub fn open_repo(path: &str) -> Result<Repository, Error> {
     Repository::open(PathBuf::from(path).as_path())
 }
 
+/// Like [`open_repo()`], but the repository may also be located in a parent
+/// directory of the specified `path`.
+pub fn discover_repo(path: &str) -> Result<Repository, Error> {
+    Repository::discover(PathBuf::from(path).as_path())
+}
+
 /// Fetches the SHA1 of the commit for the specified [`git2::Repository`].
 ///
 /// The optionally specified `depth` can be used to traverse the tree a number of times
@@ -49,7 +60,7 @@ fn get_sha(repo: &Repository, depth: Option<u32>) -> Result<git2::Object<'_>, Er
 /// If there are files staged for a commit, then the resulting [`Diff`] will describe
 /// the staged changes. However, if there are no staged changes, then the last commit's
 /// [`Diff`] is returned.
-pub fn get_diff(repo: &Repository) -> Result<git2::Diff> {
+pub fn get_diff(repo: &Repository) -> Result<git2::Diff<'_>> {
     let head = get_sha(repo, None).unwrap().peel_to_tree().unwrap();
     let mut has_staged_files = false;
     for entry in repo.statuses(None).unwrap().iter() {
@@ -156,6 +167,28 @@ pub fn parse_diff_from_buf(
     }
 }
 
+/// Same as [`parse_diff_from_buf`] but reads the diff from a file at the given `path`.
+///
+/// If the given `path` is `-`, then the diff is read from stdin instead.
+/// This allows a diff (as produced by `git diff`) to be piped into cpp-linter
+/// when no git server's REST API (or repository history) is available.
+pub fn parse_diff_from_file(
+    path: &Path,
+    file_filter: &FileFilter,
+    lines_changed_only: &LinesChangedOnly,
+) -> Result<Vec<FileObj>> {
+    let mut buf = Vec::new();
+    if path.as_os_str() == "-" {
+        io::stdin()
+            .read_to_end(&mut buf)
+            .with_context(|| "Failed to read diff from stdin")?;
+    } else {
+        buf = fs::read(path)
+            .with_context(|| format!("Failed to read diff from file: {}", path.display()))?;
+    }
+    Ok(parse_diff_from_buf(&buf, file_filter, lines_changed_only))
+}
+
 mod brute_force_parse_diff {
     //! A private module to house the brute force algorithms of parsing a diff as a string.
     //! This module is only intended as a fall back mechanism when [super::parse_diff_from_buf]
@@ -389,6 +422,7 @@ mod test {
     use std::{
         env::{self, current_dir, set_current_dir},
         fs::read,
+        path::PathBuf,
     };
 
     use git2::build::CheckoutBuilder;
@@ -417,6 +451,7 @@ mod test {
 
     use tempfile::{tempdir, TempDir};
 
+    use super::parse_diff_from_file;
     use crate::{
         cli::LinesChangedOnly,
         common_fs::FileFilter,
@@ -510,4 +545,33 @@ mod test {
         set_current_dir(cur_dir).unwrap(); // prep to delete temp_folder
         drop(tmp); // delete temp_folder
     }
+
+    #[test]
+    fn diff_from_file() {
+        let file_filter = FileFilter::new(
+            &["target".to_string()],
+            vec!["cpp".to_string(), "hpp".to_string()],
+        );
+        let files = parse_diff_from_file(
+            &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/comment_test_assets/patch.diff"),
+            &file_filter,
+            &LinesChangedOnly::On,
+        )
+        .unwrap();
+        assert_eq!(files.len(), 2);
+        for file in files {
+            assert!(file.name.starts_with("src"));
+            assert!(!file.added_lines.is_empty());
+        }
+    }
+
+    #[test]
+    fn diff_from_missing_file() {
+        let result = parse_diff_from_file(
+            &PathBuf::from("not-a-file.diff"),
+            &FileFilter::new(&[], vec![]),
+            &LinesChangedOnly::Off,
+        );
+        assert!(result.is_err());
+    }
 }
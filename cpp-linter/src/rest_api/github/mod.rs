@@ -17,8 +17,10 @@ use reqwest::{
 
 // project specific modules/crates
 use super::{RestApiClient, RestApiRateLimitHeaders};
-use crate::clang_tools::clang_format::tally_format_advice;
-use crate::clang_tools::clang_tidy::tally_tidy_advice;
+use crate::clang_tools::analyzer::tally_advice;
+use crate::clang_tools::clang_format::CLANG_FORMAT_NAME;
+use crate::clang_tools::clang_tidy::CLANG_TIDY_NAME;
+use crate::clang_tools::stats::CheckStats;
 use crate::clang_tools::ClangVersions;
 use crate::cli::{FeedbackInput, LinesChangedOnly, ThreadComments};
 use crate::common_fs::{FileFilter, FileObj};
@@ -124,18 +126,18 @@ impl RestApiClient for GithubApiClient {
         file_filter: &FileFilter,
         lines_changed_only: &LinesChangedOnly,
     ) -> Result<Vec<FileObj>> {
-        if env::var("CI").is_ok_and(|val| val.as_str() == "true")
-            && self.repo.is_some()
-            && self.sha.is_some()
-        {
+        if let (true, Some(repo), Some(sha)) = (
+            env::var("CI").is_ok_and(|val| val.as_str() == "true"),
+            &self.repo,
+            &self.sha,
+        ) {
             // get diff from Github REST API
             let is_pr = self.event_name == "pull_request";
             let pr = self.pull_request.to_string();
-            let sha = self.sha.clone().unwrap();
             let url = self
                 .api_url
                 .join("repos/")?
-                .join(format!("{}/", self.repo.as_ref().unwrap()).as_str())?
+                .join(format!("{}/", repo).as_str())?
                 .join(if is_pr { "pulls/" } else { "commits/" })?
                 .join(if is_pr { pr.as_str() } else { sha.as_str() })?;
             let mut diff_header = HeaderMap::new();
@@ -189,39 +191,29 @@ impl RestApiClient for GithubApiClient {
         feedback_inputs: FeedbackInput,
         clang_versions: ClangVersions,
     ) -> Result<u64> {
-        let tidy_checks_failed = tally_tidy_advice(files);
-        let format_checks_failed = tally_format_advice(files);
+        let tidy_checks_failed = tally_advice(files, Some(CLANG_TIDY_NAME));
+        let format_checks_failed = tally_advice(files, Some(CLANG_FORMAT_NAME));
+        let checks_failed = tally_advice(files, None);
         let mut comment = None;
 
         if feedback_inputs.file_annotations {
-            self.post_annotations(files, feedback_inputs.style.as_str());
+            self.post_annotations(files);
         }
         if feedback_inputs.step_summary {
-            comment = Some(Self::make_comment(
-                files,
-                format_checks_failed,
-                tidy_checks_failed,
-                &clang_versions,
-                None,
-            ));
+            comment = Some(Self::make_comment(files, &clang_versions, None));
             self.post_step_summary(comment.as_ref().unwrap());
         }
         self.set_exit_code(
-            format_checks_failed + tidy_checks_failed,
+            checks_failed,
             Some(format_checks_failed),
             Some(tidy_checks_failed),
         );
+        self.set_check_stats(&CheckStats::new(files));
 
         if feedback_inputs.thread_comments != ThreadComments::Off {
             // post thread comment for PR or push event
             if comment.as_ref().is_some_and(|c| c.len() > 65535) || comment.is_none() {
-                comment = Some(Self::make_comment(
-                    files,
-                    format_checks_failed,
-                    tidy_checks_failed,
-                    &clang_versions,
-                    Some(65535),
-                ));
+                comment = Some(Self::make_comment(files, &clang_versions, Some(65535)));
             }
             if let Some(repo) = &self.repo {
                 let is_pr = self.event_name == "pull_request";
@@ -239,7 +231,7 @@ impl RestApiClient for GithubApiClient {
                     comments_url,
                     &comment.unwrap(),
                     feedback_inputs.no_lgtm,
-                    format_checks_failed + tidy_checks_failed == 0,
+                    checks_failed == 0,
                     feedback_inputs.thread_comments == ThreadComments::Update,
                 )
                 .await?;
@@ -251,7 +243,7 @@ impl RestApiClient for GithubApiClient {
             self.post_review(files, &feedback_inputs, &clang_versions)
                 .await?;
         }
-        Ok(format_checks_failed + tidy_checks_failed)
+        Ok(checks_failed)
     }
 }
 
@@ -273,7 +265,7 @@ mod test {
         clang_tools::{
             clang_format::{FormatAdvice, Replacement},
             clang_tidy::{TidyAdvice, TidyNotification},
-            ClangVersions,
+            ClangVersions, ToolVersion,
         },
         cli::{FeedbackInput, LinesChangedOnly},
         common_fs::{FileFilter, FileObj},
@@ -309,15 +301,21 @@ mod test {
                     diagnostic: String::from("clang-diagnostic-warning"),
                     suggestion: vec![],
                     fixed_lines: vec![],
+                    doc_urls: vec![],
                 }];
-                file.tidy_advice = Some(TidyAdvice {
+                file.advice.push(Arc::new(TidyAdvice {
                     notes,
                     patched: None,
-                });
-                file.format_advice = Some(FormatAdvice {
-                    replacements: vec![Replacement { offset: 0, line: 1 }],
+                }));
+                file.advice.push(Arc::new(FormatAdvice {
+                    replacements: vec![Replacement {
+                        offset: 0,
+                        line: 1,
+                        ..Default::default()
+                    }],
                     patched: None,
-                });
+                    style: String::from("file"),
+                }));
                 files.push(Arc::new(Mutex::new(file)));
             }
         }
@@ -349,8 +347,13 @@ mod test {
             },
         );
         let clang_versions = ClangVersions {
-            format_version: Some("x.y.z".to_string()),
-            tidy_version: Some("x.y.z".to_string()),
+            tools: ["clang-format", "clang-tidy"]
+                .map(|name| ToolVersion {
+                    name: name.to_string(),
+                    version: "x.y.z".to_string(),
+                    config: None,
+                })
+                .to_vec(),
         };
         rest_api_client
             .post_feedback(&files, feedback_inputs, clang_versions)
@@ -388,6 +391,11 @@ mod test {
                 .unwrap();
             assert!(number > 0);
         }
+        assert!(comment.contains("| clang-diagnostic-warning | 65535 | tests/demo/demo.cpp |\n"));
+        assert!(gh_out.contains(concat!(
+            "check-stats={\"checks\":[{\"name\":\"clang-diagnostic-warning\",",
+            "\"count\":65535,\"files\":[\"tests/demo/demo.cpp\"]}]"
+        )));
     }
 
     #[tokio::test]
@@ -397,7 +405,10 @@ mod test {
         assert!(comment.contains(":heavy_check_mark:\nNo problems need attention."));
         assert_eq!(
             gh_out,
-            "checks-failed=0\nformat-checks-failed=0\ntidy-checks-failed=0\n"
+            concat!(
+                "checks-failed=0\nformat-checks-failed=0\ntidy-checks-failed=0\n",
+                "check-stats={\"checks\":[],\"severities\":[]}\n"
+            )
         );
     }
 
@@ -416,7 +427,10 @@ mod test {
         assert!(comment.is_empty());
         assert_eq!(
             gh_out,
-            "checks-failed=0\nformat-checks-failed=0\ntidy-checks-failed=0\n"
+            concat!(
+                "checks-failed=0\nformat-checks-failed=0\ntidy-checks-failed=0\n",
+                "check-stats={\"checks\":[],\"severities\":[]}\n"
+            )
         );
     }
 
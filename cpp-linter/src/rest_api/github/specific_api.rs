@@ -13,7 +13,7 @@ use anyhow::{anyhow, Context, Result};
 use reqwest::{Client, Method, Url};
 
 use crate::{
-    clang_tools::{clang_format::summarize_style, ClangVersions, ReviewComments},
+    clang_tools::{analyzer::Annotation, stats::CheckStats, ClangVersions, ReviewComments},
     cli::{FeedbackInput, LinesChangedOnly},
     common_fs::{FileFilter, FileObj},
     git::parse_diff_from_buf,
@@ -28,6 +28,41 @@ use super::{
     GithubApiClient, RestApiClient,
 };
 
+/// Escape the given `text` for use in the message of a workflow command (ie a
+/// file annotation).
+fn escape_annotation(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape the given `text` for use in a property (ie the `title`) of a workflow
+/// command.
+///
+/// Unlike the message, a property value also ends at a `:` or `,`.
+fn escape_property(text: &str) -> String {
+    escape_annotation(text)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// Describe an `annotation` about the given `file` as a workflow command.
+fn annotation_command(file: &Path, annotation: &Annotation) -> String {
+    format!(
+        "::{severity} file={file},line={line},col={cols},title={title}::{message}",
+        severity = match annotation.severity.as_str() {
+            "error" => "error",
+            "warning" => "warning",
+            _ => "notice",
+        },
+        file = file.to_string_lossy().replace('\\', "/"),
+        line = annotation.line,
+        cols = annotation.cols,
+        title = escape_property(&annotation.title),
+        message = escape_annotation(&annotation.message),
+    )
+}
+
 impl GithubApiClient {
     /// Instantiate a [`GithubApiClient`] object.
     pub fn new() -> Result<Self> {
@@ -160,47 +195,41 @@ impl GithubApiClient {
         }
     }
 
-    /// Post file annotations.
-    pub fn post_annotations(&self, files: &[Arc<Mutex<FileObj>>], style: &str) {
-        let style_guide = summarize_style(style);
+    /// Expose the given `stats` as a JSON string in the `check-stats` output variable.
+    pub fn set_check_stats(&self, stats: &CheckStats) {
+        if let Ok(gh_out) = env::var("GITHUB_OUTPUT") {
+            if let Ok(mut gh_out_file) = OpenOptions::new().append(true).open(gh_out) {
+                if let Err(e) = writeln!(gh_out_file, "check-stats={}", stats.to_json()) {
+                    log::error!("Could not write to GITHUB_OUTPUT file: {}", e);
+                }
+            } else {
+                log::debug!("GITHUB_OUTPUT file could not be opened");
+            }
+        }
+    }
 
-        // iterate over clang-format advice and post annotations
+    /// Post file annotations.
+    pub fn post_annotations(&self, files: &[Arc<Mutex<FileObj>>]) {
         for file in files {
             let file = file.lock().unwrap();
-            if let Some(format_advice) = &file.format_advice {
-                // assemble a list of line numbers
-                let mut lines = Vec::new();
-                for replacement in &format_advice.replacements {
-                    if !lines.contains(&replacement.line) {
-                        lines.push(replacement.line);
-                    }
-                }
-                // post annotation if any applicable lines were formatted
-                if !lines.is_empty() {
-                    println!(
-                            "::notice file={name},title=Run clang-format on {name}::File {name} does not conform to {style_guide} style guidelines. (lines {line_set})",
-                            name = &file.name.to_string_lossy().replace('\\', "/"),
-                            line_set = lines.iter().map(|val| val.to_string()).collect::<Vec<_>>().join(","),
-                        );
+            // iterate over each analyzer's advice and post annotations
+            for advice in &file.advice {
+                for annotation in advice.annotations(&file) {
+                    println!("{}", annotation_command(&file.name, &annotation));
                 }
-            } // end format_advice iterations
+            }
 
-            // iterate over clang-tidy advice and post annotations
-            // The tidy_advice vector is parallel to the files vector; meaning it serves as a file filterer.
-            // lines are already filter as specified to clang-tidy CLI.
-            if let Some(tidy_advice) = &file.tidy_advice {
-                for note in &tidy_advice.notes {
-                    if note.filename == file.name.to_string_lossy().replace('\\', "/") {
-                        println!(
-                            "::{severity} file={file},line={line},title={file}:{line}:{cols} [{diag}]::{info}",
-                            severity = if note.severity == *"note" { "notice".to_string() } else {note.severity.clone()},
-                            file = note.filename,
-                            line = note.line,
-                            cols = note.cols,
-                            diag = note.diagnostic,
-                            info = note.rationale,
-                        );
-                    }
+            // iterate over NOLINT audit findings and post annotations
+            if let Some(findings) = &file.nolint_advice {
+                for finding in findings {
+                    println!(
+                        "::warning file={file},line={line},title=NOLINT audit [{title}]::`{marker}` {problem}",
+                        file = file.name.to_string_lossy().replace('\\', "/"),
+                        line = finding.line,
+                        title = escape_property(&finding.marker),
+                        marker = escape_annotation(&finding.marker),
+                        problem = escape_annotation(finding.problem.describe()),
+                    );
                 }
             }
         }
@@ -431,8 +460,7 @@ impl GithubApiClient {
             let file = file.lock().unwrap();
             file.make_suggestions_from_patch(&mut review_comments, summary_only)?;
         }
-        let has_no_changes =
-            review_comments.full_patch[0].is_empty() && review_comments.full_patch[1].is_empty();
+        let has_no_changes = review_comments.full_patch.values().all(String::is_empty);
         if has_no_changes && feedback_input.no_lgtm {
             log::debug!("Not posting an approved review because `no-lgtm` is true");
             return dismissal.await;
@@ -574,3 +602,57 @@ impl GithubApiClient {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{annotation_command, escape_annotation, escape_property};
+    use crate::{
+        clang_tools::analyzer::{Advice, AnalyzerAdvice, AnalyzerDiagnostic, RelatedLocation},
+        common_fs::FileObj,
+    };
+
+    #[test]
+    fn escape_annotation_message() {
+        assert_eq!(
+            escape_annotation("100% sure\r\nsecond line"),
+            "100%25 sure%0D%0Asecond line"
+        );
+        let file = FileObj::new(Path::new("src/demo.cpp").to_path_buf());
+        let advice = AnalyzerAdvice {
+            tool: String::from("rules"),
+            diagnostics: vec![AnalyzerDiagnostic {
+                line: 3,
+                cols: 5,
+                severity: String::from("error"),
+                check: String::from("house:no-todo,v2"),
+                message: String::from("100% wrong"),
+                related: vec![RelatedLocation {
+                    file: String::from("src/demo.cpp"),
+                    line: 1,
+                    cols: 1,
+                    message: String::from("declared here"),
+                }],
+            }],
+            patched: None,
+        };
+        let annotations = advice.annotations(&file);
+        assert_eq!(
+            annotation_command(&file.name, &annotations[0]),
+            concat!(
+                "::error file=src/demo.cpp,line=3,col=5,title=rules [house%3Ano-todo%2Cv2]",
+                "::100%25 wrong%0A1. src/demo.cpp:1:1: declared here"
+            )
+        );
+    }
+
+    #[test]
+    fn escape_property_value() {
+        assert_eq!(
+            escape_property("NOLINTNEXTLINE(bugprone-narrowing-conversions, cert-*)"),
+            "NOLINTNEXTLINE(bugprone-narrowing-conversions%2C cert-*)"
+        );
+        assert_eq!(escape_property("a: 100%\n"), "a%3A 100%25%0A");
+    }
+}
@@ -5,7 +5,6 @@
 
 use std::fmt::Debug;
 use std::future::Future;
-use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
@@ -17,7 +16,10 @@ use reqwest::{Client, IntoUrl, Method, Request, Response, Url};
 
 // project specific modules
 pub mod github;
-use crate::clang_tools::ClangVersions;
+use crate::clang_tools::{
+    analyzer::tally_advice, nolint::tally_nolint_advice, stats::CheckStats, ClangVersions,
+    ToolVersion,
+};
 use crate::cli::{FeedbackInput, LinesChangedOnly};
 use crate::common_fs::{FileFilter, FileObj};
 
@@ -212,18 +214,20 @@ pub trait RestApiClient {
         lines_changed_only: &LinesChangedOnly,
     ) -> impl Future<Output = Result<Vec<FileObj>>>;
 
-    /// Makes a comment in MarkDown syntax based on the concerns in `format_advice` and
-    /// `tidy_advice` about the given set of `files`.
+    /// Makes a comment in MarkDown syntax based on the concerns in the advice about the
+    /// given set of `files`.
     ///
     /// This method has a default definition and should not need to be redefined by
     /// implementors.
     ///
-    /// Returns the markdown comment as a string as well as the total count of
-    /// `format_checks_failed` and `tidy_checks_failed` (in respective order).
+    /// The comment begins with a table of [`CheckStats`] (sorted by frequency) when
+    /// clang-tidy or any analyzer reported concerns. The table is only included if it
+    /// fits along with the openers of all other sections.
+    ///
+    /// Each tool in the `clang_versions` that reported concerns gets its own section
+    /// (in the order the tools were run).
     fn make_comment(
         files: &[Arc<Mutex<FileObj>>],
-        format_checks_failed: u64,
-        tidy_checks_failed: u64,
         clang_versions: &ClangVersions,
         max_len: Option<u64>,
     ) -> String {
@@ -231,33 +235,35 @@ pub trait RestApiClient {
         let mut remaining_length =
             max_len.unwrap_or(u64::MAX) - comment.len() as u64 - USER_OUTREACH.len() as u64;
 
-        if format_checks_failed > 0 || tidy_checks_failed > 0 {
+        if tally_advice(files, None) > 0 {
             let prompt = ":warning:\nSome files did not pass the configured checks!\n";
             remaining_length -= prompt.len() as u64;
             comment.push_str(prompt);
-            if format_checks_failed > 0 {
-                make_format_comment(
-                    files,
-                    &mut comment,
-                    format_checks_failed,
-                    // tidy_version should be `Some()` value at this point.
-                    clang_versions.tidy_version.as_ref().unwrap(),
-                    &mut remaining_length,
-                );
+            let stats = CheckStats::new(files);
+            if !stats.is_empty() {
+                // the sections' openers take precedence over the table
+                let reserved = clang_versions
+                    .tools
+                    .iter()
+                    .filter_map(|tool| tool_opener(files, tool))
+                    .map(|opener| opener.len() + DETAILS_CLOSER.len())
+                    .sum::<usize>();
+                let table = stats.make_table();
+                if (table.len() + reserved) as u64 <= remaining_length {
+                    remaining_length -= table.len() as u64;
+                    comment.push_str(&table);
+                }
             }
-            if tidy_checks_failed > 0 {
-                make_tidy_comment(
-                    files,
-                    &mut comment,
-                    tidy_checks_failed,
-                    // format_version should be `Some()` value at this point.
-                    clang_versions.format_version.as_ref().unwrap(),
-                    &mut remaining_length,
-                );
+            for tool in &clang_versions.tools {
+                make_tool_comment(files, &mut comment, tool, &mut remaining_length);
             }
         } else {
             comment.push_str(":heavy_check_mark:\nNo problems need attention.");
         }
+        let nolint_findings = tally_nolint_advice(files);
+        if nolint_findings > 0 {
+            make_nolint_comment(files, &mut comment, nolint_findings, &mut remaining_length);
+        }
         comment.push_str(USER_OUTREACH);
         comment
     }
@@ -268,8 +274,8 @@ pub trait RestApiClient {
     /// The given `files` should've been gathered from `get_list_of_changed_files()` or
     /// `list_source_files()`.
     ///
-    /// The `format_advice` and `tidy_advice` should be a result of parsing output from
-    /// clang-format and clang-tidy (see `capture_clang_tools_output()`).
+    /// The advice about the `files` should be a result of running the analyzers (see
+    /// `capture_clang_tools_output()`).
     ///
     /// All other parameters correspond to CLI arguments.
     fn post_feedback(
@@ -317,90 +323,124 @@ pub trait RestApiClient {
     }
 }
 
-fn make_format_comment(
+/// Describe the configuration sources used by a clang tool (if any are known).
+fn describe_config_used(config_used: Option<&String>) -> String {
+    config_used
+        .map(|config| format!("Configured by {config}\n\n"))
+        .unwrap_or_default()
+}
+
+/// The closing tag of each collapsible section in the thread comment.
+const DETAILS_CLOSER: &str = "\n</details>";
+
+/// Make the opener of the section about the concerns that the given `tool` reported
+/// about the `files`.
+///
+/// Returns [`None`] if the `tool` reported no concerns.
+fn tool_opener(files: &[Arc<Mutex<FileObj>>], tool: &ToolVersion) -> Option<String> {
+    let mut total = 0;
+    let mut tool_advice = None;
+    for file in files {
+        let file = file.lock().unwrap();
+        for advice in file
+            .advice
+            .iter()
+            .filter(|a| a.get_tool_name() == tool.name)
+        {
+            total += advice.concerns(&file);
+            tool_advice.get_or_insert_with(|| Arc::clone(advice));
+        }
+    }
+    let advice = tool_advice.filter(|_| total > 0)?;
+    Some(format!(
+        "\n<details><summary>{name} (v{version}) reports: {summary}</summary>\n\n{config}",
+        name = tool.name,
+        version = tool.version,
+        summary = advice.summarize(total),
+        config = describe_config_used(tool.config.as_ref()),
+    ))
+}
+
+fn make_tool_comment(
     files: &[Arc<Mutex<FileObj>>],
     comment: &mut String,
-    format_checks_failed: u64,
-    version_used: &String,
+    tool: &ToolVersion,
     remaining_length: &mut u64,
 ) {
-    let opener = format!(
-        "\n<details><summary>clang-format (v{version_used}) reports: <strong>{format_checks_failed} file(s) not formatted</strong></summary>\n\n",
-    );
-    let closer = String::from("\n</details>");
-    let mut format_comment = String::new();
-    *remaining_length -= opener.len() as u64 + closer.len() as u64;
+    let Some(opener) = tool_opener(files, tool) else {
+        return;
+    };
+    let closer = DETAILS_CLOSER;
+    let reserved = opener.len() as u64 + closer.len() as u64;
+    if reserved > *remaining_length {
+        // earlier sections used the whole length budget
+        return;
+    }
+    let mut tool_comment = String::new();
+    *remaining_length = remaining_length.saturating_sub(reserved);
     for file in files {
         let file = file.lock().unwrap();
-        if let Some(format_advice) = &file.format_advice {
-            if !format_advice.replacements.is_empty() && *remaining_length > 0 {
-                let note = format!("- {}\n", file.name.to_string_lossy().replace('\\', "/"));
-                if (note.len() as u64) < *remaining_length {
-                    format_comment.push_str(&note.to_string());
-                    *remaining_length -= note.len() as u64;
-                }
-            }
+        for advice in file
+            .advice
+            .iter()
+            .filter(|a| a.get_tool_name() == tool.name)
+        {
+            tool_comment.push_str(&advice.describe(&file, remaining_length));
         }
     }
     comment.push_str(&opener);
-    comment.push_str(&format_comment);
-    comment.push_str(&closer);
+    comment.push_str(&tool_comment);
+    comment.push_str(closer);
 }
 
-fn make_tidy_comment(
+fn make_nolint_comment(
     files: &[Arc<Mutex<FileObj>>],
     comment: &mut String,
-    tidy_checks_failed: u64,
-    version_used: &String,
+    nolint_findings: u64,
     remaining_length: &mut u64,
 ) {
     let opener = format!(
-        "\n<details><summary>clang-tidy (v{version_used}) reports: {tidy_checks_failed}<strong> concern(s)</strong></summary>\n\n"
+        "\n<details><summary>NOLINT audit reports: <strong>{nolint_findings} suppression concern(s)</strong></summary>\n\n"
     );
-    let closer = String::from("\n</details>");
-    let mut tidy_comment = String::new();
-    *remaining_length -= opener.len() as u64 + closer.len() as u64;
+    let closer = DETAILS_CLOSER;
+    let reserved = opener.len() as u64 + closer.len() as u64;
+    if reserved > *remaining_length {
+        // earlier sections used the whole length budget
+        return;
+    }
+    let mut nolint_comment = String::new();
+    *remaining_length = remaining_length.saturating_sub(reserved);
     for file in files {
         let file = file.lock().unwrap();
-        if let Some(tidy_advice) = &file.tidy_advice {
-            for tidy_note in &tidy_advice.notes {
-                let file_path = PathBuf::from(&tidy_note.filename);
-                if file_path == file.name {
-                    let mut tmp_note = format!("- {}\n\n", tidy_note.filename);
-                    tmp_note.push_str(&format!(
-                        "   <strong>{filename}:{line}:{cols}:</strong> {severity}: [{diagnostic}]\n   > {rationale}\n{concerned_code}",
-                        filename = tidy_note.filename,
-                        line = tidy_note.line,
-                        cols = tidy_note.cols,
-                        severity = tidy_note.severity,
-                        diagnostic = tidy_note.diagnostic_link(),
-                        rationale = tidy_note.rationale,
-                        concerned_code = if tidy_note.suggestion.is_empty() {String::from("")} else {
-                            format!("\n   ```{ext}\n   {suggestion}\n   ```\n",
-                                ext = file_path.extension().unwrap_or_default().to_string_lossy(),
-                                suggestion = tidy_note.suggestion.join("\n   "),
-                            ).to_string()
-                        },
-                    ).to_string());
-
-                    if (tmp_note.len() as u64) < *remaining_length {
-                        tidy_comment.push_str(&tmp_note);
-                        *remaining_length -= tmp_note.len() as u64;
-                    }
+        if let Some(findings) = &file.nolint_advice {
+            for finding in findings {
+                let note = format!(
+                    "- {name}:{line}: `{marker}` {problem}\n",
+                    name = file.name.to_string_lossy().replace('\\', "/"),
+                    line = finding.line,
+                    marker = finding.marker,
+                    problem = finding.problem.describe(),
+                );
+                if (note.len() as u64) < *remaining_length {
+                    nolint_comment.push_str(&note);
+                    *remaining_length -= note.len() as u64;
                 }
             }
         }
     }
     comment.push_str(&opener);
-    comment.push_str(&tidy_comment);
-    comment.push_str(&closer);
+    comment.push_str(&nolint_comment);
+    comment.push_str(closer);
 }
 
 /// This module tests the silent errors' debug logs
 /// from `try_next_page()` and `send_api_request()` functions.
 #[cfg(test)]
 mod test {
-    use std::sync::{Arc, Mutex};
+    use std::{
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
 
     use anyhow::{anyhow, Result};
     use chrono::Utc;
@@ -413,13 +453,18 @@ mod test {
 
     use crate::cli::LinesChangedOnly;
     use crate::{
-        clang_tools::ClangVersions,
+        clang_tools::{
+            analyzer::{AnalyzerAdvice, AnalyzerDiagnostic},
+            clang_format::{FormatAdvice, Replacement},
+            nolint::{NolintFinding, NolintProblem},
+            ClangVersions, ToolVersion,
+        },
         cli::FeedbackInput,
         common_fs::{FileFilter, FileObj},
         logger,
     };
 
-    use super::{RestApiClient, RestApiRateLimitHeaders};
+    use super::{make_nolint_comment, make_tool_comment, RestApiClient, RestApiRateLimitHeaders};
 
     /// A dummy struct to impl RestApiClient
     #[derive(Default)]
@@ -482,6 +527,108 @@ mod test {
         dummy.end_log_group();
     }
 
+    // ************************************************* comment length budget tests
+
+    #[test]
+    fn nolint_comment_budget() {
+        let mut file = FileObj::new(PathBuf::from("src/demo.cpp"));
+        file.nolint_advice = Some(vec![NolintFinding {
+            line: 1,
+            marker: String::from("NOLINT"),
+            problem: NolintProblem::NoCheckName,
+        }]);
+        let files = vec![Arc::new(Mutex::new(file))];
+
+        // the opener does not fit, so the section is skipped
+        let mut comment = String::new();
+        let mut remaining_length = 10;
+        make_nolint_comment(&files, &mut comment, 1, &mut remaining_length);
+        assert!(comment.is_empty());
+        assert_eq!(remaining_length, 10);
+
+        let mut remaining_length = 1000;
+        make_nolint_comment(&files, &mut comment, 1, &mut remaining_length);
+        assert!(comment.contains("- src/demo.cpp:1: `NOLINT`"));
+        assert_eq!(remaining_length, 1000 - comment.len() as u64);
+    }
+
+    #[test]
+    fn analyzer_comment_budget() {
+        let mut file = FileObj::new(PathBuf::from("src/demo.cpp"));
+        file.advice.push(Arc::new(AnalyzerAdvice {
+            tool: String::from("cppcheck"),
+            diagnostics: vec![AnalyzerDiagnostic {
+                line: 2,
+                cols: 1,
+                severity: String::from("error"),
+                check: String::from("nullPointer"),
+                message: String::from("Null pointer dereference"),
+                related: vec![],
+            }],
+            patched: None,
+        }));
+        let files = vec![Arc::new(Mutex::new(file))];
+        let tool = ToolVersion {
+            name: String::from("cppcheck"),
+            version: String::from("2.13"),
+            config: None,
+        };
+
+        // the opener does not fit, so the section is skipped
+        let mut comment = String::new();
+        let mut remaining_length = 10;
+        make_tool_comment(&files, &mut comment, &tool, &mut remaining_length);
+        assert!(comment.is_empty());
+        assert_eq!(remaining_length, 10);
+
+        // only the opener and closer fit
+        let mut remaining_length = 120;
+        make_tool_comment(&files, &mut comment, &tool, &mut remaining_length);
+        assert!(comment.contains("reports: 1<strong> concern(s)</strong>"));
+        assert!(!comment.contains("nullPointer"));
+        assert_eq!(remaining_length, 120 - comment.len() as u64);
+
+        let mut comment = String::new();
+        let mut remaining_length = 1000;
+        make_tool_comment(&files, &mut comment, &tool, &mut remaining_length);
+        assert!(comment.contains("- <strong>src/demo.cpp:2:1:</strong> error: [nullPointer]"));
+        assert_eq!(remaining_length, 1000 - comment.len() as u64);
+    }
+
+    #[test]
+    fn format_comment_budget() {
+        let mut file = FileObj::new(PathBuf::from("src/demo.cpp"));
+        file.advice.push(Arc::new(FormatAdvice {
+            replacements: (1..=50)
+                .map(|line| Replacement {
+                    offset: 0,
+                    line,
+                    cols: 1,
+                    end_line: line,
+                    end_cols: 1,
+                    text: String::from("`"),
+                    ..Default::default()
+                })
+                .collect(),
+            patched: None,
+            style: String::from("llvm"),
+        }));
+        let files = vec![Arc::new(Mutex::new(file))];
+        let tool = ToolVersion {
+            name: String::from("clang-format"),
+            version: String::from("x.y.z"),
+            config: None,
+        };
+        let mut comment = String::new();
+        let mut remaining_length = 400;
+        make_tool_comment(&files, &mut comment, &tool, &mut remaining_length);
+        // the file is listed even if not all its replacements fit
+        assert!(comment.contains("- src/demo.cpp\n"));
+        assert!(comment.contains("  - `` 1:1 insert \"`\" ``\n"));
+        assert!(comment.contains(" more\n"));
+        assert!(comment.len() <= 400);
+    }
+
     // ************************************************* try_next_page() tests
 
     #[test]
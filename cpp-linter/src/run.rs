@@ -8,15 +8,25 @@ use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 // non-std crates
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use log::{set_max_level, LevelFilter};
 #[cfg(feature = "openssl-vendored")]
 use openssl_probe;
 
 // project specific modules/crates
-use crate::clang_tools::capture_clang_tools_output;
-use crate::cli::{get_arg_parser, ClangParams, Cli, FeedbackInput, LinesChangedOnly};
-use crate::common_fs::FileFilter;
+use crate::clang_tools::{
+    capture_clang_tools_output,
+    compilation_db::{
+        dedupe_units, discover_database, infer_flags, load_database, validate_database,
+        write_database, CompilationUnit,
+    },
+    config_source::explain_config,
+    include_cleaner::INCLUDE_CLEANER_NAME,
+    include_graph::is_header,
+};
+use crate::cli::{get_arg_parser, ClangParams, Cli, ConfigFile, FeedbackInput, LinesChangedOnly};
+use crate::common_fs::{normalize_path, FileFilter, FileObj, IgnoreFile, IGNORE_FILE_NAME};
+use crate::git::{discover_repo, parse_diff_from_file};
 use crate::logger;
 use crate::rest_api::{github::GithubApiClient, RestApiClient};
 
@@ -51,7 +61,7 @@ pub async fn run_main(args: Vec<String>) -> Result<()> {
 
     let arg_parser = get_arg_parser();
     let args = arg_parser.get_matches_from(args);
-    let cli = Cli::from(&args);
+    let mut cli = Cli::from(&args);
 
     if args.subcommand_matches("version").is_some() {
         println!("cpp-linter v{}", VERSION);
@@ -66,22 +76,91 @@ pub async fn run_main(args: Vec<String>) -> Result<()> {
         return Err(anyhow!("Clang version not specified."));
     }
 
+    if let Some(diff_file) = &mut cli.diff_file {
+        // resolve relative to the working directory before changing to the repo-root
+        if diff_file.as_os_str() != "-" && diff_file.is_relative() {
+            *diff_file = env::current_dir()?.join(&diff_file);
+        }
+    }
+    if let Some(config) = &mut cli.config {
+        if config.is_relative() {
+            *config = env::current_dir()?.join(&config);
+        }
+    }
+
+    // resolve relative to the working directory before changing to the repo-root
+    let explained_file = match args.subcommand_matches("config") {
+        Some(sub_args) => Some(
+            env::current_dir()?.join(
+                sub_args
+                    .get_one::<String>("file")
+                    .ok_or(anyhow!("No file given to the `config` sub-command"))?,
+            ),
+        ),
+        None => None,
+    };
+
     if cli.repo_root != "." {
         env::set_current_dir(Path::new(&cli.repo_root))
             .unwrap_or_else(|_| panic!("'{}' is inaccessible or does not exist", cli.repo_root));
     }
 
+    let config = if let Some(path) = &cli.config {
+        let config = ConfigFile::load(path)?.ok_or(anyhow!(
+            "No `[tool.cpp-linter]` table found in {}",
+            path.display()
+        ))?;
+        Some((path.clone(), config))
+    } else {
+        ConfigFile::discover(Path::new("."))?
+    };
+    if let Some((_, config)) = &config {
+        config.apply(&mut cli, &args)?;
+    }
+
+    if let Some(file) = &explained_file {
+        let root = env::current_dir()?;
+        let file = normalize_path(file);
+        let file = file.strip_prefix(&root).unwrap_or(&file);
+        // use the same compilation database (and inferred flags) as an analysis would
+        if cli.database.is_none() {
+            cli.database = discover_database(Path::new("."));
+        }
+        let mut clang_params = ClangParams::from(&cli);
+        if let Some(db_path) = &clang_params.database {
+            let units = load_database(db_path)?;
+            if let Some((_, flags)) = infer_flags(file, &units, &root) {
+                clang_params
+                    .inferred_flags
+                    .insert(file.to_path_buf(), flags);
+            }
+        }
+        print!(
+            "{}",
+            explain_config(file, &root, &clang_params, &cli.version)?
+        );
+        return Ok(());
+    }
+
     let rest_api_client = GithubApiClient::new()?;
     set_max_level(if cli.verbosity || rest_api_client.debug_enabled {
         LevelFilter::Debug
     } else {
         LevelFilter::Info
     });
+    if let Some((path, _)) = &config {
+        log::info!("Using configuration from {}", path.display());
+    }
     log::info!("Processing event {}", rest_api_client.event_name);
     let is_pr = rest_api_client.event_name == "pull_request";
 
     let mut file_filter = FileFilter::new(&cli.ignore, cli.extensions.clone());
     file_filter.parse_submodules();
+    let ignore_file = IgnoreFile::load(Path::new(IGNORE_FILE_NAME))?;
+    if let Some(ignore_file) = &ignore_file {
+        log::info!("Using ignore patterns from {IGNORE_FILE_NAME}");
+        file_filter.add_ignore_patterns(&ignore_file.general)?;
+    }
     if let Some(files) = &cli.not_ignored {
         file_filter.not_ignored.extend(files.clone());
     }
@@ -100,19 +179,95 @@ pub async fn run_main(args: Vec<String>) -> Result<()> {
     }
 
     rest_api_client.start_log_group(String::from("Get list of specified source files"));
-    let files =
+    if cli.database.is_none() {
+        cli.database = discover_database(Path::new("."));
+        if let Some(db_path) = &cli.database {
+            log::info!(
+                "Using the compilation database found in {}",
+                db_path.to_string_lossy()
+            );
+        }
+    }
+    // A deduplicated copy of the database (if needed) that clang-tidy shall use.
+    // This must outlive the analysis, so it is bound to the scope of this function.
+    let mut deduped_database = None;
+    let database_json = match &cli.database {
+        Some(db_path) => {
+            let units = load_database(db_path)?;
+            validate_database(&units, db_path)?;
+            let total = units.len();
+            let units = dedupe_units(units, cli.database_config.as_deref());
+            if units.len() < total {
+                let tmp_dir = tempfile::tempdir()
+                    .with_context(|| "Failed to create a directory for the compilation database")?;
+                write_database(&units, tmp_dir.path())?;
+                log::info!(
+                    "Using {} of {total} compilation database entries (one per file)",
+                    units.len()
+                );
+                deduped_database = Some(tmp_dir);
+            }
+            Some(units)
+        }
+        None if cli.files_from_database => {
+            return Err(anyhow!(
+                "`--files-from-database` requires a compilation database (see `--database`)"
+            ));
+        }
+        None => None,
+    };
+    let database_files = match (&database_json, cli.files_from_database) {
+        (Some(units), true) => Some(file_filter.list_database_files(units, &env::current_dir()?)),
+        _ => None,
+    };
+    let (files, diff_available) =
         if !matches!(cli.lines_changed_only, LinesChangedOnly::Off) || cli.files_changed_only {
             // parse_diff(github_rest_api_payload)
-            rest_api_client
-                .get_list_of_changed_files(&file_filter, &cli.lines_changed_only)
-                .await?
+            let changed_files = get_changed_files(
+                &cli,
+                &rest_api_client,
+                &file_filter,
+                &cli.lines_changed_only,
+            )
+            .await?;
+            let changed_files =
+                if let (Some(database_files), Some(units)) = (&database_files, &database_json) {
+                    let root = env::current_dir()?;
+                    changed_files
+                        .into_iter()
+                        .filter(|file| is_in_database(file, database_files, units, &root))
+                        .collect()
+                } else {
+                    changed_files
+                };
+            (changed_files, true)
         } else {
             // walk the folder and look for files with specified extensions according to ignore values.
-            let mut all_files = file_filter.list_source_files(".")?;
-            if is_pr && (cli.tidy_review || cli.format_review) {
-                let changed_files = rest_api_client
-                    .get_list_of_changed_files(&file_filter, &LinesChangedOnly::Off)
-                    .await?;
+            let mut all_files = if let Some(database_files) = database_files {
+                database_files
+            } else if cli.tracked_only {
+                let repo = discover_repo(".").with_context(|| {
+                    "Please ensure the repository is checked out before using `--tracked-only`."
+                })?;
+                file_filter.list_tracked_files(&repo, &env::current_dir()?)?
+            } else {
+                file_filter.list_source_files(".")?
+            };
+            // path-scoped overrides may enable `lines-changed-only` for some files
+            let overrides_need_diff = cli.overrides.iter().any(|o| {
+                o.lines_changed_only
+                    .as_ref()
+                    .is_some_and(|l| *l != LinesChangedOnly::Off)
+            });
+            // clang-include-cleaner only analyzes changed files
+            let analyzers_need_diff = cli.analyzers.iter().any(|a| a == INCLUDE_CLEANER_NAME);
+            let needs_diff = (is_pr && (cli.tidy_review || cli.format_review))
+                || overrides_need_diff
+                || analyzers_need_diff;
+            if needs_diff {
+                let changed_files =
+                    get_changed_files(&cli, &rest_api_client, &file_filter, &LinesChangedOnly::Off)
+                        .await?;
                 for changed_file in changed_files {
                     for file in &mut all_files {
                         if changed_file.name == file.name {
@@ -123,7 +278,7 @@ pub async fn run_main(args: Vec<String>) -> Result<()> {
                     }
                 }
             }
-            all_files
+            (all_files, needs_diff)
         };
     let mut arc_files = vec![];
     log::info!("Giving attention to the following files:");
@@ -134,6 +289,31 @@ pub async fn run_main(args: Vec<String>) -> Result<()> {
     rest_api_client.end_log_group();
 
     let mut clang_params = ClangParams::from(&cli);
+    clang_params.database_json = database_json;
+    clang_params.diff_available = diff_available;
+    if let Some(tmp_dir) = &deduped_database {
+        clang_params.database = Some(tmp_dir.path().to_path_buf());
+    }
+    if let Some(ignore_file) = &ignore_file {
+        if !ignore_file.tidy.is_empty() {
+            clang_params
+                .tidy_filter
+                .get_or_insert_with(|| FileFilter::new(&[], cli.extensions.clone()))
+                .add_ignore_patterns(&ignore_file.tidy)?;
+        }
+        if !ignore_file.format.is_empty() {
+            clang_params
+                .format_filter
+                .get_or_insert_with(|| FileFilter::new(&[], cli.extensions.clone()))
+                .add_ignore_patterns(&ignore_file.format)?;
+        }
+        if !ignore_file.analyzers.is_empty() {
+            clang_params
+                .analyzer_filter
+                .get_or_insert_with(|| FileFilter::new(&[], cli.extensions.clone()))
+                .add_ignore_patterns(&ignore_file.analyzers)?;
+        }
+    }
     clang_params.format_review &= is_pr;
     clang_params.tidy_review &= is_pr;
     let user_inputs = FeedbackInput::from(&cli);
@@ -155,10 +335,86 @@ pub async fn run_main(args: Vec<String>) -> Result<()> {
     Ok(())
 }
 
+/// Get the list of changed files.
+///
+/// If `--diff-file` was specified, then the diff is parsed from that file (or stdin).
+/// Otherwise, the changes are fetched with the given `rest_api_client`.
+async fn get_changed_files(
+    cli: &Cli,
+    rest_api_client: &impl RestApiClient,
+    file_filter: &FileFilter,
+    lines_changed_only: &LinesChangedOnly,
+) -> Result<Vec<FileObj>> {
+    if let Some(diff_file) = &cli.diff_file {
+        log::debug!("Getting file changes from {}", diff_file.display());
+        parse_diff_from_file(diff_file, file_filter, lines_changed_only)
+    } else {
+        rest_api_client
+            .get_list_of_changed_files(file_filter, lines_changed_only)
+            .await
+    }
+}
+
+/// Should the changed `file` be analyzed when using `--files-from-database`?
+///
+/// Sources listed in the `database_files` are kept. Headers are kept because they are
+/// analyzed through an including translation unit, and other sources are kept if
+/// their compile flags can be inferred from the database `units` (see
+/// [`infer_flags()`]). Otherwise, a warning is logged.
+fn is_in_database(
+    file: &FileObj,
+    database_files: &[FileObj],
+    units: &[CompilationUnit],
+    root: &Path,
+) -> bool {
+    if database_files.iter().any(|f| f.name == file.name)
+        || is_header(&file.name)
+        || infer_flags(&file.name, units, root).is_some()
+    {
+        return true;
+    }
+    log::warn!(
+        "{} has no entry in the compilation database; skipping it",
+        file.name.to_string_lossy()
+    );
+    false
+}
+
 #[cfg(test)]
 mod test {
-    use super::run_main;
-    use std::env;
+    use super::{is_in_database, run_main};
+    use crate::{clang_tools::compilation_db::CompilationUnit, common_fs::FileObj};
+    use std::{
+        env,
+        path::{Path, PathBuf},
+    };
+    use tempfile::tempdir;
+
+    #[test]
+    fn changed_files_from_database() {
+        let tmp = tempdir().unwrap();
+        let units = vec![CompilationUnit {
+            directory: tmp.path().join("build").to_string_lossy().to_string(),
+            file: String::from("../src/demo.cpp"),
+            command: Some(String::from("c++ -DDEMO -c ../src/demo.cpp")),
+            ..Default::default()
+        }];
+        let database_files = vec![FileObj::new(PathBuf::from("src/demo.cpp"))];
+        let is_kept = |name: &str| {
+            is_in_database(
+                &FileObj::new(PathBuf::from(name)),
+                &database_files,
+                &units,
+                tmp.path(),
+            )
+        };
+        assert!(is_kept("src/demo.cpp"));
+        // headers are analyzed through an including translation unit
+        assert!(is_kept("src/demo.hpp"));
+        // flags can be inferred from a sibling entry
+        assert!(is_kept("src/other.cpp"));
+        assert!(!is_kept("tools/gen.cpp"));
+    }
 
     #[tokio::test]
     async fn normal() {
@@ -240,4 +496,20 @@ mod test {
         .await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn diff_file() {
+        env::remove_var("GITHUB_OUTPUT"); // avoid writing to GH_OUT in parallel-running tests
+        let diff_file =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/comment_test_assets/patch.diff");
+        let result = run_main(vec![
+            "cpp-linter".to_string(),
+            "--style".to_string(),
+            String::new(),
+            "--tidy-checks=-*".to_string(),
+            format!("--diff-file={}", diff_file.to_string_lossy()),
+        ])
+        .await;
+        assert!(result.is_ok());
+    }
 }